@@ -6,7 +6,7 @@ fn main() {
         ..Default::default()
     };
     let playbook_opts = AnsiblePlaybookOptions {
-        inventory: "127.0.0.1,".into(),
+        inventory: vec!["127.0.0.1,".into()],
         ..Default::default()
     };
 
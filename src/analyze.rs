@@ -0,0 +1,522 @@
+use crate::inventory::{HostPattern, Inventory};
+use crate::model::{playbook_json, Play, Playbook, RoleRef, Task, TaskEntry, RAW_PARAMS};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// modules importing tasks and roles when the playbook is parsed
+const IMPORT_TASKS: [&str; 2] = ["import_tasks", "ansible.builtin.import_tasks"];
+const IMPORT_ROLE: [&str; 2] = ["import_role", "ansible.builtin.import_role"];
+const IMPORT_PLAYBOOK: &str = "ansible.builtin.import_playbook";
+
+// modules including tasks and roles when the play runs
+const DYNAMIC_INCLUDES: [&str; 6] = [
+    "include",
+    "ansible.builtin.include",
+    "include_tasks",
+    "ansible.builtin.include_tasks",
+    "include_role",
+    "ansible.builtin.include_role",
+];
+
+// imports nested deeper are taken for an import loop
+const MAX_IMPORT_DEPTH: usize = 64;
+
+// extensions ansible tries on the task files of roles
+const TASK_FILE_EXTENSIONS: [&str; 3] = [".yml", ".yaml", ""];
+
+/// Task of a play, as found by `PlaybookAnalyzer`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnalyzedTask {
+    pub name: String,                 // name of the task, its module when it has none
+    pub module: String,               // module run by the task
+    pub role: String,                 // role the task belongs to, empty for the tasks of the play
+    pub tags: Vec<String>,            // tags of the task and the ones it inherits
+    pub file: String,                 // file the task is defined in
+    pub unresolvable: Option<String>, // why the tasks of an include cannot be listed, such as a dynamic include
+}
+
+/// Play of a playbook, as found by `PlaybookAnalyzer`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnalyzedPlay {
+    pub name: String,                 // name of the play
+    pub hosts: String,                // pattern of the hosts the play targets
+    pub file: String,                 // playbook the play is defined in
+    pub tags: Vec<String>,            // tags of the play and of the playbook imports it comes from
+    pub tasks: Vec<AnalyzedTask>, // tasks in the order they run: pre_tasks, roles, tasks, then post_tasks
+    pub unresolvable: Option<String>, // why an imported playbook cannot be read, such as a templated path
+}
+
+/// Plays and tasks of a playbook with its imports expanded
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaybookAnalysis {
+    pub plays: Vec<AnalyzedPlay>, // plays in the order they run
+}
+
+/// Lists the plays, tasks and tags of playbooks from their YAML, the way
+/// `--list-tasks` and `--list-tags` do but without running ansible.
+/// Imports and roles are expanded, dynamic includes are listed as tasks
+/// whose content is unresolvable. As with ansible, only the tasks of the
+/// `block` section of blocks are listed, the `rescue` and `always` ones
+/// being omitted.
+#[derive(Debug, Clone, Default)]
+pub struct PlaybookAnalyzer {
+    pub roles_path: Vec<String>, // directories roles are searched in after the `roles/` directory of the playbook, as `ANSIBLE_ROLES_PATH`
+}
+
+// where the tasks being listed come from
+#[derive(Clone)]
+struct TaskContext {
+    playbook_dir: PathBuf, // directory of the playbook, roles being searched from it
+    directory: PathBuf,    // directory relative task imports are resolved from
+    file: String,          // file holding the tasks
+    role: String,          // role the tasks belong to
+    tags: Vec<String>,     // tags inherited by the tasks
+    depth: usize,          // number of nested imports
+}
+
+// roles already applied to the play, with their parameters
+type AppliedRoles = Vec<(PathBuf, String, Value)>;
+
+fn push_tags(tags: &mut Vec<String>, other: &[String]) {
+    for tag in other {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+}
+
+fn with_tags(tags: &[String], other: &[String]) -> Vec<String> {
+    let mut tags = tags.to_vec();
+    push_tags(&mut tags, other);
+    tags
+}
+
+fn is_templated(value: &str) -> bool {
+    value.contains("{{") || value.contains("{%")
+}
+
+// module of a task, `action` and `local_action` naming it in their value
+fn task_module(task: &Task) -> String {
+    if !task.module.is_empty() {
+        return task.module.clone();
+    }
+
+    match task
+        .keywords
+        .get("action")
+        .or_else(|| task.keywords.get("local_action"))
+    {
+        Some(Value::String(action)) => action.split_whitespace().next().unwrap_or("").into(),
+        Some(Value::Object(action)) => match action.get("module") {
+            Some(Value::String(module)) => module.clone(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    }
+}
+
+// string argument of a task, free-form arguments being read for `key`
+fn task_arg(task: &Task, key: &str) -> String {
+    match task.args.get(key).or_else(|| task.args.get(RAW_PARAMS)) {
+        Some(Value::String(value)) => value.clone(),
+        _ => String::new(),
+    }
+}
+
+fn read_yaml(path: &Path) -> Result<Value, Box<dyn Error>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => return Err(format!("{}: {}", path.display(), err).into()),
+    };
+
+    match serde_yaml::from_str(&content) {
+        Ok(value) => Ok(playbook_json(value)),
+        Err(err) => Err(format!("{}: {}", path.display(), err).into()),
+    }
+}
+
+fn read_tasks(path: &Path) -> Result<Vec<TaskEntry>, Box<dyn Error>> {
+    match serde_json::from_value::<Option<Vec<TaskEntry>>>(read_yaml(path)?) {
+        Ok(tasks) => Ok(tasks.unwrap_or_default()),
+        Err(err) => Err(format!("{}: {}", path.display(), err).into()),
+    }
+}
+
+// task file of a role, `None` when missing
+fn role_task_file(role_dir: &Path, tasks_from: &str) -> Option<PathBuf> {
+    TASK_FILE_EXTENSIONS
+        .iter()
+        .map(|ext| {
+            role_dir
+                .join("tasks")
+                .join(format!("{}{}", tasks_from, ext))
+        })
+        .find(|path| path.is_file())
+}
+
+impl AnalyzedPlay {
+    /// Returns the tags of the tasks of the play, inherited ones included, as
+    /// `--list-tags` shows them
+    pub fn task_tags(&self) -> BTreeSet<String> {
+        self.tasks
+            .iter()
+            .flat_map(|task| task.tags.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the hosts of `inventory` the play targets, restricted to the
+    /// ones `limit` selects when it is not empty, as `--list-hosts` shows them
+    pub fn resolve_hosts(
+        &self,
+        inventory: &Inventory,
+        limit: &str,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        if is_templated(&self.hosts) {
+            return Err(format!(
+                "(analyze::resolve_hosts) pattern '{}' is only known when the play runs",
+                self.hosts
+            )
+            .into());
+        }
+
+        let resolve = || -> Result<Vec<String>, Box<dyn Error>> {
+            let mut hosts = HostPattern::parse(&self.hosts)?.resolve(inventory)?;
+            if !limit.trim().is_empty() {
+                let limited = inventory.resolve_limit(limit)?;
+                hosts.retain(|host| limited.contains(host));
+            }
+            Ok(hosts)
+        };
+
+        resolve().map_err(|err| format!("(analyze::resolve_hosts) {}", err).into())
+    }
+}
+
+impl PlaybookAnalysis {
+    /// Returns the tags of every task of the playbook
+    pub fn tags(&self) -> BTreeSet<String> {
+        self.plays
+            .iter()
+            .flat_map(AnalyzedPlay::task_tags)
+            .collect()
+    }
+
+    /// Returns the tasks of the playbook whose content cannot be known
+    /// without running it
+    pub fn unresolvable_tasks(&self) -> Vec<&AnalyzedTask> {
+        self.plays
+            .iter()
+            .flat_map(|play| play.tasks.iter())
+            .filter(|task| task.unresolvable.is_some())
+            .collect()
+    }
+}
+
+impl PlaybookAnalyzer {
+    /// Analyze the playbook file `playbook`, expanding its imports
+    pub fn analyze(&self, playbook: impl AsRef<Path>) -> Result<PlaybookAnalysis, Box<dyn Error>> {
+        let mut analysis = PlaybookAnalysis::default();
+        match self.analyze_file(playbook.as_ref(), &[], 0, &mut analysis.plays) {
+            Ok(()) => Ok(analysis),
+            Err(err) => Err(format!("(analyze::analyze) {}", err).into()),
+        }
+    }
+
+    fn analyze_file(
+        &self,
+        path: &Path,
+        tags: &[String],
+        depth: usize,
+        plays: &mut Vec<AnalyzedPlay>,
+    ) -> Result<(), Box<dyn Error>> {
+        if depth > MAX_IMPORT_DEPTH {
+            return Err(format!("{}: too many nested imports", path.display()).into());
+        }
+
+        let playbook = Playbook::from_file(path)?;
+        let directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let file = path.to_string_lossy().to_string();
+
+        for play in &playbook.plays {
+            let import = match play.keywords.get(IMPORT_PLAYBOOK) {
+                Some(Value::String(import)) => import.clone(),
+                _ => play.import_playbook.clone(),
+            };
+            let play_tags = with_tags(tags, &play.tags);
+
+            if import.is_empty() {
+                plays.push(self.analyze_play(play, &directory, &file, play_tags)?);
+            } else if is_templated(&import) {
+                plays.push(AnalyzedPlay {
+                    name: import,
+                    file: file.clone(),
+                    tags: play_tags,
+                    unresolvable: Some("templated playbook import".into()),
+                    ..Default::default()
+                });
+            } else {
+                self.analyze_file(&directory.join(import), &play_tags, depth + 1, plays)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn analyze_play(
+        &self,
+        play: &Play,
+        directory: &Path,
+        file: &str,
+        tags: Vec<String>,
+    ) -> Result<AnalyzedPlay, Box<dyn Error>> {
+        let context = TaskContext {
+            playbook_dir: directory.to_path_buf(),
+            directory: directory.to_path_buf(),
+            file: file.to_string(),
+            role: String::new(),
+            tags: tags.clone(),
+            depth: 0,
+        };
+
+        let mut tasks = vec![];
+        let mut applied = vec![];
+        self.list_entries(&play.pre_tasks, &context, &mut tasks, &mut applied)?;
+        for role in &play.roles {
+            self.apply_role(role, "main", true, &context, &mut tasks, &mut applied)?;
+        }
+        self.list_entries(&play.tasks, &context, &mut tasks, &mut applied)?;
+        self.list_entries(&play.post_tasks, &context, &mut tasks, &mut applied)?;
+
+        Ok(AnalyzedPlay {
+            name: play.name.clone(),
+            hosts: play.hosts.clone(),
+            file: file.to_string(),
+            tags,
+            tasks,
+            unresolvable: None,
+        })
+    }
+
+    fn list_entries(
+        &self,
+        entries: &[TaskEntry],
+        context: &TaskContext,
+        tasks: &mut Vec<AnalyzedTask>,
+        applied: &mut AppliedRoles,
+    ) -> Result<(), Box<dyn Error>> {
+        for entry in entries {
+            match entry {
+                TaskEntry::Task(task) => self.list_task(task, context, tasks, applied)?,
+                TaskEntry::Block(block) => {
+                    let context = TaskContext {
+                        tags: with_tags(&context.tags, &block.tags),
+                        ..context.clone()
+                    };
+                    self.list_entries(&block.block, &context, tasks, applied)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn list_task(
+        &self,
+        task: &Task,
+        context: &TaskContext,
+        tasks: &mut Vec<AnalyzedTask>,
+        applied: &mut AppliedRoles,
+    ) -> Result<(), Box<dyn Error>> {
+        let module = task_module(task);
+        let tags = with_tags(&context.tags, &task.tags);
+        let listed = |unresolvable: Option<&str>| AnalyzedTask {
+            name: match task.name.is_empty() {
+                true => module.clone(),
+                false => task.name.clone(),
+            },
+            module: module.clone(),
+            role: context.role.clone(),
+            tags: tags.clone(),
+            file: context.file.clone(),
+            unresolvable: unresolvable.map(String::from),
+        };
+
+        if DYNAMIC_INCLUDES.contains(&module.as_str()) {
+            tasks.push(listed(Some(
+                "dynamic include, its tasks are only known when the play runs",
+            )));
+            return Ok(());
+        }
+
+        if IMPORT_TASKS.contains(&module.as_str()) {
+            let file = task_arg(task, "file");
+            if file.is_empty() || is_templated(&file) {
+                tasks.push(listed(Some("templated task import")));
+                return Ok(());
+            }
+            if context.depth >= MAX_IMPORT_DEPTH {
+                return Err(format!("{}: too many nested imports", context.file).into());
+            }
+
+            let path = context.directory.join(&file);
+            let context = TaskContext {
+                directory: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+                file: path.to_string_lossy().to_string(),
+                tags,
+                depth: context.depth + 1,
+                ..context.clone()
+            };
+            return self.list_entries(&read_tasks(&path)?, &context, tasks, applied);
+        }
+
+        if IMPORT_ROLE.contains(&module.as_str()) {
+            let name = task_arg(task, "name");
+            let tasks_from = match task.args.get("tasks_from") {
+                Some(Value::String(tasks_from)) => tasks_from.clone(),
+                _ => "main".into(),
+            };
+            if name.is_empty() || is_templated(&name) || is_templated(&tasks_from) {
+                tasks.push(listed(Some("templated role import")));
+                return Ok(());
+            }
+
+            let role = RoleRef {
+                role: name,
+                tags: task.tags.clone(),
+                ..Default::default()
+            };
+            // unlike the roles of the play, imported roles run every time
+            return self.apply_role(&role, &tasks_from, false, context, tasks, applied);
+        }
+
+        tasks.push(listed(None));
+        Ok(())
+    }
+
+    // directory of `role`: a path, or a name searched in the `roles/`
+    // directory of the playbook, the roles path, then the playbook directory
+    fn find_role(&self, role: &str, playbook_dir: &Path) -> Option<PathBuf> {
+        if role.contains('/') {
+            return Some(playbook_dir.join(role)).filter(|path| path.is_dir());
+        }
+
+        let mut directories = vec![playbook_dir.join("roles")];
+        directories.extend(self.roles_path.iter().map(PathBuf::from));
+        directories.push(playbook_dir.to_path_buf());
+
+        directories
+            .into_iter()
+            .map(|directory| directory.join(role))
+            .find(|path| path.is_dir())
+    }
+
+    fn apply_role(
+        &self,
+        role: &RoleRef,
+        tasks_from: &str,
+        once: bool,
+        context: &TaskContext,
+        tasks: &mut Vec<AnalyzedTask>,
+        applied: &mut AppliedRoles,
+    ) -> Result<(), Box<dyn Error>> {
+        let tags = with_tags(&context.tags, &role.tags);
+        let unresolvable = |reason: String| AnalyzedTask {
+            name: role.role.clone(),
+            module: "import_role".into(),
+            role: context.role.clone(),
+            tags: tags.clone(),
+            file: context.file.clone(),
+            unresolvable: Some(reason),
+        };
+
+        if is_templated(&role.role) {
+            tasks.push(unresolvable("templated role name".into()));
+            return Ok(());
+        }
+        if context.depth >= MAX_IMPORT_DEPTH {
+            return Err(format!("role '{}': too many nested imports", role.role).into());
+        }
+
+        let role_dir = match self.find_role(&role.role, &context.playbook_dir) {
+            Some(role_dir) => role_dir,
+            // roles of collections are not installed next to the playbook
+            None if role.role.contains('.') => {
+                tasks.push(unresolvable(format!(
+                    "role '{}' not found, it may come from a collection",
+                    role.role
+                )));
+                return Ok(());
+            }
+            None => return Err(format!("role '{}' not found", role.role).into()),
+        };
+
+        let meta = ["main.yml", "main.yaml"]
+            .iter()
+            .map(|file| role_dir.join("meta").join(file))
+            .find(|path| path.is_file())
+            .map(|path| read_yaml(&path))
+            .transpose()?
+            .unwrap_or(Value::Null);
+
+        // a role runs once per play unless given other parameters
+        let mut params = Map::new();
+        params.insert("vars".into(), Value::Object(role.vars.clone()));
+        params.insert("keywords".into(), Value::Object(role.keywords.clone()));
+        let key = (
+            role_dir.clone(),
+            tasks_from.to_string(),
+            Value::Object(params),
+        );
+        let allow_duplicates = matches!(meta.get("allow_duplicates"), Some(Value::Bool(true)));
+        if once && applied.contains(&key) && !allow_duplicates {
+            return Ok(());
+        }
+        applied.push(key);
+
+        let name = role_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| role.role.clone());
+        let role_context = TaskContext {
+            directory: role_dir.join("tasks"),
+            file: String::new(),
+            role: name,
+            tags,
+            depth: context.depth + 1,
+            ..context.clone()
+        };
+
+        // dependencies run first, inheriting the tags of the role
+        if let Some(Value::Array(dependencies)) = meta.get("dependencies") {
+            for dependency in dependencies {
+                let dependency: RoleRef = match serde_json::from_value(dependency.clone()) {
+                    Ok(dependency) => dependency,
+                    Err(err) => {
+                        return Err(format!("role '{}' dependencies: {}", role.role, err).into())
+                    }
+                };
+                self.apply_role(&dependency, "main", true, &role_context, tasks, applied)?;
+            }
+        }
+
+        let path = match role_task_file(&role_dir, tasks_from) {
+            Some(path) => path,
+            None if tasks_from == "main" => return Ok(()),
+            None => {
+                return Err(
+                    format!("role '{}' has no task file '{}'", role.role, tasks_from).into(),
+                )
+            }
+        };
+        let role_context = TaskContext {
+            directory: path.parent().unwrap_or(&role_dir).to_path_buf(),
+            file: path.to_string_lossy().to_string(),
+            ..role_context
+        };
+
+        self.list_entries(&read_tasks(&path)?, &role_context, tasks, applied)
+    }
+}
@@ -0,0 +1,348 @@
+use crate::executor::{sibling_binary, verify_binary, DefaultExecutor};
+use crate::playbook::AnsiblePlaybookCmd;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::process::Child;
+
+/// Action to perform with ansible-config
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnsibleConfigAction {
+    Dump,
+    List,
+    View,
+}
+
+impl AnsibleConfigAction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AnsibleConfigAction::Dump => "dump",
+            AnsibleConfigAction::List => "list",
+            AnsibleConfigAction::View => "view",
+        }
+    }
+}
+
+/// Parameters described on ansible-config's man page
+#[derive(Debug, Default, Clone)]
+pub struct AnsibleConfigOptions {
+    pub config: String, // path to the configuration file, defaults to first file found in precedence
+    pub config_type: String, // filter down to a specific plugin type (base, all, become, callback, ...)
+    pub format: String,      // output format for dump and list (display, json, yaml, ini, env)
+    pub only_changed: bool,  // only show configurations that have changed from the default
+}
+
+impl AnsibleConfigOptions {
+    const CONFIG_FLAG: &str = "--config";
+    const CONFIG_TYPE_FLAG: &str = "--type";
+    const FORMAT_FLAG: &str = "--format";
+    const ONLY_CHANGED_FLAG: &str = "--only-changed";
+
+    /// Returns a list of options flags to be used on ansible-config execution
+    pub fn gen_opts(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut cmd: Vec<String> = Vec::new();
+
+        if !self.config.is_empty() {
+            cmd.push(Self::CONFIG_FLAG.to_string());
+            cmd.push(self.config.clone());
+        }
+
+        if !self.config_type.is_empty() {
+            cmd.push(Self::CONFIG_TYPE_FLAG.to_string());
+            cmd.push(self.config_type.clone());
+        }
+
+        if !self.format.is_empty() {
+            cmd.push(Self::FORMAT_FLAG.to_string());
+            cmd.push(self.format.clone());
+        }
+
+        if self.only_changed {
+            cmd.push(Self::ONLY_CHANGED_FLAG.to_string());
+        }
+
+        Ok(cmd)
+    }
+}
+
+/// Effective value of a setting as reported by `ansible-config dump`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnsibleConfigSetting {
+    pub value: Value,
+    pub origin: String, // `default`, `env: ANSIBLE_FORKS`, or the configuration file path
+    pub setting_type: String, // bool, int, list, path, string, ...
+}
+
+/// Definition of a setting as reported by `ansible-config list`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AnsibleConfigDefinition {
+    #[serde(default, deserialize_with = "deserialize_lines")]
+    pub description: Vec<String>,
+    #[serde(default)]
+    pub default: Value,
+    #[serde(rename = "type", default)]
+    pub setting_type: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_env")]
+    pub env: Vec<String>, // environment variables which set the value
+}
+
+#[derive(Deserialize)]
+struct AnsibleConfigDumpEntry {
+    name: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    origin: Option<String>,
+    #[serde(rename = "type", default)]
+    setting_type: Option<String>,
+}
+
+fn deserialize_lines<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(line) => vec![line],
+        Value::Array(lines) => lines
+            .into_iter()
+            .filter_map(|line| line.as_str().map(|line| line.to_string()))
+            .collect(),
+        _ => vec![],
+    })
+}
+
+// env entries are listed as `[{"name": "ANSIBLE_FORKS"}]`
+fn deserialize_env<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(entries) => entries
+            .iter()
+            .filter_map(|entry| entry.get("name").and_then(|name| name.as_str()))
+            .map(|name| name.to_string())
+            .collect(),
+        _ => vec![],
+    })
+}
+
+// type of the value when ansible-config does not report it
+fn infer_type(value: &Value) -> String {
+    match value {
+        Value::Null => "none",
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "int",
+        Value::String(_) => "string",
+        Value::Array(_) => "list",
+        Value::Object(_) => "dict",
+    }
+    .to_string()
+}
+
+fn parse_dump_entries(
+    entries: Value,
+    prefix: &str,
+    settings: &mut BTreeMap<String, AnsibleConfigSetting>,
+) -> Result<(), Box<dyn Error>> {
+    let entries: Vec<AnsibleConfigDumpEntry> = match serde_json::from_value(entries) {
+        Ok(entries) => entries,
+        Err(err) => return Err(format!("(config::parse_config_dump) {}", err).into()),
+    };
+
+    for entry in entries {
+        let setting_type = match entry.setting_type {
+            Some(setting_type) => setting_type,
+            None => infer_type(&entry.value),
+        };
+        settings.insert(
+            format!("{}{}", prefix, entry.name),
+            AnsibleConfigSetting {
+                value: entry.value,
+                origin: entry.origin.unwrap_or_default(),
+                setting_type,
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Parse the output of `ansible-config dump --format json` into a map of
+/// setting name to its effective value. Plugin settings dumped with
+/// `--type all` are prefixed by their section, eg. `become.sudo.become_exe`.
+pub fn parse_config_dump(
+    output: &str,
+) -> Result<BTreeMap<String, AnsibleConfigSetting>, Box<dyn Error>> {
+    let dump: Value = match serde_json::from_str(output) {
+        Ok(dump) => dump,
+        Err(err) => return Err(format!("(config::parse_config_dump) {}", err).into()),
+    };
+
+    let mut settings = BTreeMap::new();
+    match dump {
+        Value::Array(_) => parse_dump_entries(dump, "", &mut settings)?,
+        Value::Object(sections) => {
+            for (section, entries) in sections {
+                match entries {
+                    Value::Array(_) => {
+                        let prefix = match section.as_str() {
+                            "base" => String::new(),
+                            section => format!("{}.", section),
+                        };
+                        parse_dump_entries(entries, &prefix, &mut settings)?
+                    }
+                    Value::Object(plugins) => {
+                        for (plugin, entries) in plugins {
+                            let prefix = format!("{}.{}.", section, plugin);
+                            parse_dump_entries(entries, &prefix, &mut settings)?
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => return Err("(config::parse_config_dump) unexpected dump format".into()),
+    }
+
+    Ok(settings)
+}
+
+/// Parse the output of `ansible-config list --format json`
+pub fn parse_config_list(
+    output: &str,
+) -> Result<BTreeMap<String, AnsibleConfigDefinition>, Box<dyn Error>> {
+    match serde_json::from_str(output) {
+        Ok(list) => Ok(list),
+        Err(err) => Err(format!("(config::parse_config_list) {}", err).into()),
+    }
+}
+
+/// Ansible-config command representation and how to execute it
+#[derive(Debug, Clone)]
+pub struct AnsibleConfigCmd {
+    pub binary: String,                // Ansible config binary
+    pub executor: DefaultExecutor,     // Executor used to run the command
+    pub action: AnsibleConfigAction,   // action to perform
+    pub options: AnsibleConfigOptions, // config options
+    pub env: BTreeMap<String, String>, // extra environment variables set for the execution
+}
+
+const DEFAULT_ANSIBLE_CONFIG_BINARY: &str = "ansible-config";
+impl Default for AnsibleConfigCmd {
+    fn default() -> Self {
+        AnsibleConfigCmd {
+            binary: DEFAULT_ANSIBLE_CONFIG_BINARY.into(),
+            executor: DefaultExecutor {},
+            action: AnsibleConfigAction::Dump,
+            options: AnsibleConfigOptions {
+                ..Default::default()
+            },
+            env: BTreeMap::new(),
+        }
+    }
+}
+
+impl AnsibleConfigCmd {
+    const JSON_FORMAT: &str = "json";
+
+    /// Build an ansible-config command running with the same binaries
+    /// installation and environment as the given playbook command, so its
+    /// output matches the configuration that playbook will see
+    pub fn from_playbook(playbook: &AnsiblePlaybookCmd) -> Self {
+        AnsibleConfigCmd {
+            binary: sibling_binary(&playbook.binary, DEFAULT_ANSIBLE_CONFIG_BINARY),
+            executor: playbook.executor.clone(),
+            env: playbook.env.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// run ansible-config
+    pub fn run(&self) -> Result<Child, Box<dyn Error>> {
+        if let Err(err) = verify_binary(&self.binary) {
+            return Err(format!("(config::run) {}", err).into());
+        }
+
+        let command = self.command()?;
+        self.executor.run_with_env(command, &self.env)
+    }
+
+    fn action_output(
+        &self,
+        action: AnsibleConfigAction,
+        options: AnsibleConfigOptions,
+    ) -> Result<String, Box<dyn Error>> {
+        if let Err(err) = verify_binary(&self.binary) {
+            return Err(format!("(config::action_output) {}", err).into());
+        }
+
+        let cmd = AnsibleConfigCmd {
+            action,
+            options,
+            ..self.clone()
+        };
+
+        let output = self.executor.output_with_env(cmd.command()?, &self.env)?;
+        if !output.status.success() {
+            return Err(format!(
+                "(config::action_output) {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Run `ansible-config dump --format json` and return the effective
+    /// settings, only the changed ones when `only_changed` is set
+    pub fn dump(&self) -> Result<BTreeMap<String, AnsibleConfigSetting>, Box<dyn Error>> {
+        let options = AnsibleConfigOptions {
+            format: Self::JSON_FORMAT.into(),
+            ..self.options.clone()
+        };
+        parse_config_dump(&self.action_output(AnsibleConfigAction::Dump, options)?)
+    }
+
+    /// Run `ansible-config list --format json` and return every setting definition
+    pub fn list(&self) -> Result<BTreeMap<String, AnsibleConfigDefinition>, Box<dyn Error>> {
+        let options = AnsibleConfigOptions {
+            format: Self::JSON_FORMAT.into(),
+            only_changed: false,
+            ..self.options.clone()
+        };
+        parse_config_list(&self.action_output(AnsibleConfigAction::List, options)?)
+    }
+
+    /// Run `ansible-config view` and return the configuration file content
+    pub fn view(&self) -> Result<String, Box<dyn Error>> {
+        let options = AnsibleConfigOptions {
+            config: self.options.config.clone(),
+            ..Default::default()
+        };
+        self.action_output(AnsibleConfigAction::View, options)
+    }
+
+    /// generate command line
+    pub fn command(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        if self.action == AnsibleConfigAction::View
+            && (self.options.only_changed || !self.options.format.is_empty())
+        {
+            return Err("(config::command) view does not support format nor only-changed".into());
+        }
+
+        let mut cmd = vec![];
+
+        cmd.push(self.binary.clone());
+        cmd.push(self.action.as_str().to_string());
+        cmd.append(&mut self.options.gen_opts()?);
+
+        Ok(cmd)
+    }
+
+    pub fn to_string(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.command()?.join(" "))
+    }
+}
@@ -0,0 +1,473 @@
+use crate::executor::{verify_binary, DefaultExecutor};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::process::Child;
+
+/// Plugin types documented by ansible-doc's `--type` option
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnsibleDocPluginType {
+    Become,
+    Cache,
+    Callback,
+    Cliconf,
+    Connection,
+    Filter,
+    Httpapi,
+    Inventory,
+    Keyword,
+    Lookup,
+    Module,
+    Netconf,
+    Role,
+    Shell,
+    Strategy,
+    Test,
+    Vars,
+}
+
+impl AnsibleDocPluginType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AnsibleDocPluginType::Become => "become",
+            AnsibleDocPluginType::Cache => "cache",
+            AnsibleDocPluginType::Callback => "callback",
+            AnsibleDocPluginType::Cliconf => "cliconf",
+            AnsibleDocPluginType::Connection => "connection",
+            AnsibleDocPluginType::Filter => "filter",
+            AnsibleDocPluginType::Httpapi => "httpapi",
+            AnsibleDocPluginType::Inventory => "inventory",
+            AnsibleDocPluginType::Keyword => "keyword",
+            AnsibleDocPluginType::Lookup => "lookup",
+            AnsibleDocPluginType::Module => "module",
+            AnsibleDocPluginType::Netconf => "netconf",
+            AnsibleDocPluginType::Role => "role",
+            AnsibleDocPluginType::Shell => "shell",
+            AnsibleDocPluginType::Strategy => "strategy",
+            AnsibleDocPluginType::Test => "test",
+            AnsibleDocPluginType::Vars => "vars",
+        }
+    }
+}
+
+/// Parameters described on ansible-doc's man page
+#[derive(Debug, Default, Clone)]
+pub struct AnsibleDocOptions {
+    pub json: bool,           // change output into json format
+    pub list: bool,           // list available plugins
+    pub module_path: String,  // prepend colon-separated path(s) to module library
+    pub playbook_dir: String, // directory to use as the playbook directory for roles and collections lookups
+}
+
+impl AnsibleDocOptions {
+    const JSON_FLAG: &str = "--json";
+    const LIST_FLAG: &str = "--list";
+    const MODULE_PATH_FLAG: &str = "--module-path";
+    const PLAYBOOK_DIR_FLAG: &str = "--playbook-dir";
+
+    /// Returns a list of options flags to be used on ansible-doc execution
+    pub fn gen_opts(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut cmd: Vec<String> = Vec::new();
+
+        if self.json {
+            cmd.push(Self::JSON_FLAG.to_string());
+        }
+
+        if self.list {
+            cmd.push(Self::LIST_FLAG.to_string());
+        }
+
+        if !self.module_path.is_empty() {
+            cmd.push(Self::MODULE_PATH_FLAG.to_string());
+            cmd.push(self.module_path.clone());
+        }
+
+        if !self.playbook_dir.is_empty() {
+            cmd.push(Self::PLAYBOOK_DIR_FLAG.to_string());
+            cmd.push(self.playbook_dir.clone());
+        }
+
+        Ok(cmd)
+    }
+}
+
+/// Documentation of a single plugin option
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AnsibleDocOption {
+    #[serde(default, deserialize_with = "deserialize_lines")]
+    pub description: Vec<String>,
+    #[serde(rename = "type", default = "default_option_type")]
+    pub option_type: String, // str, int, bool, list, dict, path, raw, ...
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Value,
+    #[serde(default, deserialize_with = "deserialize_choices")]
+    pub choices: Vec<Value>,
+    #[serde(default)]
+    pub elements: Option<String>, // type of the elements when option_type is list
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    pub aliases: Vec<String>,
+    #[serde(
+        default,
+        alias = "options",
+        deserialize_with = "deserialize_null_default"
+    )]
+    pub suboptions: BTreeMap<String, AnsibleDocOption>,
+}
+
+/// Documentation of a single module return value
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AnsibleDocReturn {
+    #[serde(default, deserialize_with = "deserialize_lines")]
+    pub description: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    pub returned: String,
+    #[serde(rename = "type", default = "default_option_type")]
+    pub return_type: String,
+    #[serde(default)]
+    pub sample: Value,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    pub contains: BTreeMap<String, AnsibleDocReturn>,
+}
+
+/// Documentation of a plugin as returned by `ansible-doc --json`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnsibleDoc {
+    pub name: String,
+    pub short_description: String,
+    pub description: Vec<String>,
+    pub options: BTreeMap<String, AnsibleDocOption>,
+    pub returns: BTreeMap<String, AnsibleDocReturn>,
+    pub examples: String,
+}
+
+#[derive(Deserialize)]
+struct AnsibleDocEntry {
+    #[serde(default)]
+    doc: AnsibleDocBody,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    examples: String,
+    #[serde(
+        default,
+        rename = "return",
+        deserialize_with = "deserialize_null_default"
+    )]
+    returns: BTreeMap<String, AnsibleDocReturn>,
+}
+
+#[derive(Default, Deserialize)]
+struct AnsibleDocBody {
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    short_description: String,
+    #[serde(default, deserialize_with = "deserialize_lines")]
+    description: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_null_default")]
+    options: BTreeMap<String, AnsibleDocOption>,
+}
+
+fn default_option_type() -> String {
+    "str".into()
+}
+
+fn deserialize_null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// descriptions are either a single string or a list of paragraphs
+fn deserialize_lines<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(line) => vec![line],
+        Value::Array(lines) => lines
+            .into_iter()
+            .map(|line| match line {
+                Value::String(line) => line,
+                other => other.to_string(),
+            })
+            .collect(),
+        _ => vec![],
+    })
+}
+
+// choices are either a list of values or a map of value to description
+fn deserialize_choices<'de, D>(deserializer: D) -> Result<Vec<Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(choices) => choices,
+        Value::Object(choices) => choices.into_iter().map(|(k, _)| Value::String(k)).collect(),
+        _ => vec![],
+    })
+}
+
+/// Parse the output of `ansible-doc --json`
+pub fn parse_doc(output: &str) -> Result<BTreeMap<String, AnsibleDoc>, Box<dyn Error>> {
+    let entries: BTreeMap<String, AnsibleDocEntry> = match serde_json::from_str(output) {
+        Ok(entries) => entries,
+        Err(err) => return Err(format!("(doc::parse_doc) {}", err).into()),
+    };
+
+    Ok(entries
+        .into_iter()
+        .map(|(name, entry)| {
+            let doc = AnsibleDoc {
+                name: name.clone(),
+                short_description: entry.doc.short_description,
+                description: entry.doc.description,
+                options: entry.doc.options,
+                returns: entry.returns,
+                examples: entry.examples,
+            };
+            (name, doc)
+        })
+        .collect())
+}
+
+/// Parse the output of `ansible-doc --list --json` into a map of plugin
+/// name to its short description
+pub fn parse_doc_list(output: &str) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    match serde_json::from_str(output) {
+        Ok(list) => Ok(list),
+        Err(err) => Err(format!("(doc::parse_doc_list) {}", err).into()),
+    }
+}
+
+// scalar value as ansible would compare it once templated, eg. `1` and `"1"`
+fn plain_value(value: &Value) -> String {
+    match value.as_str() {
+        Some(value) => value.to_string(),
+        None => value.to_string(),
+    }
+}
+
+impl AnsibleDocOption {
+    fn names<'a>(name: &'a str, option: &'a AnsibleDocOption) -> impl Iterator<Item = &'a str> {
+        std::iter::once(name).chain(option.aliases.iter().map(|alias| alias.as_str()))
+    }
+
+    fn matches_type(&self, value: &Value) -> bool {
+        match self.option_type.as_str() {
+            "str" | "path" => !value.is_array() && !value.is_object(),
+            "int" => {
+                value.is_i64()
+                    || value.is_u64()
+                    || value.as_str().is_some_and(|v| v.parse::<i64>().is_ok())
+            }
+            "float" => {
+                value.is_number() || value.as_str().is_some_and(|v| v.parse::<f64>().is_ok())
+            }
+            "bool" => {
+                value.is_boolean()
+                    || value.as_str().is_some_and(|v| {
+                        matches!(
+                            v.to_lowercase().as_str(),
+                            "yes" | "no" | "true" | "false" | "on" | "off" | "y" | "n" | "1" | "0"
+                        )
+                    })
+            }
+            "list" => value.is_array() || value.is_string(),
+            "dict" => value.is_object() || value.is_string(),
+            _ => true,
+        }
+    }
+
+    fn validate(&self, name: &str, value: &Value, errors: &mut Vec<String>) {
+        if value.is_null() {
+            return;
+        }
+
+        if !self.matches_type(value) {
+            errors.push(format!(
+                "option '{}' expects a value of type '{}'",
+                name, self.option_type
+            ));
+            return;
+        }
+
+        if !self.choices.is_empty() {
+            let values = match value {
+                Value::Array(values) => values.clone(),
+                value => vec![value.clone()],
+            };
+            for value in values {
+                let matches = self
+                    .choices
+                    .iter()
+                    .any(|choice| plain_value(choice) == plain_value(&value));
+                if !matches {
+                    errors.push(format!(
+                        "option '{}' got '{}', expected one of {:?}",
+                        name, value, self.choices
+                    ));
+                }
+            }
+        }
+
+        if !self.suboptions.is_empty() {
+            let items = match value {
+                Value::Array(items) => items.clone(),
+                value => vec![value.clone()],
+            };
+            for item in items {
+                if let Value::Object(args) = item {
+                    validate_options(&self.suboptions, &args, &format!("{}.", name), errors);
+                }
+            }
+        }
+    }
+}
+
+fn validate_options(
+    options: &BTreeMap<String, AnsibleDocOption>,
+    args: &serde_json::Map<String, Value>,
+    prefix: &str,
+    errors: &mut Vec<String>,
+) {
+    for key in args.keys() {
+        let known = options
+            .iter()
+            .any(|(name, option)| AnsibleDocOption::names(name, option).any(|n| n == key));
+        if !known {
+            errors.push(format!("unsupported option '{}{}'", prefix, key));
+        }
+    }
+
+    for (name, option) in options {
+        let value = AnsibleDocOption::names(name, option).find_map(|n| args.get(n));
+        match value {
+            Some(value) => option.validate(&format!("{}{}", prefix, name), value, errors),
+            None if option.required => {
+                errors.push(format!("missing required option '{}{}'", prefix, name))
+            }
+            None => {}
+        }
+    }
+}
+
+impl AnsibleDoc {
+    /// Validate module arguments against the documented options: unknown
+    /// options, missing required ones, types and choices are checked
+    pub fn validate_args(&self, args: &Value) -> Result<(), Box<dyn Error>> {
+        let args = match args {
+            Value::Object(args) => args.clone(),
+            Value::Null => serde_json::Map::new(),
+            _ => return Err("(doc::validate_args) module arguments must be a map".into()),
+        };
+
+        let mut errors = vec![];
+        validate_options(&self.options, &args, "", &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("(doc::validate_args) {}: {}", self.name, errors.join(", ")).into())
+        }
+    }
+}
+
+/// Ansible-doc command representation and how to execute it
+#[derive(Debug, Clone)]
+pub struct AnsibleDocCmd {
+    pub binary: String,                    // Ansible doc binary
+    pub executor: DefaultExecutor,         // Executor used to run the command
+    pub plugin_type: AnsibleDocPluginType, // type of the documented plugins
+    pub plugins: Vec<String>,              // plugins to document
+    pub options: AnsibleDocOptions,        // doc options
+}
+
+const DEFAULT_ANSIBLE_DOC_BINARY: &str = "ansible-doc";
+impl Default for AnsibleDocCmd {
+    fn default() -> Self {
+        AnsibleDocCmd {
+            binary: DEFAULT_ANSIBLE_DOC_BINARY.into(),
+            executor: DefaultExecutor {},
+            plugin_type: AnsibleDocPluginType::Module,
+            plugins: vec![],
+            options: AnsibleDocOptions {
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl AnsibleDocCmd {
+    const TYPE_FLAG: &str = "--type";
+
+    /// run ansible-doc
+    pub fn run(&self) -> Result<Child, Box<dyn Error>> {
+        if let Err(err) = verify_binary(&self.binary) {
+            return Err(format!("(doc::run) {}", err).into());
+        }
+
+        let command = self.command()?;
+        self.executor.run(command)
+    }
+
+    fn json_output(&self, list: bool) -> Result<String, Box<dyn Error>> {
+        if let Err(err) = verify_binary(&self.binary) {
+            return Err(format!("(doc::json_output) {}", err).into());
+        }
+
+        let cmd = AnsibleDocCmd {
+            plugins: if list { vec![] } else { self.plugins.clone() },
+            options: AnsibleDocOptions {
+                json: true,
+                list,
+                ..self.options.clone()
+            },
+            ..self.clone()
+        };
+
+        let output = self.executor.output(cmd.command()?)?;
+        if !output.status.success() {
+            return Err(format!(
+                "(doc::json_output) {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Run `ansible-doc --json` and return the documentation of every plugin
+    pub fn docs(&self) -> Result<BTreeMap<String, AnsibleDoc>, Box<dyn Error>> {
+        parse_doc(&self.json_output(false)?)
+    }
+
+    /// Run `ansible-doc --list --json` and return the available plugins of
+    /// the configured type along with their short description
+    pub fn list(&self) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+        parse_doc_list(&self.json_output(true)?)
+    }
+
+    /// generate command line
+    pub fn command(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        if !self.options.list && self.plugins.is_empty() {
+            return Err("(doc::command) at least one plugin is required unless listing".into());
+        }
+
+        let mut cmd = vec![];
+
+        cmd.push(self.binary.clone());
+        cmd.push(Self::TYPE_FLAG.to_string());
+        cmd.push(self.plugin_type.as_str().to_string());
+        cmd.append(&mut self.options.gen_opts()?);
+        cmd.append(&mut self.plugins.clone());
+
+        Ok(cmd)
+    }
+
+    pub fn to_string(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.command()?.join(" "))
+    }
+}
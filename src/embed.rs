@@ -0,0 +1,355 @@
+use crate::executor::{create_private_dirs, create_temp_dir, write_private_file};
+use crate::source::PlaybookSource;
+use std::env;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+
+// FNV-1a parameters, the hash being computed at compile time
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// fallback extraction directories tried when the one named after the hash
+// is taken
+const MAX_FALLBACKS: usize = 8;
+
+/// File of a playbook project embedded in the binary
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmbeddedFile {
+    pub path: &'static str, // path relative to the project root, such as `roles/web/tasks/main.yml`
+    pub content: &'static [u8], // content of the file
+    pub mode: u32,          // permissions of the file, such as `0o755` for scripts
+}
+
+/// Playbook project embedded in the binary, see `embed_playbooks!` and
+/// `generate_playbook_bundle`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybookBundle {
+    pub files: &'static [EmbeddedFile], // files of the project
+    pub hash: u64,                      // hash of the paths, modes and contents of the files
+}
+
+const fn hash_bytes(mut hash: u64, bytes: &[u8]) -> u64 {
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+        i += 1;
+    }
+
+    hash
+}
+
+/// Returns the content hash of `files`, usable in constants
+pub const fn bundle_hash(files: &[EmbeddedFile]) -> u64 {
+    let mut hash = FNV_OFFSET;
+    let mut i = 0;
+    while i < files.len() {
+        hash = hash_bytes(hash, files[i].path.as_bytes());
+        hash = hash_bytes(hash, &[0]);
+        hash = hash_bytes(hash, &files[i].mode.to_le_bytes());
+        hash = hash_bytes(hash, &(files[i].content.len() as u64).to_le_bytes());
+        hash = hash_bytes(hash, files[i].content);
+        i += 1;
+    }
+
+    hash
+}
+
+/// Embed the files of a playbook project at compile time, their paths being
+/// relative to `root`, itself relative to the crate root. Files are
+/// readable and writable by their owner, a mode can be given to the ones
+/// which must be executable.
+///
+/// ```ignore
+/// static PLAYBOOKS: PlaybookBundle = embed_playbooks!("playbooks", [
+///     "site.yml",
+///     "roles/web/tasks/main.yml",
+///     "roles/web/files/healthcheck.sh" => 0o755,
+/// ]);
+/// ```
+#[macro_export]
+macro_rules! embed_playbooks {
+    ($root:literal, [$($path:literal $(=> $mode:expr)?),* $(,)?]) => {{
+        const FILES: &[$crate::EmbeddedFile] = &[$(
+            $crate::EmbeddedFile {
+                path: $path,
+                content: include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $root, "/", $path)),
+                mode: $crate::embed_playbooks!(@mode $($mode)?),
+            },
+        )*];
+        const HASH: u64 = $crate::bundle_hash(FILES);
+        $crate::PlaybookBundle { files: FILES, hash: HASH }
+    }};
+    (@mode $mode:expr) => { $mode };
+    (@mode) => { 0o644 };
+}
+
+// files of `directory`, recursively and in path order, version control
+// directories being skipped
+fn project_files(
+    root: &Path,
+    directory: &Path,
+    files: &mut Vec<(String, PathBuf, u32)>,
+) -> Result<(), Box<dyn Error>> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if name == ".git" {
+            continue;
+        }
+        if path.is_dir() {
+            project_files(root, &path, files)?;
+            continue;
+        }
+
+        let relative = path.strip_prefix(root)?;
+        let relative = match relative.to_str() {
+            Some(relative) => relative.replace('\\', "/"),
+            None => return Err(format!("{} is not a valid UTF-8 path", path.display()).into()),
+        };
+        files.push((relative, path.clone(), file_mode(&path)?));
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(path: &Path) -> Result<u32, Box<dyn Error>> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(fs::metadata(path)?.permissions().mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> Result<u32, Box<dyn Error>> {
+    Ok(0o644)
+}
+
+/// Generate, from a build script, the Rust expression embedding every file
+/// of the playbook project in `root`, and write it to `out`. The bundle is
+/// then included with
+/// `include!(concat!(env!("OUT_DIR"), "/playbooks.rs"))`, and the build
+/// script is run again when the project changes.
+pub fn generate_playbook_bundle(
+    root: impl AsRef<Path>,
+    out: impl AsRef<Path>,
+) -> Result<(), Box<dyn Error>> {
+    let root = match root.as_ref().canonicalize() {
+        Ok(root) => root,
+        Err(err) => {
+            return Err(format!(
+                "(embed::generate_playbook_bundle) {}: {}",
+                root.as_ref().display(),
+                err
+            )
+            .into())
+        }
+    };
+
+    let mut files = vec![];
+    if let Err(err) = project_files(&root, &root, &mut files) {
+        return Err(format!("(embed::generate_playbook_bundle) {}", err).into());
+    }
+
+    let mut code = String::from("{\n    const FILES: &[::rs_ansible::EmbeddedFile] = &[\n");
+    for (relative, path, mode) in &files {
+        let _ = writeln!(
+            code,
+            "        ::rs_ansible::EmbeddedFile {{ path: {:?}, content: include_bytes!({:?}), mode: 0o{:o} }},",
+            relative,
+            path.to_string_lossy(),
+            mode
+        );
+    }
+    code.push_str(
+        "    ];\n    const HASH: u64 = ::rs_ansible::bundle_hash(FILES);\n    ::rs_ansible::PlaybookBundle { files: FILES, hash: HASH }\n}\n",
+    );
+
+    if let Err(err) = fs::write(out.as_ref(), code) {
+        return Err(format!(
+            "(embed::generate_playbook_bundle) {}: {}",
+            out.as_ref().display(),
+            err
+        )
+        .into());
+    }
+    println!("cargo:rerun-if-changed={}", root.display());
+
+    Ok(())
+}
+
+// permissions of an extracted file, only granted to its owner
+fn private_mode(mode: u32) -> u32 {
+    0o600 | (mode & 0o100)
+}
+
+// uid of the current user, found once through a directory it creates
+#[cfg(unix)]
+fn current_uid() -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    static UID: OnceLock<Option<u32>> = OnceLock::new();
+    *UID.get_or_init(|| {
+        let directory = create_temp_dir("uid").ok()?;
+        let uid = fs::metadata(&directory).map(|metadata| metadata.uid()).ok();
+        let _ = fs::remove_dir(&directory);
+
+        uid
+    })
+}
+
+// whether `path` is a directory, and not a link to one, only accessible by
+// the current user
+#[cfg(unix)]
+fn is_private_dir(path: &Path) -> bool {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => {
+            metadata.is_dir()
+                && metadata.permissions().mode() & 0o777 == 0o700
+                && Some(metadata.uid()) == current_uid()
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_private_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<(), Box<dyn Error>> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(fs::set_permissions(path, fs::Permissions::from_mode(mode))?)
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<(), Box<dyn Error>> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn has_mode(path: &Path, mode: u32) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::symlink_metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o777 == mode)
+}
+
+#[cfg(not(unix))]
+fn has_mode(path: &Path, _mode: u32) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_file())
+}
+
+impl PlaybookBundle {
+    // path of `file` within an extraction, which it cannot escape
+    fn file_path(directory: &Path, file: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let path = Path::new(file);
+        if file.is_empty()
+            || !path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(format!("'{}' is not a path within the bundle", file).into());
+        }
+
+        Ok(directory.join(path))
+    }
+
+    // whether `directory` holds an untampered extraction of the bundle
+    fn is_extracted_to(&self, directory: &Path) -> bool {
+        if !is_private_dir(directory) {
+            return false;
+        }
+
+        self.files.iter().all(|file| {
+            let path = match Self::file_path(directory, file.path) {
+                Ok(path) => path,
+                Err(_) => return false,
+            };
+            has_mode(&path, private_mode(file.mode))
+                && fs::read(&path).is_ok_and(|content| content == file.content)
+        })
+    }
+
+    fn write_to(&self, directory: &Path) -> Result<(), Box<dyn Error>> {
+        for file in self.files {
+            let path = Self::file_path(directory, file.path)?;
+            if let Some(parent) = path.parent() {
+                create_private_dirs(parent)?;
+            }
+            write_private_file(&path, file.content)?;
+            set_mode(&path, private_mode(file.mode))?;
+        }
+
+        Ok(())
+    }
+
+    /// Extract the bundle to a directory of the system temporary directory
+    /// only accessible by the current user, and return it. The directory is
+    /// named after the content hash, so later extractions of the same bundle
+    /// reuse it once checked to be untampered. When the path is taken by
+    /// anything else, such as a tampered extraction or a directory of
+    /// another user, the numbered fallbacks `-1` to `-8` are tried the same
+    /// way. Extractions are kept for later runs and never removed by the
+    /// crate, the caller may remove them once no run uses them.
+    pub fn extract(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let name = format!("rs-ansible-bundle-{:016x}", self.hash);
+        for fallback in 0..=MAX_FALLBACKS {
+            let target = match fallback {
+                0 => env::temp_dir().join(&name),
+                n => env::temp_dir().join(format!("{}-{}", name, n)),
+            };
+            if self.is_extracted_to(&target) {
+                return Ok(target);
+            }
+            if fs::symlink_metadata(&target).is_ok() {
+                continue;
+            }
+
+            let directory = create_temp_dir("bundle")?;
+            if let Err(err) = self.write_to(&directory) {
+                let _ = fs::remove_dir_all(&directory);
+                return Err(format!("(embed::extract) {}", err).into());
+            }
+
+            // another process may have extracted the bundle meanwhile
+            let renamed =
+                fs::symlink_metadata(&target).is_err() && fs::rename(&directory, &target).is_ok();
+            if !renamed {
+                let _ = fs::remove_dir_all(&directory);
+            }
+            if self.is_extracted_to(&target) {
+                return Ok(target);
+            }
+        }
+
+        Err(format!(
+            "(embed::extract) {} and its fallbacks are taken",
+            env::temp_dir().join(name).display()
+        )
+        .into())
+    }
+
+    /// Extract the bundle and return the source running its playbook
+    /// `path`, the extraction being kept as described on `extract`
+    pub fn playbook(&self, path: &str) -> Result<PlaybookSource, Box<dyn Error>> {
+        if !self.files.iter().any(|file| file.path == path) {
+            return Err(format!("(embed::playbook) '{}' is not a file of the bundle", path).into());
+        }
+
+        let directory = self.extract()?;
+        let playbook = Self::file_path(&directory, path)?;
+
+        Ok(PlaybookSource::Path(playbook.to_string_lossy().to_string()))
+    }
+}
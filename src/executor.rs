@@ -1,5 +1,14 @@
+use rand::{thread_rng, Rng};
+use std::collections::BTreeMap;
+use std::env;
 use std::error::Error;
-use std::process::{Child, Command, Stdio};
+use std::fmt;
+use std::fs::{DirBuilder, OpenOptions};
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::thread;
 use which::which;
 
 pub fn verify_binary(binary: &str) -> Result<(), Box<dyn Error>> {
@@ -9,22 +18,263 @@ pub fn verify_binary(binary: &str) -> Result<(), Box<dyn Error>> {
     }
 }
 
+/// Returns the path of another ansible binary installed along `binary`, eg.
+/// `ansible-config` from `/opt/venv/bin/ansible-playbook`. Binaries looked
+/// up through `PATH` resolve to the bare `name`.
+pub(crate) fn sibling_binary(binary: &str, name: &str) -> String {
+    if !binary.contains('/') {
+        return name.to_string();
+    }
+
+    Path::new(binary)
+        .with_file_name(name)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Returns a path within the system temporary directory which does not exist
+/// yet, eg. `/tmp/rs-ansible-inventory-1234567890.json`
+pub(crate) fn temp_path(prefix: &str, extension: &str) -> PathBuf {
+    let mut name = format!("rs-ansible-{}-{}", prefix, thread_rng().gen::<u64>());
+    if !extension.is_empty() {
+        name = format!("{}.{}", name, extension);
+    }
+
+    env::temp_dir().join(name)
+}
+
+/// Write `content` to a new file only readable by the current user
+pub(crate) fn write_private_file(path: &Path, content: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = match options.open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(
+                format!("(executor::write_private_file) {}: {}", path.display(), err).into(),
+            )
+        }
+    };
+    if let Err(err) = file.write_all(content) {
+        return Err(format!("(executor::write_private_file) {}: {}", path.display(), err).into());
+    }
+
+    Ok(())
+}
+
+/// Write `content` to a new temporary file only readable by the current user
+pub(crate) fn write_temp_file(
+    prefix: &str,
+    extension: &str,
+    content: &str,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let path = temp_path(prefix, extension);
+
+    match write_private_file(&path, content.as_bytes()) {
+        Ok(()) => Ok(path),
+        Err(err) => Err(format!("(executor::write_temp_file) {}", err).into()),
+    }
+}
+
+/// Create a directory and its missing parents, only accessible by the
+/// current user
+pub(crate) fn create_private_dirs(path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+
+    match builder.create(path) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!(
+            "(executor::create_private_dirs) {}: {}",
+            path.display(),
+            err
+        )
+        .into()),
+    }
+}
+
+/// Create a new temporary directory only accessible by the current user
+pub(crate) fn create_temp_dir(prefix: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let path = temp_path(prefix, "");
+
+    let mut builder = DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+
+    match builder.create(&path) {
+        Ok(_) => Ok(path),
+        Err(err) => Err(format!("(executor::create_temp_dir) {}", err).into()),
+    }
+}
+
+type Cleanup = Box<dyn FnOnce(Option<ExitStatus>) + Send>;
+
+/// Running ansible process. It behaves as the underlying `Child`, and runs
+/// the cleanups registered for the execution, such as removing temporary
+/// files, once the process has finished. When dropped before the process
+/// has finished, the process is waited for in background before cleaning up.
+pub struct AnsibleChild {
+    pub warnings: Vec<String>, // warnings found while preparing the execution
+    child: Option<Child>,
+    cleanups: Vec<Cleanup>,
+}
+
+impl fmt::Debug for AnsibleChild {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnsibleChild")
+            .field("warnings", &self.warnings)
+            .field("child", &self.child)
+            .field("cleanups", &self.cleanups.len())
+            .finish()
+    }
+}
+
+impl AnsibleChild {
+    pub fn new(child: Child) -> Self {
+        AnsibleChild {
+            warnings: vec![],
+            child: Some(child),
+            cleanups: vec![],
+        }
+    }
+
+    /// Register a cleanup to run once the process has finished, which gets
+    /// the process exit status when it is known
+    pub fn on_exit<F>(&mut self, cleanup: F)
+    where
+        F: FnOnce(Option<ExitStatus>) + Send + 'static,
+    {
+        self.cleanups.push(Box::new(cleanup));
+    }
+
+    fn cleanup(&mut self, status: Option<ExitStatus>) {
+        for cleanup in self.cleanups.drain(..) {
+            cleanup(status);
+        }
+    }
+
+    /// Wait for the process to finish and run the cleanups
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        let status = self.deref_mut().wait()?;
+        self.cleanup(Some(status));
+
+        Ok(status)
+    }
+
+    /// Wait for the process to finish collecting its whole output, and run
+    /// the cleanups
+    pub fn wait_with_output(mut self) -> io::Result<Output> {
+        let child = self
+            .child
+            .take()
+            .expect("(executor::wait_with_output) child");
+        let output = child.wait_with_output();
+        self.cleanup(output.as_ref().ok().map(|output| output.status));
+
+        output
+    }
+}
+
+impl Deref for AnsibleChild {
+    type Target = Child;
+
+    fn deref(&self) -> &Child {
+        self.child.as_ref().expect("(executor::deref) child")
+    }
+}
+
+impl DerefMut for AnsibleChild {
+    fn deref_mut(&mut self) -> &mut Child {
+        self.child.as_mut().expect("(executor::deref_mut) child")
+    }
+}
+
+impl Drop for AnsibleChild {
+    fn drop(&mut self) {
+        if self.cleanups.is_empty() {
+            return;
+        }
+
+        let mut child = match self.child.take() {
+            Some(child) => child,
+            None => return self.cleanup(None),
+        };
+
+        match child.try_wait() {
+            Ok(Some(status)) => self.cleanup(Some(status)),
+            _ => {
+                let cleanups: Vec<Cleanup> = self.cleanups.drain(..).collect();
+                thread::spawn(move || {
+                    let status = child.wait().ok();
+                    for cleanup in cleanups {
+                        cleanup(status);
+                    }
+                });
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DefaultExecutor {}
 
 impl DefaultExecutor {
     pub fn run(&self, command: Vec<String>) -> Result<Child, Box<dyn Error>> {
-        if let Err(err) = verify_binary(&command[0].as_str()) {
+        self.run_with_env(command, &BTreeMap::new())
+    }
+
+    /// Run command with extra environment variables set on top of the
+    /// current process ones
+    pub fn run_with_env(
+        &self,
+        command: Vec<String>,
+        env: &BTreeMap<String, String>,
+    ) -> Result<Child, Box<dyn Error>> {
+        if let Err(err) = verify_binary(command[0].as_str()) {
             return Err(format!("(executor::run) {}", err).into());
         }
 
         let child = Command::new(command[0].clone())
             .args(&command[1..])
+            .envs(env)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
             .expect("(executor::run) run command");
 
-        return Ok(child);
+        Ok(child)
+    }
+
+    /// Run command and wait for it to finish, collecting its whole output
+    pub fn output(&self, command: Vec<String>) -> Result<Output, Box<dyn Error>> {
+        self.output_with_env(command, &BTreeMap::new())
+    }
+
+    /// Same as `output`, with extra environment variables
+    pub fn output_with_env(
+        &self,
+        command: Vec<String>,
+        env: &BTreeMap<String, String>,
+    ) -> Result<Output, Box<dyn Error>> {
+        let child = self.run_with_env(command, env)?;
+
+        match child.wait_with_output() {
+            Ok(output) => Ok(output),
+            Err(err) => Err(format!("(executor::output) {}", err).into()),
+        }
     }
 }
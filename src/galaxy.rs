@@ -0,0 +1,267 @@
+use crate::executor::{verify_binary, DefaultExecutor};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::process::Child;
+
+/// Kind of content managed by ansible-galaxy
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnsibleGalaxyType {
+    Role,
+    Collection,
+}
+
+impl AnsibleGalaxyType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AnsibleGalaxyType::Role => "role",
+            AnsibleGalaxyType::Collection => "collection",
+        }
+    }
+}
+
+/// Action to perform on ansible-galaxy content
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnsibleGalaxyAction {
+    Install,
+    List,
+    Build,
+}
+
+impl AnsibleGalaxyAction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AnsibleGalaxyAction::Install => "install",
+            AnsibleGalaxyAction::List => "list",
+            AnsibleGalaxyAction::Build => "build",
+        }
+    }
+}
+
+/// Parameters described on ansible-galaxy's man page for the `install`,
+/// `list` and `build` actions of roles and collections.
+#[derive(Debug, Default, Clone)]
+pub struct AnsibleGalaxyOptions {
+    pub force: bool, // force overwriting an existing role, collection or artifact
+    pub force_with_deps: bool, // force overwriting an existing role or collection and its dependencies
+    pub format: String,        // format to display the list output in (human, json or yaml)
+    pub ignore_errors: bool, // ignore errors during installation and continue with the next specified item
+    pub no_deps: bool,       // don't download roles or collections listed as dependencies
+    pub offline: bool, // install collection artifacts (tarballs) without contacting any distribution servers
+    pub output_path: String, // the path in which the collection is built to
+    pub path: String,  // the path to the directory containing your roles or collections
+    pub requirements_file: String, // a file containing a list of roles or collections to be installed
+}
+
+impl AnsibleGalaxyOptions {
+    const FORCE_FLAG: &str = "--force";
+    const FORCE_WITH_DEPS_FLAG: &str = "--force-with-deps";
+    const FORMAT_FLAG: &str = "--format";
+    const IGNORE_ERRORS_FLAG: &str = "--ignore-errors";
+    const NO_DEPS_FLAG: &str = "--no-deps";
+    const OFFLINE_FLAG: &str = "--offline";
+    const OUTPUT_PATH_FLAG: &str = "--output-path";
+    const PATH_FLAG: &str = "-p";
+    const REQUIREMENTS_FILE_FLAG: &str = "-r";
+
+    /// Returns a list of options flags to be used on ansible-galaxy execution
+    pub fn gen_opts(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut cmd: Vec<String> = Vec::new();
+
+        if self.force {
+            cmd.push(Self::FORCE_FLAG.to_string());
+        }
+
+        if self.force_with_deps {
+            cmd.push(Self::FORCE_WITH_DEPS_FLAG.to_string());
+        }
+
+        if !self.format.is_empty() {
+            cmd.push(Self::FORMAT_FLAG.to_string());
+            cmd.push(self.format.clone());
+        }
+
+        if self.ignore_errors {
+            cmd.push(Self::IGNORE_ERRORS_FLAG.to_string());
+        }
+
+        if self.no_deps {
+            cmd.push(Self::NO_DEPS_FLAG.to_string());
+        }
+
+        if self.offline {
+            cmd.push(Self::OFFLINE_FLAG.to_string());
+        }
+
+        if !self.output_path.is_empty() {
+            cmd.push(Self::OUTPUT_PATH_FLAG.to_string());
+            cmd.push(self.output_path.clone());
+        }
+
+        if !self.path.is_empty() {
+            cmd.push(Self::PATH_FLAG.to_string());
+            cmd.push(self.path.clone());
+        }
+
+        if !self.requirements_file.is_empty() {
+            cmd.push(Self::REQUIREMENTS_FILE_FLAG.to_string());
+            cmd.push(self.requirements_file.clone());
+        }
+
+        Ok(cmd)
+    }
+}
+
+/// Installed collection as reported by `ansible-galaxy collection list`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnsibleGalaxyCollection {
+    pub namespace: String, // collection namespace, eg. `community`
+    pub name: String,      // collection name within its namespace, eg. `general`
+    pub version: String,   // installed version, `*` when the collection has no version
+    pub path: String,      // `ansible_collections` directory the collection was found in
+}
+
+impl AnsibleGalaxyCollection {
+    /// Fully qualified collection name, eg. `community.general`
+    pub fn fqcn(&self) -> String {
+        format!("{}.{}", self.namespace, self.name)
+    }
+}
+
+#[derive(Deserialize)]
+struct AnsibleGalaxyCollectionInfo {
+    version: String,
+}
+
+/// Parse the output of `ansible-galaxy collection list --format json`
+pub fn parse_collection_list(output: &str) -> Result<Vec<AnsibleGalaxyCollection>, Box<dyn Error>> {
+    let paths: BTreeMap<String, BTreeMap<String, AnsibleGalaxyCollectionInfo>> =
+        match serde_json::from_str(output) {
+            Ok(paths) => paths,
+            Err(err) => return Err(format!("(galaxy::parse_collection_list) {}", err).into()),
+        };
+
+    let mut collections = vec![];
+    for (path, entries) in paths {
+        for (fqcn, info) in entries {
+            let (namespace, name) = match fqcn.split_once('.') {
+                Some(parts) => parts,
+                None => {
+                    return Err(format!(
+                        "(galaxy::parse_collection_list) invalid collection name '{}'",
+                        fqcn
+                    )
+                    .into())
+                }
+            };
+
+            collections.push(AnsibleGalaxyCollection {
+                namespace: namespace.to_string(),
+                name: name.to_string(),
+                version: info.version,
+                path: path.clone(),
+            });
+        }
+    }
+
+    Ok(collections)
+}
+
+/// Ansible-galaxy command representation and how to execute it
+#[derive(Debug, Clone)]
+pub struct AnsibleGalaxyCmd {
+    pub binary: String,                 // Ansible galaxy binary
+    pub executor: DefaultExecutor,      // Executor used to run the command
+    pub galaxy_type: AnsibleGalaxyType, // whether roles or collections are managed
+    pub action: AnsibleGalaxyAction,    // action to perform
+    pub args: Vec<String>, // names, tarballs, directories or urls the action applies to
+    pub options: AnsibleGalaxyOptions, // galaxy options
+}
+
+const DEFAULT_ANSIBLE_GALAXY_BINARY: &str = "ansible-galaxy";
+impl Default for AnsibleGalaxyCmd {
+    fn default() -> Self {
+        AnsibleGalaxyCmd {
+            binary: DEFAULT_ANSIBLE_GALAXY_BINARY.into(),
+            executor: DefaultExecutor {},
+            galaxy_type: AnsibleGalaxyType::Collection,
+            action: AnsibleGalaxyAction::Install,
+            args: vec![],
+            options: AnsibleGalaxyOptions {
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl AnsibleGalaxyCmd {
+    const JSON_FORMAT: &str = "json";
+
+    /// run ansible-galaxy
+    pub fn run(&self) -> Result<Child, Box<dyn Error>> {
+        if let Err(err) = verify_binary(&self.binary) {
+            return Err(format!("(galaxy::run) {}", err).into());
+        }
+
+        let command = self.command()?;
+        self.executor.run(command)
+    }
+
+    /// Run `ansible-galaxy collection list --format json`, honouring the
+    /// configured path, and return the installed collections
+    pub fn list_collections(&self) -> Result<Vec<AnsibleGalaxyCollection>, Box<dyn Error>> {
+        if let Err(err) = verify_binary(&self.binary) {
+            return Err(format!("(galaxy::list_collections) {}", err).into());
+        }
+
+        let list = AnsibleGalaxyCmd {
+            galaxy_type: AnsibleGalaxyType::Collection,
+            action: AnsibleGalaxyAction::List,
+            options: AnsibleGalaxyOptions {
+                format: Self::JSON_FORMAT.into(),
+                path: self.options.path.clone(),
+                ..Default::default()
+            },
+            ..self.clone()
+        };
+
+        let output = self.executor.output(list.command()?)?;
+        if !output.status.success() {
+            return Err(format!(
+                "(galaxy::list_collections) {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+
+        parse_collection_list(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// generate command line
+    pub fn command(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        if self.galaxy_type == AnsibleGalaxyType::Role {
+            if self.action == AnsibleGalaxyAction::Build {
+                return Err("(galaxy::command) roles can not be built, only collections".into());
+            }
+            if self.options.offline {
+                return Err(
+                    "(galaxy::command) offline mode is only available for collections".into(),
+                );
+            }
+        }
+
+        let mut cmd = vec![];
+
+        cmd.push(self.binary.clone());
+        cmd.push(self.galaxy_type.as_str().to_string());
+        cmd.push(self.action.as_str().to_string());
+        cmd.append(&mut self.options.gen_opts()?);
+        cmd.append(&mut self.args.clone());
+
+        Ok(cmd)
+    }
+
+    pub fn to_string(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.command()?.join(" "))
+    }
+}
@@ -0,0 +1,197 @@
+use super::expression::to_text;
+use super::{evaluate_expression, is_truthy, Inventory, InventoryVars};
+use crate::variables::VariableResolver;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Groups named after the value of an expression, as `keyed_groups` of the
+/// `constructed` inventory plugin. A string value makes a single group, a
+/// list one group per item, and a dictionary one group per `key_value` pair.
+/// Names are kept as written, as with ansible's default of never transforming
+/// invalid group characters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryKeyedGroup {
+    pub key: String, // expression evaluated for every host, such as `ansible_distribution`
+    pub prefix: String, // prefix of the group names, such as `os`
+    pub separator: String, // separator between the prefix and the value, `_` by default
+    pub parent_group: String, // group the new groups are made children of, if any
+    pub default_value: Option<String>, // value used in place of an empty string, list item or dictionary value
+    pub trailing_separator: bool, // keep the separator after the key of a dictionary entry with an empty value, exclusive with a default value
+    pub leading_separator: bool,  // keep the separator before the value when the prefix is empty
+}
+
+impl Default for InventoryKeyedGroup {
+    fn default() -> Self {
+        Self {
+            key: String::new(),
+            prefix: String::new(),
+            separator: "_".into(),
+            parent_group: String::new(),
+            default_value: None,
+            trailing_separator: true,
+            leading_separator: true,
+        }
+    }
+}
+
+/// Variables and groups built from host variables, as the `constructed`
+/// inventory plugin does
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InventoryConstructedOptions {
+    pub compose: BTreeMap<String, String>, // host variables and the expressions computing them
+    pub groups: BTreeMap<String, String>,  // groups and the conditions hosts are added to them on
+    pub keyed_groups: Vec<InventoryKeyedGroup>, // groups named after expression values
+    pub strict: bool, // fail on expressions which cannot be evaluated, instead of skipping them
+}
+
+impl InventoryKeyedGroup {
+    // names of the groups `value` puts a host in, as ansible's
+    // `_add_host_to_keyed_groups` builds them
+    fn group_names(&self, value: &Value) -> Result<Vec<String>, String> {
+        if self.default_value.is_some() && !self.trailing_separator {
+            return Err("default_value and trailing_separator are mutually exclusive".into());
+        }
+        let or_default = |name: String| match (name.is_empty(), &self.default_value) {
+            (true, Some(default)) => default.clone(),
+            _ => name,
+        };
+
+        let names: Vec<String> = match value {
+            Value::Null => vec![],
+            Value::String(name) => vec![or_default(name.clone())],
+            Value::Array(items) => items.iter().map(|item| or_default(to_text(item))).collect(),
+            Value::Object(map) => map
+                .iter()
+                .map(|(key, value)| match (to_text(value), &self.default_value) {
+                    (value, _) if !value.is_empty() => {
+                        format!("{}{}{}", key, self.separator, value)
+                    }
+                    (_, Some(default)) => format!("{}{}{}", key, self.separator, default),
+                    (_, None) if !self.trailing_separator => key.clone(),
+                    (_, None) => format!("{}{}", key, self.separator),
+                })
+                .collect(),
+            value => {
+                return Err(format!(
+                    "key '{}' is {}, not a string, a list or a dictionary",
+                    self.key,
+                    to_text(value)
+                ))
+            }
+        };
+
+        let separator = if self.prefix.is_empty() && !self.leading_separator {
+            ""
+        } else {
+            self.separator.as_str()
+        };
+        names
+            .into_iter()
+            .map(
+                |name| match format!("{}{}{}", self.prefix, separator, name) {
+                    name if name.is_empty() => {
+                        Err(format!("key '{}' makes an empty group name", self.key))
+                    }
+                    name => Ok(name),
+                },
+            )
+            .collect()
+    }
+}
+
+impl Inventory {
+    // variables expressions are evaluated with for `host`: its group and host
+    // variables, with the magic `inventory_hostname` and `group_names`
+    fn construct_vars(&self, resolver: &VariableResolver, host: &str) -> InventoryVars {
+        let mut vars: InventoryVars = resolver
+            .resolve_host(host)
+            .map(|resolved| {
+                resolved
+                    .into_iter()
+                    .map(|(name, variable)| (name, variable.value))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let groups: Vec<Value> = self
+            .host_groups(host)
+            .into_iter()
+            .filter(|group| !Inventory::is_implicit_group(group))
+            .map(Value::String)
+            .collect();
+        vars.insert("inventory_hostname".into(), Value::String(host.into()));
+        vars.insert("group_names".into(), Value::Array(groups));
+
+        vars
+    }
+
+    /// Add the variables and groups of `options` to every host, as the
+    /// `constructed` inventory plugin does. Composed variables are computed
+    /// first, from the variables the host had, then conditional groups and
+    /// keyed groups see them. Unless `strict` is set, expressions which
+    /// cannot be evaluated for a host are skipped.
+    pub fn construct(
+        &mut self,
+        options: &InventoryConstructedOptions,
+    ) -> Result<(), Box<dyn Error>> {
+        let hosts = self.host_names();
+        let fail = |host: &str, err: String| -> Result<(), Box<dyn Error>> {
+            match options.strict {
+                true => Err(format!("(inventory::construct) host '{}': {}", host, err).into()),
+                false => Ok(()),
+            }
+        };
+
+        let resolver = VariableResolver {
+            inventory: self.clone(),
+            ..Default::default()
+        };
+        for host in &hosts {
+            let vars = self.construct_vars(&resolver, host);
+            for (name, expression) in &options.compose {
+                match evaluate_expression(expression, &vars) {
+                    Ok(value) => self.set_host_var(host, name, value),
+                    Err(err) => fail(host, err.to_string())?,
+                }
+            }
+        }
+
+        let resolver = VariableResolver {
+            inventory: self.clone(),
+            ..Default::default()
+        };
+        for host in &hosts {
+            let vars = self.construct_vars(&resolver, host);
+            for (group, condition) in &options.groups {
+                match evaluate_expression(condition, &vars) {
+                    Ok(value) if is_truthy(&value) => self.add_host_to_group(group, host),
+                    Ok(_) => {}
+                    Err(err) => fail(host, err.to_string())?,
+                }
+            }
+
+            for keyed in &options.keyed_groups {
+                let names = evaluate_expression(&keyed.key, &vars)
+                    .map_err(|err| err.to_string())
+                    .and_then(|value| keyed.group_names(&value));
+                let names = match names {
+                    Ok(names) => names,
+                    Err(err) => {
+                        fail(host, err)?;
+                        continue;
+                    }
+                };
+
+                for name in names {
+                    self.add_host_to_group(&name, host);
+                    if !keyed.parent_group.is_empty() {
+                        self.add_child_group(&keyed.parent_group, &name);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
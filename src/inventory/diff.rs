@@ -0,0 +1,243 @@
+use super::{Inventory, InventoryGroup, InventoryVars};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Change of a variable, or of a value nested in a variable
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryVarChange {
+    pub path: String, // variable name, followed by the keys and indexes of nested values
+    pub before: Option<Value>, // previous value, `None` when it was added
+    pub after: Option<Value>, // new value, `None` when it was removed
+}
+
+impl fmt::Display for InventoryVarChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.before, &self.after) {
+            (None, Some(after)) => write!(f, "+ {}: {}", self.path, after),
+            (Some(before), None) => write!(f, "- {}: {}", self.path, before),
+            (Some(before), Some(after)) => write!(f, "~ {}: {} -> {}", self.path, before, after),
+            (None, None) => write!(f, "  {}", self.path),
+        }
+    }
+}
+
+/// Hosts and child groups which joined or left a group
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InventoryGroupChange {
+    pub group: String,                 // group whose members changed
+    pub added_hosts: Vec<String>,      // hosts which joined the group
+    pub removed_hosts: Vec<String>,    // hosts which left the group
+    pub added_children: Vec<String>,   // child groups added to the group
+    pub removed_children: Vec<String>, // child groups removed from the group
+}
+
+/// Variable changes of a host or a group
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryVarsChange {
+    pub name: String,                     // host or group whose variables changed
+    pub changes: Vec<InventoryVarChange>, // changed values, in path order
+}
+
+/// Semantic differences between two inventories
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InventoryDiff {
+    pub added_hosts: Vec<String>,    // hosts only found in the new inventory
+    pub removed_hosts: Vec<String>,  // hosts only found in the old inventory
+    pub added_groups: Vec<String>,   // groups only found in the new inventory
+    pub removed_groups: Vec<String>, // groups only found in the old inventory
+    pub membership: Vec<InventoryGroupChange>, // groups whose hosts or children changed
+    pub host_vars: Vec<InventoryVarsChange>, // variable changes of hosts found in both
+    pub group_vars: Vec<InventoryVarsChange>, // variable changes of groups found in both, and of the implicit ones
+}
+
+impl InventoryDiff {
+    /// Returns whether both inventories are the same
+    pub fn is_empty(&self) -> bool {
+        self.added_hosts.is_empty()
+            && self.removed_hosts.is_empty()
+            && self.added_groups.is_empty()
+            && self.removed_groups.is_empty()
+            && self.membership.is_empty()
+            && self.host_vars.is_empty()
+            && self.group_vars.is_empty()
+    }
+}
+
+impl fmt::Display for InventoryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for host in &self.added_hosts {
+            writeln!(f, "+ host {}", host)?;
+        }
+        for host in &self.removed_hosts {
+            writeln!(f, "- host {}", host)?;
+        }
+        for group in &self.added_groups {
+            writeln!(f, "+ group {}", group)?;
+        }
+        for group in &self.removed_groups {
+            writeln!(f, "- group {}", group)?;
+        }
+
+        for change in &self.membership {
+            let members: Vec<String> = [
+                ("+host ", &change.added_hosts),
+                ("-host ", &change.removed_hosts),
+                ("+child ", &change.added_children),
+                ("-child ", &change.removed_children),
+            ]
+            .iter()
+            .flat_map(|(prefix, names)| names.iter().map(move |name| format!("{}{}", prefix, name)))
+            .collect();
+            writeln!(f, "~ group {}: {}", change.group, members.join(", "))?;
+        }
+
+        for (kind, changes) in [("host", &self.host_vars), ("group", &self.group_vars)] {
+            for vars in changes {
+                writeln!(f, "~ {} {} vars:", kind, vars.name)?;
+                for change in &vars.changes {
+                    writeln!(f, "    {}", change)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// path of a key nested in `parent`, keys which are not identifiers being quoted
+fn key_path(parent: &str, key: &str) -> String {
+    let identifier = !key.is_empty()
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !key.starts_with(|c: char| c.is_ascii_digit());
+    match (parent.is_empty(), identifier) {
+        (true, _) => key.to_string(),
+        (false, true) => format!("{}.{}", parent, key),
+        (false, false) => format!("{}[{}]", parent, Value::from(key)),
+    }
+}
+
+// changes between two values, dictionaries and lists of the same length being
+// compared item by item
+fn diff_values(path: &str, before: &Value, after: &Value, changes: &mut Vec<InventoryVarChange>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+            for key in keys {
+                let path = key_path(path, key);
+                match (before.get(key), after.get(key)) {
+                    (Some(b), Some(a)) => diff_values(&path, b, a, changes),
+                    (b, a) => changes.push(InventoryVarChange {
+                        path,
+                        before: b.cloned(),
+                        after: a.cloned(),
+                    }),
+                }
+            }
+        }
+        (Value::Array(b), Value::Array(a)) if b.len() == a.len() => {
+            for (index, (b, a)) in b.iter().zip(a).enumerate() {
+                diff_values(&format!("{}[{}]", path, index), b, a, changes);
+            }
+        }
+        (before, after) if before != after => changes.push(InventoryVarChange {
+            path: path.to_string(),
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }),
+        _ => {}
+    }
+}
+
+/// Returns the changes between two sets of variables, nested values being
+/// compared recursively
+pub fn diff_vars(before: &InventoryVars, after: &InventoryVars) -> Vec<InventoryVarChange> {
+    let before = Value::Object(before.clone().into_iter().collect());
+    let after = Value::Object(after.clone().into_iter().collect());
+
+    let mut changes = vec![];
+    diff_values("", &before, &after, &mut changes);
+
+    changes
+}
+
+// names of `after` missing from `before`
+fn missing(before: &[String], after: &[String]) -> Vec<String> {
+    after
+        .iter()
+        .filter(|name| !before.contains(name))
+        .cloned()
+        .collect()
+}
+
+impl Inventory {
+    /// Returns the semantic differences between the inventory and `other`,
+    /// the inventory being the old revision
+    pub fn diff(&self, other: &Inventory) -> InventoryDiff {
+        let mut diff = InventoryDiff::default();
+
+        for host in self.hosts.keys() {
+            if !other.hosts.contains_key(host) {
+                diff.removed_hosts.push(host.clone());
+            }
+        }
+        for (host, definition) in &other.hosts {
+            match self.hosts.get(host) {
+                None => diff.added_hosts.push(host.clone()),
+                Some(previous) => {
+                    let changes = diff_vars(&previous.vars, &definition.vars);
+                    if !changes.is_empty() {
+                        diff.host_vars.push(InventoryVarsChange {
+                            name: host.clone(),
+                            changes,
+                        });
+                    }
+                }
+            }
+        }
+
+        let groups: BTreeSet<&String> = self.groups.keys().chain(other.groups.keys()).collect();
+        let empty = InventoryGroup::default();
+        for group in groups {
+            let before = self.groups.get(group);
+            let after = other.groups.get(group);
+            // implicit groups are only defined to hold variables
+            let implicit = Self::is_implicit_group(group);
+            match (before, after) {
+                (Some(_), None) if !implicit => diff.removed_groups.push(group.clone()),
+                (None, Some(_)) if !implicit => diff.added_groups.push(group.clone()),
+                _ => {
+                    let before = before.unwrap_or(&empty);
+                    let after = after.unwrap_or(&empty);
+                    let changes = diff_vars(&before.vars, &after.vars);
+                    if !changes.is_empty() {
+                        diff.group_vars.push(InventoryVarsChange {
+                            name: group.clone(),
+                            changes,
+                        });
+                    }
+                }
+            }
+            let before = before.unwrap_or(&empty);
+            let after = after.unwrap_or(&empty);
+
+            let change = InventoryGroupChange {
+                group: group.clone(),
+                added_hosts: missing(&before.hosts, &after.hosts),
+                removed_hosts: missing(&after.hosts, &before.hosts),
+                added_children: missing(&before.children, &after.children),
+                removed_children: missing(&after.children, &before.children),
+            };
+            if change
+                != (InventoryGroupChange {
+                    group: group.clone(),
+                    ..Default::default()
+                })
+            {
+                diff.membership.push(change);
+            }
+        }
+
+        diff
+    }
+}
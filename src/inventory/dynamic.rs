@@ -0,0 +1,299 @@
+use super::Inventory;
+use crate::executor::create_temp_dir;
+use serde_json::json;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[cfg(unix)]
+use std::io::{BufRead, BufReader};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(unix)]
+use std::thread::{self, JoinHandle};
+
+/// Closure providing the inventory of a dynamic inventory
+pub type InventoryProvider =
+    Arc<dyn Fn() -> Result<Inventory, Box<dyn Error + Send + Sync>> + Send + Sync>;
+
+/// How the inventory script shim gets the inventory from the current process
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicInventoryTransport {
+    File,   // the inventory is rendered once, before the run, to JSON files read by the shim
+    Socket, // the shim requests the inventory over a unix socket, the provider being called on every request
+}
+
+/// Inventory provided by Rust code, exposed to ansible through a generated
+/// script implementing the `--list` and `--host` protocol of script
+/// inventories
+#[derive(Clone)]
+pub struct DynamicInventory {
+    pub provider: InventoryProvider, // closure returning the inventory
+    pub transport: DynamicInventoryTransport, // how the shim gets the inventory
+    pub interpreter: String,         // python interpreter running the shim of the socket transport
+}
+
+impl fmt::Debug for DynamicInventory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicInventory")
+            .field("transport", &self.transport)
+            .field("interpreter", &self.interpreter)
+            .finish_non_exhaustive()
+    }
+}
+
+const DEFAULT_SHIM_INTERPRETER: &str = "/usr/bin/env python3";
+const LIST_FILE: &str = "list.json";
+
+// single quoted shell word
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// executable file only accessible by the current user
+fn write_executable(path: &Path, content: &str) -> Result<(), Box<dyn Error>> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o700);
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(content.as_bytes())?;
+
+    Ok(())
+}
+
+fn script_file_shim(directory: &Path, hosts: &[String]) -> String {
+    let mut shim = String::from(
+        "#!/bin/sh\n# ansible inventory script generated by rs-ansible\ncase \"$1\" in\n",
+    );
+    shim.push_str(&format!(
+        "--list) cat {} ;;\n",
+        shell_quote(&directory.join(LIST_FILE).to_string_lossy())
+    ));
+    shim.push_str("--host)\n    case \"$2\" in\n");
+    for (index, host) in hosts.iter().enumerate() {
+        let file = directory.join(format!("host-{}.json", index));
+        shim.push_str(&format!(
+            "    {}) cat {} ;;\n",
+            shell_quote(host),
+            shell_quote(&file.to_string_lossy())
+        ));
+    }
+    shim.push_str("    *) echo '{}' ;;\n    esac ;;\n");
+    shim.push_str("*) echo \"usage: $0 --list | --host <hostname>\" >&2; exit 1 ;;\nesac\n");
+
+    shim
+}
+
+fn script_socket_shim(interpreter: &str, socket: &Path) -> String {
+    let socket = json!(socket.to_string_lossy()).to_string();
+    format!(
+        "#!{}
+# ansible inventory script generated by rs-ansible
+import socket
+import sys
+
+if len(sys.argv) == 2 and sys.argv[1] == '--list':
+    request = 'list'
+elif len(sys.argv) == 3 and sys.argv[1] == '--host':
+    request = 'host ' + sys.argv[2]
+else:
+    sys.stderr.write('usage: %s --list | --host <hostname>\\n' % sys.argv[0])
+    sys.exit(1)
+
+client = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+client.connect({})
+client.sendall((request + '\\n').encode('utf-8'))
+client.shutdown(socket.SHUT_WR)
+response = b''
+while True:
+    chunk = client.recv(65536)
+    if not chunk:
+        break
+    response += chunk
+
+status, _, body = response.decode('utf-8').partition('\\n')
+if status != 'ok':
+    sys.stderr.write(body + '\\n')
+    sys.exit(1)
+sys.stdout.write(body)
+",
+        interpreter, socket
+    )
+}
+
+// answers a `list` or `host <name>` request of the socket shim
+fn answer(provider: &InventoryProvider, request: &str) -> String {
+    let inventory = match provider() {
+        Ok(inventory) => inventory,
+        Err(err) => return format!("error\n{}", err),
+    };
+
+    let body = match request.split_once(' ') {
+        None if request == "list" => inventory.to_script_value(),
+        Some(("host", host)) => match inventory.hosts.get(host) {
+            Some(definition) => json!(definition.vars),
+            None => json!({}),
+        },
+        _ => return format!("error\ninvalid request '{}'", request),
+    };
+
+    format!("ok\n{}", body)
+}
+
+#[cfg(unix)]
+struct InventoryServer {
+    socket: PathBuf,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+#[cfg(unix)]
+impl InventoryServer {
+    fn start(socket: PathBuf, provider: InventoryProvider) -> Result<Self, Box<dyn Error>> {
+        let listener = UnixListener::bind(&socket)?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let stopped = stop.clone();
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+
+                let mut request = String::new();
+                if BufReader::new(&stream).read_line(&mut request).is_ok() {
+                    let response = answer(&provider, request.trim_end());
+                    let _ = stream.write_all(response.as_bytes());
+                }
+            }
+        });
+
+        Ok(InventoryServer {
+            socket,
+            stop,
+            handle,
+        })
+    }
+
+    fn shutdown(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // wake the listener up so it notices it has to stop
+        let _ = UnixStream::connect(&self.socket);
+        let _ = self.handle.join();
+    }
+}
+
+/// Inventory script generated for a dynamic inventory, along with what it
+/// needs to answer. Files are removed, and the socket closed, when dropped.
+pub struct DynamicInventoryShim {
+    pub path: PathBuf, // executable to pass as `--inventory`
+    directory: PathBuf,
+    #[cfg(unix)]
+    server: Option<InventoryServer>,
+}
+
+impl fmt::Debug for DynamicInventoryShim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicInventoryShim")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for DynamicInventoryShim {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(server) = self.server.take() {
+            server.shutdown();
+        }
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
+
+impl DynamicInventory {
+    pub fn new<F>(provider: F) -> Self
+    where
+        F: Fn() -> Result<Inventory, Box<dyn Error + Send + Sync>> + Send + Sync + 'static,
+    {
+        DynamicInventory {
+            provider: Arc::new(provider),
+            transport: DynamicInventoryTransport::File,
+            interpreter: DEFAULT_SHIM_INTERPRETER.to_string(),
+        }
+    }
+
+    fn write_shim(&self, directory: &Path) -> Result<DynamicInventoryShim, Box<dyn Error>> {
+        let path = directory.join("inventory");
+        let mut shim = DynamicInventoryShim {
+            path: path.clone(),
+            directory: directory.to_path_buf(),
+            #[cfg(unix)]
+            server: None,
+        };
+
+        match self.transport {
+            DynamicInventoryTransport::File => {
+                let inventory = match (self.provider)() {
+                    Ok(inventory) => inventory,
+                    Err(err) => return Err(err.to_string().into()),
+                };
+
+                fs::write(
+                    directory.join(LIST_FILE),
+                    inventory.to_script_value().to_string(),
+                )?;
+                let hosts = inventory.host_names();
+                for (index, host) in hosts.iter().enumerate() {
+                    fs::write(
+                        directory.join(format!("host-{}.json", index)),
+                        json!(inventory.hosts[host].vars).to_string(),
+                    )?;
+                }
+
+                write_executable(&path, &script_file_shim(directory, &hosts))?;
+            }
+            #[cfg(unix)]
+            DynamicInventoryTransport::Socket => {
+                let socket = directory.join("inventory.sock");
+                shim.server = Some(InventoryServer::start(
+                    socket.clone(),
+                    self.provider.clone(),
+                )?);
+                write_executable(&path, &script_socket_shim(&self.interpreter, &socket))?;
+            }
+            #[cfg(not(unix))]
+            DynamicInventoryTransport::Socket => {
+                return Err("the socket transport is only available on unix".into())
+            }
+        }
+
+        Ok(shim)
+    }
+
+    /// Generate the inventory script, within a new temporary directory
+    pub fn materialize(&self) -> Result<DynamicInventoryShim, Box<dyn Error>> {
+        let directory = create_temp_dir("dynamic-inventory")?;
+
+        match self.write_shim(&directory) {
+            Ok(shim) => Ok(shim),
+            Err(err) => {
+                let _ = fs::remove_dir_all(&directory);
+                Err(format!("(inventory::materialize) {}", err).into())
+            }
+        }
+    }
+}
@@ -0,0 +1,997 @@
+use super::render::python_literal;
+use super::InventoryVars;
+use regex::Regex;
+use serde_json::{Map, Number, Value};
+use std::error::Error;
+
+// operators, the longest ones first so they are matched before their prefixes
+const OPERATORS: [&str; 24] = [
+    "//", "**", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "~", "(", ")", "[", "]",
+    "{", "}", ",", ":", ".", "|",
+];
+
+// longest string a `*` repetition may build, in bytes
+const MAX_REPEAT_LEN: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Name(String),
+    Op(&'static str),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Var(String),
+    List(Vec<Expr>),
+    Dict(Vec<(Expr, Expr)>),
+    Attr(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Method(Box<Expr>, String, Vec<Expr>),
+    Filter(Box<Expr>, String, Vec<Expr>),
+    Test(Box<Expr>, String, Vec<Expr>, bool),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+}
+
+// undefined variables are told apart, as `default` and `is defined` accept them
+#[derive(Debug)]
+enum EvalError {
+    Undefined(String),
+    Invalid(String),
+}
+
+type EvalResult = Result<Value, EvalError>;
+
+fn invalid<T>(message: impl Into<String>) -> Result<T, EvalError> {
+    Err(EvalError::Invalid(message.into()))
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '_') {
+                i += 1;
+            }
+            let float = i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_ascii_digit();
+            if float {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let literal: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+            tokens.push(match float {
+                true => Token::Float(literal.parse().map_err(|_| "invalid number")?),
+                false => Token::Int(literal.parse().map_err(|_| "invalid number")?),
+            });
+        } else if c == '\'' || c == '"' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err("unterminated string".into()),
+                    Some(q) if *q == c => break,
+                    Some('\\') => {
+                        i += 1;
+                        match chars.get(i) {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some(escaped) => value.push(*escaped),
+                            None => return Err("unterminated string".into()),
+                        }
+                    }
+                    Some(c) => value.push(*c),
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(Token::Str(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            match OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+                Some(op) => {
+                    tokens.push(Token::Op(op));
+                    i += op.len();
+                }
+                None => return Err(format!("unexpected character '{}'", c)),
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is_op(&self, op: &str) -> bool {
+        matches!(self.peek(), Some(Token::Op(o)) if *o == op)
+    }
+
+    fn is_name(&self, name: &str) -> bool {
+        matches!(self.peek(), Some(Token::Name(n)) if n == name)
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Op(o)) if o == op => Ok(()),
+            Some(token) => Err(format!("expected '{}', got {:?}", op, token)),
+            None => Err(format!("expected '{}', got end of expression", op)),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Name(name)) => Ok(name),
+            Some(token) => Err(format!("expected a name, got {:?}", token)),
+            None => Err("expected a name, got end of expression".into()),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let value = self.or()?;
+        if !self.is_name("if") {
+            return Ok(value);
+        }
+
+        self.pos += 1;
+        let condition = self.or()?;
+        let otherwise = if self.is_name("else") {
+            self.pos += 1;
+            Some(Box::new(self.expression()?))
+        } else {
+            None
+        };
+
+        Ok(Expr::Conditional(
+            Box::new(condition),
+            Box::new(value),
+            otherwise,
+        ))
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.is_name("or") {
+            self.pos += 1;
+            left = Expr::Binary("or", Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.not()?;
+        while self.is_name("and") {
+            self.pos += 1;
+            left = Expr::Binary("and", Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.is_name("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.compare()
+    }
+
+    fn compare(&mut self) -> Result<Expr, String> {
+        let mut left = self.concat()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op(op)) if ["==", "!=", "<", "<=", ">", ">="].contains(op) => *op,
+                Some(Token::Name(name)) if name == "in" => "in",
+                Some(Token::Name(name))
+                    if name == "not"
+                        && matches!(self.tokens.get(self.pos + 1), Some(Token::Name(n)) if n == "in") =>
+                {
+                    self.pos += 1;
+                    "not in"
+                }
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.concat()?));
+        }
+    }
+
+    fn concat(&mut self) -> Result<Expr, String> {
+        let mut left = self.sum()?;
+        while self.is_op("~") {
+            self.pos += 1;
+            left = Expr::Binary("~", Box::new(left), Box::new(self.sum()?));
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut left = self.product()?;
+        while let Some(Token::Op(op @ ("+" | "-"))) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.product()?));
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while let Some(Token::Op(op @ ("*" | "/" | "//" | "%"))) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.is_op("-") {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.is_op("+") {
+            self.pos += 1;
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.postfix()?;
+        if self.is_op("**") {
+            self.pos += 1;
+            return Ok(Expr::Binary("**", Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Expr>, String> {
+        let mut arguments = vec![];
+        if !self.is_op("(") {
+            return Ok(arguments);
+        }
+
+        self.pos += 1;
+        while !self.is_op(")") {
+            arguments.push(self.expression()?);
+            if !self.is_op(")") {
+                self.expect_op(",")?;
+            }
+        }
+        self.pos += 1;
+
+        Ok(arguments)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut value = self.primary()?;
+        loop {
+            if self.is_op(".") {
+                self.pos += 1;
+                let name = match self.next() {
+                    Some(Token::Name(name)) => name,
+                    Some(Token::Int(index)) => {
+                        let index = Expr::Literal(Value::from(index));
+                        value = Expr::Index(Box::new(value), Box::new(index));
+                        continue;
+                    }
+                    _ => return Err("expected an attribute name after '.'".into()),
+                };
+                value = if self.is_op("(") {
+                    Expr::Method(Box::new(value), name, self.arguments()?)
+                } else {
+                    Expr::Attr(Box::new(value), name)
+                };
+            } else if self.is_op("[") {
+                self.pos += 1;
+                let index = self.expression()?;
+                self.expect_op("]")?;
+                value = Expr::Index(Box::new(value), Box::new(index));
+            } else if self.is_op("|") {
+                self.pos += 1;
+                let name = self.name()?;
+                value = Expr::Filter(Box::new(value), name, self.arguments()?);
+            } else if self.is_name("is") {
+                self.pos += 1;
+                let negated = self.is_name("not");
+                if negated {
+                    self.pos += 1;
+                }
+                let name = self.name()?;
+                let arguments = match self.peek() {
+                    Some(Token::Op("(")) => self.arguments()?,
+                    Some(Token::Int(_) | Token::Float(_) | Token::Str(_)) => vec![self.primary()?],
+                    _ => vec![],
+                };
+                value = Expr::Test(Box::new(value), name, arguments, negated);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Int(value)) => Ok(Expr::Literal(Value::from(value))),
+            Some(Token::Float(value)) => Ok(Expr::Literal(Value::from(value))),
+            Some(Token::Str(value)) => Ok(Expr::Literal(Value::String(value))),
+            Some(Token::Name(name)) => Ok(match name.as_str() {
+                "true" | "True" => Expr::Literal(Value::Bool(true)),
+                "false" | "False" => Expr::Literal(Value::Bool(false)),
+                "none" | "None" => Expr::Literal(Value::Null),
+                _ => Expr::Var(name),
+            }),
+            Some(Token::Op("(")) => {
+                let value = self.expression()?;
+                if self.is_op(",") {
+                    // tuples are evaluated as lists
+                    let mut items = vec![value];
+                    while self.is_op(",") {
+                        self.pos += 1;
+                        if self.is_op(")") {
+                            break;
+                        }
+                        items.push(self.expression()?);
+                    }
+                    self.expect_op(")")?;
+                    return Ok(Expr::List(items));
+                }
+                self.expect_op(")")?;
+                Ok(value)
+            }
+            Some(Token::Op("[")) => {
+                let mut items = vec![];
+                while !self.is_op("]") {
+                    items.push(self.expression()?);
+                    if !self.is_op("]") {
+                        self.expect_op(",")?;
+                    }
+                }
+                self.pos += 1;
+                Ok(Expr::List(items))
+            }
+            Some(Token::Op("{")) => {
+                let mut items = vec![];
+                while !self.is_op("}") {
+                    let key = self.expression()?;
+                    self.expect_op(":")?;
+                    items.push((key, self.expression()?));
+                    if !self.is_op("}") {
+                        self.expect_op(",")?;
+                    }
+                }
+                self.pos += 1;
+                Ok(Expr::Dict(items))
+            }
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".into()),
+        }
+    }
+}
+
+fn parse(expression: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        pos: 0,
+    };
+    let expr = parser.expression()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {:?}", token)),
+    }
+}
+
+/// Returns whether a value is true for Jinja, as python does: empty strings
+/// and collections, zero and none being false
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+// string a value is rendered to by Jinja
+pub(super) fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => python_literal(value),
+    }
+}
+
+fn float(value: f64) -> EvalResult {
+    match Number::from_f64(value) {
+        Some(number) => Ok(Value::Number(number)),
+        None => invalid("invalid float result"),
+    }
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Bool(a), Value::Number(b)) | (Value::Number(b), Value::Bool(a)) => {
+            b.as_f64() == Some(*a as u8 as f64)
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn compare(op: &str, a: &Value, b: &Value) -> EvalResult {
+    let ordering = match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .unwrap_or_default()
+            .partial_cmp(&b.as_f64().unwrap_or_default()),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    let ordering = match ordering {
+        Some(ordering) => ordering,
+        None => {
+            return invalid(format!(
+                "'{}' not supported between {} and {}",
+                op,
+                to_text(a),
+                to_text(b)
+            ))
+        }
+    };
+
+    Ok(Value::Bool(match op {
+        "<" => ordering.is_lt(),
+        "<=" => ordering.is_le(),
+        ">" => ordering.is_gt(),
+        _ => ordering.is_ge(),
+    }))
+}
+
+fn contains(container: &Value, item: &Value) -> Result<bool, EvalError> {
+    match container {
+        Value::String(s) => Ok(s.contains(&to_text(item))),
+        Value::Array(items) => Ok(items.iter().any(|i| values_equal(i, item))),
+        Value::Object(map) => Ok(map.contains_key(&to_text(item))),
+        value => invalid(format!(
+            "argument of type {} is not iterable",
+            to_text(value)
+        )),
+    }
+}
+
+fn arithmetic(op: &str, a: &Value, b: &Value) -> EvalResult {
+    if let (Some(x), Some(y)) = (a.as_i64(), b.as_i64()) {
+        let result = match op {
+            "+" => x.checked_add(y),
+            "-" => x.checked_sub(y),
+            "*" => x.checked_mul(y),
+            "//" if y != 0 => Some((x as f64 / y as f64).floor() as i64),
+            // the remainder takes the sign of the divisor, as in python
+            "%" if y != 0 => {
+                let r = x.wrapping_rem(y);
+                Some(if r != 0 && (r < 0) != (y < 0) {
+                    r + y
+                } else {
+                    r
+                })
+            }
+            "**" if y >= 0 => u32::try_from(y).ok().and_then(|y| x.checked_pow(y)),
+            _ => None,
+        };
+        if let Some(result) = result {
+            return Ok(Value::from(result));
+        }
+    }
+
+    match (op, a, b) {
+        ("+", Value::String(x), Value::String(y)) => Ok(Value::String(format!("{}{}", x, y))),
+        ("+", Value::Array(x), Value::Array(y)) => {
+            Ok(Value::Array([x.clone(), y.clone()].concat()))
+        }
+        ("*", Value::String(s), n) | ("*", n, Value::String(s)) if n.is_i64() => {
+            let count = usize::try_from(n.as_i64().unwrap_or(0).max(0)).unwrap_or(usize::MAX);
+            match s.len().checked_mul(count) {
+                Some(len) if len <= MAX_REPEAT_LEN => Ok(Value::String(s.repeat(count))),
+                _ => invalid(format!(
+                    "repeated string longer than {} bytes",
+                    MAX_REPEAT_LEN
+                )),
+            }
+        }
+        (_, Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+            if y == 0.0 && ["/", "//", "%"].contains(&op) {
+                return invalid("division by zero");
+            }
+            float(match op {
+                "+" => x + y,
+                "-" => x - y,
+                "*" => x * y,
+                "/" => x / y,
+                "//" => (x / y).floor(),
+                "%" => ((x % y) + y) % y,
+                _ => x.powf(y),
+            })
+        }
+        _ => invalid(format!(
+            "unsupported operand types for {}: {} and {}",
+            op,
+            to_text(a),
+            to_text(b)
+        )),
+    }
+}
+
+fn index(value: &Value, key: &Value, description: &str) -> EvalResult {
+    let undefined = || Err(EvalError::Undefined(description.to_string()));
+    match (value, key) {
+        (Value::Object(map), key) => map.get(&to_text(key)).cloned().map_or_else(undefined, Ok),
+        (Value::Array(items), Value::Number(n)) => {
+            let i = n.as_i64().unwrap_or(0);
+            let i = if i < 0 { items.len() as i64 + i } else { i };
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| items.get(i))
+                .cloned()
+                .map_or_else(undefined, Ok)
+        }
+        (Value::String(s), Value::Number(n)) => {
+            let chars: Vec<char> = s.chars().collect();
+            let i = n.as_i64().unwrap_or(0);
+            let i = if i < 0 { chars.len() as i64 + i } else { i };
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| chars.get(i))
+                .map(|c| Value::String(c.to_string()))
+                .map_or_else(undefined, Ok)
+        }
+        _ => undefined(),
+    }
+}
+
+// python regular expressions refer to groups as `\1`
+fn regex_replacement(replacement: &str) -> String {
+    let backreference = Regex::new(r"\\(\d+)").expect("(inventory::evaluate) backreference regex");
+    backreference
+        .replace_all(&replacement.replace('$', "$$"), "$${$1}")
+        .to_string()
+}
+
+fn regex(pattern: &str) -> Result<Regex, EvalError> {
+    Regex::new(pattern).or_else(|err| invalid(err.to_string()))
+}
+
+fn to_int(value: &Value) -> Value {
+    let int = match value {
+        Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f.trunc() as i64)),
+        Value::Bool(b) => Some(*b as i64),
+        Value::String(s) => s
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .or_else(|| s.trim().parse::<f64>().ok().map(|f| f.trunc() as i64)),
+        _ => None,
+    };
+    Value::from(int.unwrap_or(0))
+}
+
+fn to_bool(value: &Value) -> bool {
+    match value {
+        Value::String(s) => {
+            ["yes", "on", "1", "true", "y", "t"].contains(&s.to_lowercase().as_str())
+        }
+        value => is_truthy(value),
+    }
+}
+
+fn items_of(value: &Value, filter: &str) -> Result<Vec<Value>, EvalError> {
+    match value {
+        Value::Array(items) => Ok(items.clone()),
+        Value::Object(map) => Ok(map.keys().map(|k| Value::String(k.clone())).collect()),
+        Value::String(s) => Ok(s.chars().map(|c| Value::String(c.to_string())).collect()),
+        value => invalid(format!(
+            "{} expects a sequence, got {}",
+            filter,
+            to_text(value)
+        )),
+    }
+}
+
+fn string_arg(arguments: &[Value], index: usize, default: &str) -> String {
+    arguments.get(index).map_or(default.to_string(), to_text)
+}
+
+fn apply_filter(name: &str, value: Value, arguments: &[Value]) -> EvalResult {
+    let text = || to_text(&value);
+    match name {
+        "lower" => Ok(Value::String(text().to_lowercase())),
+        "upper" => Ok(Value::String(text().to_uppercase())),
+        "capitalize" => {
+            let text = text().to_lowercase();
+            let mut chars = text.chars();
+            Ok(Value::String(match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }))
+        }
+        "trim" => Ok(Value::String(text().trim().to_string())),
+        "string" => Ok(Value::String(text())),
+        "int" => Ok(to_int(&value)),
+        "float" => float(match &value {
+            Value::Number(n) => n.as_f64().unwrap_or(0.0),
+            Value::String(s) => s.trim().parse().unwrap_or(0.0),
+            Value::Bool(b) => *b as u8 as f64,
+            _ => 0.0,
+        }),
+        "bool" => Ok(Value::Bool(to_bool(&value))),
+        "length" | "count" => Ok(Value::from(match &value {
+            Value::String(s) => s.chars().count(),
+            Value::Array(items) => items.len(),
+            Value::Object(map) => map.len(),
+            value => return invalid(format!("object of type {} has no length", to_text(value))),
+        })),
+        "join" => Ok(Value::String(
+            items_of(&value, name)?
+                .iter()
+                .map(to_text)
+                .collect::<Vec<_>>()
+                .join(&string_arg(arguments, 0, "")),
+        )),
+        "first" => items_of(&value, name)?
+            .first()
+            .cloned()
+            .map_or_else(|| Err(EvalError::Undefined("first".into())), Ok),
+        "last" => items_of(&value, name)?
+            .last()
+            .cloned()
+            .map_or_else(|| Err(EvalError::Undefined("last".into())), Ok),
+        "list" => Ok(Value::Array(items_of(&value, name)?)),
+        "reverse" => {
+            let mut items = items_of(&value, name)?;
+            items.reverse();
+            Ok(Value::Array(items))
+        }
+        "sort" | "unique" | "min" | "max" => {
+            let mut items = items_of(&value, name)?;
+            items.sort_by(|a, b| {
+                compare("<", a, b)
+                    .ok()
+                    .map(|less| match less {
+                        Value::Bool(true) => std::cmp::Ordering::Less,
+                        _ if values_equal(a, b) => std::cmp::Ordering::Equal,
+                        _ => std::cmp::Ordering::Greater,
+                    })
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            match name {
+                "min" => Ok(items.first().cloned().unwrap_or(Value::Null)),
+                "max" => Ok(items.last().cloned().unwrap_or(Value::Null)),
+                "unique" => {
+                    let mut unique: Vec<Value> = vec![];
+                    for item in items_of(&value, name)? {
+                        if !unique.iter().any(|u| values_equal(u, &item)) {
+                            unique.push(item);
+                        }
+                    }
+                    Ok(Value::Array(unique))
+                }
+                _ => Ok(Value::Array(items)),
+            }
+        }
+        "sum" => items_of(&value, name)?
+            .iter()
+            .try_fold(Value::from(0), |total, item| arithmetic("+", &total, item)),
+        "abs" => match value.as_i64() {
+            Some(n) => Ok(Value::from(n.abs())),
+            None => float(value.as_f64().unwrap_or(0.0).abs()),
+        },
+        "round" => {
+            let precision = arguments.first().and_then(|p| p.as_i64()).unwrap_or(0);
+            let factor = 10f64.powi(precision as i32);
+            float((value.as_f64().unwrap_or(0.0) * factor).round() / factor)
+        }
+        "replace" => Ok(Value::String(
+            text().replace(&string_arg(arguments, 0, ""), &string_arg(arguments, 1, "")),
+        )),
+        "regex_replace" => {
+            let re = regex(&string_arg(arguments, 0, ""))?;
+            let replacement = regex_replacement(&string_arg(arguments, 1, ""));
+            Ok(Value::String(
+                re.replace_all(&text(), replacement.as_str()).to_string(),
+            ))
+        }
+        "regex_search" => {
+            let re = regex(&string_arg(arguments, 0, ""))?;
+            Ok(re
+                .find(&text())
+                .map_or(Value::Null, |m| Value::String(m.as_str().to_string())))
+        }
+        "split" => Ok(Value::Array(match arguments.first() {
+            Some(separator) => text()
+                .split(to_text(separator).as_str())
+                .map(|part| Value::String(part.to_string()))
+                .collect(),
+            None => text()
+                .split_whitespace()
+                .map(|part| Value::String(part.to_string()))
+                .collect(),
+        })),
+        "ternary" => {
+            if value.is_null() && arguments.len() > 2 {
+                return Ok(arguments[2].clone());
+            }
+            let index = if is_truthy(&value) { 0 } else { 1 };
+            Ok(arguments.get(index).cloned().unwrap_or(Value::Null))
+        }
+        "to_json" => Ok(Value::String(value.to_string())),
+        "dict2items" => match value {
+            Value::Object(map) => Ok(Value::Array(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        let mut item = Map::new();
+                        item.insert("key".into(), Value::String(key));
+                        item.insert("value".into(), value);
+                        Value::Object(item)
+                    })
+                    .collect(),
+            )),
+            value => invalid(format!(
+                "dict2items expects a dictionary, got {}",
+                to_text(&value)
+            )),
+        },
+        name => invalid(format!("no filter named '{}'", name)),
+    }
+}
+
+fn apply_test(name: &str, value: &Value, arguments: &[Value]) -> Result<bool, EvalError> {
+    let argument = || arguments.first().cloned().unwrap_or(Value::Null);
+    Ok(match name {
+        "none" => value.is_null(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "float" => value.is_f64(),
+        "boolean" => value.is_boolean(),
+        "mapping" => value.is_object(),
+        "sequence" | "iterable" => value.is_array() || value.is_string() || value.is_object(),
+        "true" => value == &Value::Bool(true),
+        "false" => value == &Value::Bool(false),
+        "truthy" => is_truthy(value),
+        "falsy" => !is_truthy(value),
+        "eq" | "equalto" | "sameas" => values_equal(value, &argument()),
+        "ne" => !values_equal(value, &argument()),
+        "lt" | "le" | "gt" | "ge" => {
+            let op = match name {
+                "lt" => "<",
+                "le" => "<=",
+                "gt" => ">",
+                _ => ">=",
+            };
+            compare(op, value, &argument())? == Value::Bool(true)
+        }
+        "in" => contains(&argument(), value)?,
+        "contains" => contains(value, &argument())?,
+        "match" => regex(&format!("^(?:{})", to_text(&argument())))?.is_match(&to_text(value)),
+        "search" | "regex" => regex(&to_text(&argument()))?.is_match(&to_text(value)),
+        "divisibleby" => match (value.as_i64(), argument().as_i64()) {
+            (Some(n), Some(d)) if d != 0 => n % d == 0,
+            _ => false,
+        },
+        "even" => value.as_i64().is_some_and(|n| n % 2 == 0),
+        "odd" => value.as_i64().is_some_and(|n| n % 2 != 0),
+        name => return invalid(format!("no test named '{}'", name)),
+    })
+}
+
+fn call_method(value: &Value, name: &str, arguments: &[Value]) -> EvalResult {
+    match (value, name) {
+        (Value::String(s), "startswith") => {
+            Ok(Value::Bool(s.starts_with(&string_arg(arguments, 0, ""))))
+        }
+        (Value::String(s), "endswith") => {
+            Ok(Value::Bool(s.ends_with(&string_arg(arguments, 0, ""))))
+        }
+        (Value::String(s), "lower") => Ok(Value::String(s.to_lowercase())),
+        (Value::String(s), "upper") => Ok(Value::String(s.to_uppercase())),
+        (Value::String(s), "strip") => Ok(Value::String(s.trim().to_string())),
+        (Value::String(s), "lstrip") => Ok(Value::String(s.trim_start().to_string())),
+        (Value::String(s), "rstrip") => Ok(Value::String(s.trim_end().to_string())),
+        (Value::String(_), "split" | "replace") => apply_filter(name, value.clone(), arguments),
+        (Value::Object(map), "get") => Ok(map
+            .get(&string_arg(arguments, 0, ""))
+            .cloned()
+            .unwrap_or_else(|| arguments.get(1).cloned().unwrap_or(Value::Null))),
+        (Value::Object(map), "keys") => Ok(Value::Array(
+            map.keys().map(|key| Value::String(key.clone())).collect(),
+        )),
+        (Value::Object(map), "values") => Ok(Value::Array(map.values().cloned().collect())),
+        (Value::Object(map), "items") => Ok(Value::Array(
+            map.iter()
+                .map(|(key, value)| Value::Array(vec![Value::String(key.clone()), value.clone()]))
+                .collect(),
+        )),
+        _ => invalid(format!("no method named '{}' on {}", name, to_text(value))),
+    }
+}
+
+fn evaluate(expr: &Expr, vars: &InventoryVars) -> EvalResult {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Var(name) => vars
+            .get(name)
+            .cloned()
+            .ok_or_else(|| EvalError::Undefined(name.clone())),
+        Expr::List(items) => Ok(Value::Array(
+            items
+                .iter()
+                .map(|item| evaluate(item, vars))
+                .collect::<Result<_, _>>()?,
+        )),
+        Expr::Dict(items) => {
+            let mut map = Map::new();
+            for (key, value) in items {
+                map.insert(to_text(&evaluate(key, vars)?), evaluate(value, vars)?);
+            }
+            Ok(Value::Object(map))
+        }
+        Expr::Attr(value, name) => {
+            index(&evaluate(value, vars)?, &Value::String(name.clone()), name)
+        }
+        Expr::Index(value, key) => {
+            let key = evaluate(key, vars)?;
+            index(&evaluate(value, vars)?, &key, &to_text(&key))
+        }
+        Expr::Method(value, name, arguments) => {
+            let arguments = evaluate_all(arguments, vars)?;
+            call_method(&evaluate(value, vars)?, name, &arguments)
+        }
+        Expr::Filter(value, name, arguments) => {
+            let arguments = evaluate_all(arguments, vars)?;
+            if name == "default" || name == "d" {
+                let fallback = arguments
+                    .first()
+                    .cloned()
+                    .unwrap_or(Value::String(String::new()));
+                let falsy = arguments.get(1).is_some_and(is_truthy);
+                return match evaluate(value, vars) {
+                    Err(EvalError::Undefined(_)) => Ok(fallback),
+                    Ok(value) if falsy && !is_truthy(&value) => Ok(fallback),
+                    result => result,
+                };
+            }
+            apply_filter(name, evaluate(value, vars)?, &arguments)
+        }
+        Expr::Test(value, name, arguments, negated) => {
+            let result = match name.as_str() {
+                "defined" | "undefined" => {
+                    let defined = match evaluate(value, vars) {
+                        Err(EvalError::Undefined(_)) => false,
+                        Err(err) => return Err(err),
+                        Ok(_) => true,
+                    };
+                    defined == (name == "defined")
+                }
+                _ => {
+                    let arguments = evaluate_all(arguments, vars)?;
+                    apply_test(name, &evaluate(value, vars)?, &arguments)?
+                }
+            };
+            Ok(Value::Bool(result != *negated))
+        }
+        Expr::Not(value) => Ok(Value::Bool(!is_truthy(&evaluate(value, vars)?))),
+        Expr::Neg(value) => arithmetic("-", &Value::from(0), &evaluate(value, vars)?),
+        Expr::Binary("and", left, right) => {
+            let left = evaluate(left, vars)?;
+            match is_truthy(&left) {
+                true => evaluate(right, vars),
+                false => Ok(left),
+            }
+        }
+        Expr::Binary("or", left, right) => {
+            let left = evaluate(left, vars)?;
+            match is_truthy(&left) {
+                true => Ok(left),
+                false => evaluate(right, vars),
+            }
+        }
+        Expr::Binary(op, left, right) => {
+            let (left, right) = (evaluate(left, vars)?, evaluate(right, vars)?);
+            match *op {
+                "==" => Ok(Value::Bool(values_equal(&left, &right))),
+                "!=" => Ok(Value::Bool(!values_equal(&left, &right))),
+                "<" | "<=" | ">" | ">=" => compare(op, &left, &right),
+                "in" => Ok(Value::Bool(contains(&right, &left)?)),
+                "not in" => Ok(Value::Bool(!contains(&right, &left)?)),
+                "~" => Ok(Value::String(format!(
+                    "{}{}",
+                    to_text(&left),
+                    to_text(&right)
+                ))),
+                op => arithmetic(op, &left, &right),
+            }
+        }
+        Expr::Conditional(condition, value, otherwise) => {
+            if is_truthy(&evaluate(condition, vars)?) {
+                evaluate(value, vars)
+            } else {
+                match otherwise {
+                    Some(otherwise) => evaluate(otherwise, vars),
+                    None => Err(EvalError::Undefined("conditional without else".into())),
+                }
+            }
+        }
+    }
+}
+
+fn evaluate_all(exprs: &[Expr], vars: &InventoryVars) -> Result<Vec<Value>, EvalError> {
+    exprs.iter().map(|expr| evaluate(expr, vars)).collect()
+}
+
+/// Evaluate a Jinja expression, as found in `{{ }}` or `when`, with `vars`.
+/// Literals, variables, attributes and subscripts, arithmetic, comparison
+/// and logical operators, inline conditionals, the common filters and tests
+/// and a few string and dictionary methods are supported.
+pub fn evaluate_expression(
+    expression: &str,
+    vars: &InventoryVars,
+) -> Result<Value, Box<dyn Error>> {
+    let expr = match parse(expression) {
+        Ok(expr) => expr,
+        Err(err) => {
+            return Err(format!("(inventory::evaluate_expression) {}: {}", expression, err).into())
+        }
+    };
+
+    match evaluate(&expr, vars) {
+        Ok(value) => Ok(value),
+        Err(EvalError::Undefined(name)) => Err(format!(
+            "(inventory::evaluate_expression) {}: '{}' is undefined",
+            expression, name
+        )
+        .into()),
+        Err(EvalError::Invalid(message)) => Err(format!(
+            "(inventory::evaluate_expression) {}: {}",
+            expression, message
+        )
+        .into()),
+    }
+}
@@ -0,0 +1,181 @@
+use super::{Inventory, ALL_GROUP, UNGROUPED_GROUP};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Options of the DOT and Mermaid renderings of an inventory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InventoryGraphOptions {
+    pub collapse_threshold: usize, // groups with more hosts are drawn with a single node counting them, 0 to never collapse
+    pub host_vars: bool,           // list the variables of the hosts in their node
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum NodeKind {
+    Group,
+    Host,
+    Collapsed,
+}
+
+// inventory laid out as nodes, labelled by lines, and edges between them
+#[derive(Debug, Default)]
+struct Graph {
+    nodes: Vec<(NodeKind, Vec<String>)>,
+    edges: Vec<(usize, usize)>,
+}
+
+fn var_line(key: &str, value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{}={}", key, s),
+        value => format!("{}={}", key, value),
+    }
+}
+
+fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_escape(value: &str) -> String {
+    value
+        .replace('&', "#amp;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+impl Inventory {
+    fn graph(&self, options: &InventoryGraphOptions) -> Graph {
+        let mut graph = Graph::default();
+        let mut groups: BTreeMap<String, usize> = BTreeMap::new();
+        let mut hosts: BTreeMap<String, usize> = BTreeMap::new();
+
+        let ungrouped = self.ungrouped_hosts();
+        let mut names = vec![ALL_GROUP.to_string()];
+        if !ungrouped.is_empty() {
+            names.push(UNGROUPED_GROUP.to_string());
+        }
+        names.extend(
+            self.groups
+                .keys()
+                .filter(|name| !Self::is_implicit_group(name))
+                .cloned(),
+        );
+        for name in names {
+            groups.insert(name.clone(), graph.nodes.len());
+            graph.nodes.push((NodeKind::Group, vec![name]));
+        }
+
+        let mut children: Vec<(String, Vec<String>)> = vec![];
+        let mut top_level = self.top_level_groups();
+        if !ungrouped.is_empty() {
+            top_level.insert(0, UNGROUPED_GROUP.to_string());
+        }
+        children.push((ALL_GROUP.to_string(), top_level));
+        for (name, group) in &self.groups {
+            if !Self::is_implicit_group(name) {
+                children.push((name.clone(), group.children.clone()));
+            }
+        }
+        for (parent, names) in children {
+            for child in names {
+                if let Some(child) = groups.get(&child) {
+                    graph.edges.push((groups[&parent], *child));
+                }
+            }
+        }
+
+        let mut members: Vec<(String, Vec<String>)> = vec![];
+        if !ungrouped.is_empty() {
+            members.push((UNGROUPED_GROUP.to_string(), ungrouped));
+        }
+        for (name, group) in &self.groups {
+            if !Self::is_implicit_group(name) && !group.hosts.is_empty() {
+                members.push((name.clone(), group.hosts.clone()));
+            }
+        }
+        for (group, members) in members {
+            let group = groups[&group];
+            if options.collapse_threshold > 0 && members.len() > options.collapse_threshold {
+                graph.edges.push((group, graph.nodes.len()));
+                graph.nodes.push((
+                    NodeKind::Collapsed,
+                    vec![format!("{} hosts", members.len())],
+                ));
+                continue;
+            }
+
+            for host in members {
+                let node = match hosts.get(&host) {
+                    Some(node) => *node,
+                    None => {
+                        let mut label = vec![host.clone()];
+                        if options.host_vars {
+                            if let Some(definition) = self.hosts.get(&host) {
+                                label.extend(
+                                    definition
+                                        .vars
+                                        .iter()
+                                        .map(|(key, value)| var_line(key, value)),
+                                );
+                            }
+                        }
+                        hosts.insert(host, graph.nodes.len());
+                        graph.nodes.push((NodeKind::Host, label));
+                        graph.nodes.len() - 1
+                    }
+                };
+                graph.edges.push((group, node));
+            }
+        }
+
+        graph
+    }
+
+    /// Render the group hierarchy as a Graphviz DOT digraph
+    pub fn to_dot(&self, options: &InventoryGraphOptions) -> String {
+        let graph = self.graph(options);
+        let mut dot = vec!["digraph inventory {".to_string(), "  rankdir=LR;".into()];
+
+        for (index, (kind, label)) in graph.nodes.iter().enumerate() {
+            let shape = match kind {
+                NodeKind::Group => "box",
+                NodeKind::Host => "ellipse",
+                NodeKind::Collapsed => "folder",
+            };
+            let label: Vec<String> = label.iter().map(|line| dot_escape(line)).collect();
+            dot.push(format!(
+                "  n{} [label=\"{}\", shape={}];",
+                index,
+                label.join("\\n"),
+                shape
+            ));
+        }
+        for (from, to) in &graph.edges {
+            dot.push(format!("  n{} -> n{};", from, to));
+        }
+        dot.push("}".into());
+
+        dot.join("\n") + "\n"
+    }
+
+    /// Render the group hierarchy as a Mermaid flowchart
+    pub fn to_mermaid(&self, options: &InventoryGraphOptions) -> String {
+        let graph = self.graph(options);
+        let mut mermaid = vec!["flowchart LR".to_string()];
+
+        for (index, (kind, label)) in graph.nodes.iter().enumerate() {
+            let label: Vec<String> = label.iter().map(|line| mermaid_escape(line)).collect();
+            let label = label.join("<br/>");
+            let node = match kind {
+                NodeKind::Group => format!("[\"{}\"]", label),
+                NodeKind::Host => format!("([\"{}\"])", label),
+                NodeKind::Collapsed => format!("[/\"{}\"/]", label),
+            };
+            mermaid.push(format!("  n{}{}", index, node));
+        }
+        for (from, to) in &graph.edges {
+            mermaid.push(format!("  n{} --> n{}", from, to));
+        }
+
+        mermaid.join("\n") + "\n"
+    }
+}
@@ -0,0 +1,520 @@
+use super::range::expand_range;
+use super::{Inventory, InventoryParseError, UNGROUPED_GROUP};
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+
+// Parser of the subset of python literals `ast.literal_eval` accepts, which
+// ansible's INI plugin uses to evaluate variable values
+struct LiteralParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl LiteralParser {
+    fn new(source: &str) -> Self {
+        LiteralParser {
+            chars: source.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_blanks(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        self.skip_blanks();
+        if self.peek()? == expected {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn parse(mut self) -> Option<Value> {
+        let value = self.value()?;
+        self.skip_blanks();
+        if self.pos == self.chars.len() {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.skip_blanks();
+        match self.peek()? {
+            '\'' | '"' => self.string().map(Value::String),
+            '[' => self.sequence('[', ']'),
+            '(' => self.sequence('(', ')'),
+            '{' => self.dict(),
+            c if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => self.number(),
+            _ => self.name(),
+        }
+    }
+
+    fn name(&mut self) -> Option<Value> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+
+        match self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .as_str()
+        {
+            "True" => Some(Value::Bool(true)),
+            "False" => Some(Value::Bool(false)),
+            "None" => Some(Value::Null),
+            _ => None,
+        }
+    }
+
+    fn number(&mut self) -> Option<Value> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || "+-._".contains(c))
+        {
+            // a sign is only part of the number at its start or in an exponent
+            if "+-".contains(self.peek()?)
+                && self.pos != start
+                && !"eE".contains(self.chars[self.pos - 1])
+            {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        let literal: String = self.chars[start..self.pos]
+            .iter()
+            .filter(|c| **c != '_')
+            .collect();
+        let (negative, digits) = match literal.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, literal.trim_start_matches('+')),
+        };
+
+        let radix = match digits.get(..2) {
+            Some("0x") | Some("0X") => Some(16),
+            Some("0o") | Some("0O") => Some(8),
+            Some("0b") | Some("0B") => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            let n = i64::from_str_radix(&digits[2..], radix).ok()?;
+            return Some(Value::Number(if negative { -n } else { n }.into()));
+        }
+
+        if digits.chars().all(|c| c.is_ascii_digit()) {
+            // python 3 rejects leading zeros on decimal integers
+            if digits.len() > 1
+                && digits.starts_with('0')
+                && !digits.trim_start_matches('0').is_empty()
+            {
+                return None;
+            }
+            let n: i64 = digits.parse().ok()?;
+            return Some(Value::Number(if negative { -n } else { n }.into()));
+        }
+
+        if !digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || "eE.+-".contains(c))
+        {
+            return None;
+        }
+        let n: f64 = digits.parse().ok()?;
+        Number::from_f64(if negative { -n } else { n }).map(Value::Number)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let quote = self.peek()?;
+        self.pos += 1;
+
+        let mut value = String::new();
+        loop {
+            let c = self.peek()?;
+            self.pos += 1;
+            match c {
+                c if c == quote => break,
+                '\\' => {
+                    let escaped = self.peek()?;
+                    self.pos += 1;
+                    match escaped {
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        'r' => value.push('\r'),
+                        '0' => value.push('\0'),
+                        '\\' | '\'' | '"' => value.push(escaped),
+                        '\n' => {}
+                        other => {
+                            value.push('\\');
+                            value.push(other);
+                        }
+                    }
+                }
+                c => value.push(c),
+            }
+        }
+
+        // adjacent string literals are concatenated
+        self.skip_blanks();
+        if self.peek().is_some_and(|c| c == '\'' || c == '"') {
+            value.push_str(&self.string()?);
+        }
+
+        Some(value)
+    }
+
+    fn sequence(&mut self, open: char, close: char) -> Option<Value> {
+        self.expect(open)?;
+        let mut items = vec![];
+        loop {
+            self.skip_blanks();
+            if self.peek()? == close {
+                self.pos += 1;
+                break;
+            }
+            items.push(self.value()?);
+            self.skip_blanks();
+            match self.peek()? {
+                ',' => self.pos += 1,
+                c if c == close => {}
+                _ => return None,
+            }
+        }
+
+        Some(Value::Array(items))
+    }
+
+    fn dict(&mut self) -> Option<Value> {
+        self.expect('{')?;
+        let mut map = Map::new();
+        loop {
+            self.skip_blanks();
+            if self.peek()? == '}' {
+                self.pos += 1;
+                break;
+            }
+            let key = match self.value()? {
+                Value::String(key) => key,
+                key => key.to_string(),
+            };
+            self.expect(':')?;
+            map.insert(key, self.value()?);
+            self.skip_blanks();
+            match self.peek()? {
+                ',' => self.pos += 1,
+                '}' => {}
+                _ => return None,
+            }
+        }
+
+        Some(Value::Object(map))
+    }
+}
+
+/// Evaluate an INI value the way ansible does: python literals are turned
+/// into their value, anything else is kept as a string
+pub(crate) fn parse_ini_value(value: &str) -> Value {
+    match LiteralParser::new(value).parse() {
+        Some(parsed) => parsed,
+        None => Value::String(value.to_string()),
+    }
+}
+
+/// Split a host line as python's `shlex.split(line, comments=True)` does
+pub(crate) fn shlex_split(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut in_token = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => break,
+            c if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut token));
+                    in_token = false;
+                }
+            }
+            '\'' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => token.push(c),
+                        None => return Err("No closing quotation".into()),
+                    }
+                }
+            }
+            '"' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) if c == '"' || c == '\\' => token.push(c),
+                            Some(c) => {
+                                token.push('\\');
+                                token.push(c);
+                            }
+                            None => return Err("No closing quotation".into()),
+                        },
+                        Some(c) => token.push(c),
+                        None => return Err("No closing quotation".into()),
+                    }
+                }
+            }
+            '\\' => {
+                in_token = true;
+                match chars.next() {
+                    Some(c) => token.push(c),
+                    None => return Err("No escaped character".into()),
+                }
+            }
+            c => {
+                in_token = true;
+                token.push(c);
+            }
+        }
+    }
+
+    if in_token {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Split the port from a host pattern, eg. `web1:2222` or `[::1]:2222`.
+/// Brackets holding a host range are kept as part of the name.
+pub(crate) fn split_host_port(pattern: &str) -> Result<(String, Option<u16>), String> {
+    // bracketed IPv6 address without port
+    if let Some(address) = pattern.strip_prefix('[').and_then(|p| p.strip_suffix(']')) {
+        if address.matches(':').count() > 1 {
+            return Ok((address.to_string(), None));
+        }
+    }
+
+    // colons of host ranges are inside brackets
+    let mut depth = 0;
+    let bare_colons = pattern
+        .chars()
+        .filter(|c| {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                _ => {}
+            }
+            *c == ':' && depth == 0
+        })
+        .count();
+
+    let (name, port) = match pattern.rsplit_once(':') {
+        Some((name, port))
+            if bare_colons == 1 && !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            (name, Some(port))
+        }
+        Some((name, port))
+            if name.starts_with('[')
+                && name.ends_with(']')
+                && !port.is_empty()
+                && port.chars().all(|c| c.is_ascii_digit())
+                && name.contains(':') =>
+        {
+            (&name[1..name.len() - 1], Some(port))
+        }
+        _ => (pattern, None),
+    };
+
+    let port = match port {
+        Some(port) => match port.parse::<u16>() {
+            Ok(port) => Some(port),
+            Err(_) => return Err(format!("Invalid port '{}' for host '{}'", port, name)),
+        },
+        None => None,
+    };
+
+    Ok((name.to_string(), port))
+}
+
+// `[name]` or `[name:type]`, optionally followed by a comment
+fn section_header(line: &str) -> Option<&str> {
+    let (header, rest) = line.strip_prefix('[')?.split_once(']')?;
+    let rest = rest.trim_start();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return None;
+    }
+
+    let name = header.split_once(':').map_or(header, |(name, _)| name);
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ']') {
+        return None;
+    }
+    if let Some((_, kind)) = header.split_once(':') {
+        if !kind.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return None;
+        }
+    }
+
+    Some(header)
+}
+
+#[derive(PartialEq)]
+enum IniSection {
+    Hosts,
+    Vars,
+    Children,
+}
+
+impl Inventory {
+    /// Parse an inventory in ansible's INI format
+    pub fn from_ini(content: &str) -> Result<Inventory, InventoryParseError> {
+        let mut inventory = Inventory::new();
+        let mut group = UNGROUPED_GROUP.to_string();
+        let mut section = IniSection::Hosts;
+        // groups only referenced on `[group:vars]` sections, along with the
+        // line they were referenced on
+        let mut pending: BTreeMap<String, usize> = BTreeMap::new();
+
+        for (index, raw) in content.lines().enumerate() {
+            let number = index + 1;
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(header) = section_header(line) {
+                let (name, kind) = match header.split_once(':') {
+                    Some((name, kind)) => (name, kind),
+                    None => (header, "hosts"),
+                };
+                group = name.to_string();
+                section = match kind {
+                    "hosts" => IniSection::Hosts,
+                    "vars" => IniSection::Vars,
+                    "children" => IniSection::Children,
+                    kind => {
+                        return Err(InventoryParseError::new(
+                            number,
+                            format!("Section [{}] has unknown type: {}", header, kind),
+                        ))
+                    }
+                };
+
+                if section == IniSection::Vars {
+                    if !inventory.groups.contains_key(name) && !Inventory::is_implicit_group(name) {
+                        pending.entry(name.to_string()).or_insert(number);
+                    }
+                } else {
+                    pending.remove(name);
+                    inventory.add_group(name);
+                }
+                continue;
+            } else if line.starts_with('[') && line.ends_with(']') {
+                return Err(InventoryParseError::new(
+                    number,
+                    format!("Invalid section entry: '{}'", line),
+                ));
+            }
+
+            match section {
+                IniSection::Hosts => {
+                    let tokens =
+                        shlex_split(line).map_err(|err| InventoryParseError::new(number, err))?;
+                    let (pattern, vars) = match tokens.split_first() {
+                        Some(tokens) => tokens,
+                        None => continue,
+                    };
+
+                    let (pattern, port) = split_host_port(pattern)
+                        .map_err(|err| InventoryParseError::new(number, err))?;
+                    let hosts = expand_range(&pattern)
+                        .map_err(|err| InventoryParseError::new(number, err.to_string()))?;
+
+                    let mut host_vars = vec![];
+                    if let Some(port) = port {
+                        host_vars.push(("ansible_port".to_string(), Value::from(port)));
+                    }
+                    for var in vars {
+                        match var.split_once('=') {
+                            Some((key, value)) => {
+                                host_vars.push((key.to_string(), parse_ini_value(value)))
+                            }
+                            None => {
+                                return Err(InventoryParseError::new(
+                                    number,
+                                    format!(
+                                        "Expected key=value host variable assignment, got: {}",
+                                        var
+                                    ),
+                                ))
+                            }
+                        }
+                    }
+
+                    for host in hosts {
+                        inventory.add_host_to_group(&group, &host);
+                        for (key, value) in &host_vars {
+                            inventory.define_host_var(&host, key, value.clone(), number);
+                        }
+                    }
+                }
+                IniSection::Vars => match line.split_once('=') {
+                    Some((key, value)) => {
+                        let value = parse_ini_value(value.trim());
+                        inventory
+                            .add_group(&group)
+                            .vars
+                            .insert(key.trim().to_string(), value);
+                    }
+                    None => {
+                        return Err(InventoryParseError::new(
+                            number,
+                            format!("Expected key=value, got: {}", line),
+                        ))
+                    }
+                },
+                IniSection::Children => {
+                    let child = match line.split_once('#') {
+                        Some((child, _)) => child.trim(),
+                        None => line,
+                    };
+                    if child.is_empty() || child.contains(char::is_whitespace) {
+                        return Err(InventoryParseError::new(
+                            number,
+                            format!("Invalid group name in [{}:children]: {}", group, line),
+                        ));
+                    }
+                    pending.remove(child);
+                    inventory.add_child_group(&group, child);
+                }
+            }
+        }
+
+        if let Some((name, number)) = pending.into_iter().next() {
+            return Err(InventoryParseError::new(
+                number,
+                format!(
+                    "Section [{}:vars] not valid for undefined group: {}",
+                    name, name
+                ),
+            ));
+        }
+
+        inventory.normalize_implicit_groups();
+
+        Ok(inventory)
+    }
+}
@@ -0,0 +1,373 @@
+mod constructed;
+mod diff;
+mod dynamic;
+mod expression;
+mod graph;
+mod ini;
+mod pattern;
+mod range;
+mod render;
+mod script;
+mod shard;
+mod source;
+mod ssh_config;
+mod terraform;
+mod validate;
+mod vars;
+mod yaml;
+
+pub use constructed::*;
+pub use diff::*;
+pub use dynamic::*;
+pub use expression::*;
+pub use graph::*;
+pub use pattern::*;
+pub use range::*;
+pub use render::*;
+pub use shard::*;
+pub use source::*;
+pub use terraform::*;
+pub use validate::*;
+pub use vars::*;
+pub(crate) use yaml::yaml_to_json;
+
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Implicit group every host belongs to
+pub const ALL_GROUP: &str = "all";
+/// Implicit group of the hosts which don't belong to any other group
+pub const UNGROUPED_GROUP: &str = "ungrouped";
+
+/// Variables attached to a host or a group
+pub type InventoryVars = BTreeMap<String, Value>;
+
+/// Host of an inventory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InventoryHost {
+    pub vars: InventoryVars, // host variables
+}
+
+/// Group of an inventory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InventoryGroup {
+    pub hosts: Vec<String>, // hosts directly belonging to the group, in definition order
+    pub children: Vec<String>, // child groups, in definition order
+    pub vars: InventoryVars, // group variables
+}
+
+/// In-memory ansible inventory made of hosts, groups, children groups and
+/// their variables. The implicit `all` and `ungrouped` groups don't need to
+/// be defined, though `all` may be used to hold variables set on every host.
+/// Inventories are equal when they hold the same hosts, groups, conflicts and
+/// warnings, whatever the order their hosts were defined in.
+#[derive(Debug, Clone, Default)]
+pub struct Inventory {
+    pub hosts: BTreeMap<String, InventoryHost>,
+    pub groups: BTreeMap<String, InventoryGroup>,
+    pub conflicts: Vec<HostVarConflict>, // host variables redefined with another value while parsing
+    pub warnings: Vec<String>,           // warnings found while parsing, such as skipped keys
+    pub undeclared_groups: BTreeSet<String>, // groups only referenced as children, never declared
+    pub host_order: Vec<String>,         // hosts in definition order, as ansible lists them
+}
+
+impl PartialEq for Inventory {
+    fn eq(&self, other: &Self) -> bool {
+        self.hosts == other.hosts
+            && self.groups == other.groups
+            && self.conflicts == other.conflicts
+            && self.warnings == other.warnings
+    }
+}
+
+/// Host variable given another value by a later definition of the host, the
+/// later value being the one kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostVarConflict {
+    pub host: String,    // host defined more than once
+    pub var: String,     // variable given conflicting values
+    pub previous: Value, // value of the earlier definition
+    pub value: Value,    // value of the later definition
+    pub line: usize,     // 1-based line of the later definition, 0 when it could not be located
+}
+
+/// Error found while parsing an inventory file, along with the line it was
+/// found on
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryParseError {
+    pub line: usize,     // 1-based line of the error, 0 when it could not be located
+    pub message: String, // description of the error
+}
+
+impl InventoryParseError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        InventoryParseError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for InventoryParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for InventoryParseError {}
+
+fn push_unique(list: &mut Vec<String>, item: &str) {
+    if !list.iter().any(|i| i == item) {
+        list.push(item.to_string());
+    }
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Inventory {
+            ..Default::default()
+        }
+    }
+
+    /// Returns the host named `name`, creating it when it does not exist
+    pub fn add_host(&mut self, name: &str) -> &mut InventoryHost {
+        if !self.hosts.contains_key(name) {
+            push_unique(&mut self.host_order, name);
+        }
+        self.hosts.entry(name.to_string()).or_default()
+    }
+
+    /// Returns the hosts in definition order, followed by the ones inserted
+    /// in `hosts` directly
+    pub fn host_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .host_order
+            .iter()
+            .filter(|host| self.hosts.contains_key(*host))
+            .cloned()
+            .collect();
+        for host in self.hosts.keys() {
+            push_unique(&mut names, host);
+        }
+
+        names
+    }
+
+    /// Returns the group named `name`, creating it when it does not exist,
+    /// the group being declared even when it stays empty
+    pub fn add_group(&mut self, name: &str) -> &mut InventoryGroup {
+        self.undeclared_groups.remove(name);
+        self.groups.entry(name.to_string()).or_default()
+    }
+
+    /// Add `host` to `group`, creating both of them when needed
+    pub fn add_host_to_group(&mut self, group: &str, host: &str) {
+        self.add_host(host);
+        push_unique(&mut self.add_group(group).hosts, host);
+    }
+
+    /// Make `child` a child group of `parent`, creating both of them when
+    /// needed. A child group created this way is not declared until it is
+    /// added as a group itself.
+    pub fn add_child_group(&mut self, parent: &str, child: &str) {
+        if !self.groups.contains_key(child) {
+            self.groups
+                .insert(child.to_string(), InventoryGroup::default());
+            self.undeclared_groups.insert(child.to_string());
+        }
+        push_unique(&mut self.add_group(parent).children, child);
+    }
+
+    /// Set the variable `key` on `host`, creating the host when needed
+    pub fn set_host_var(&mut self, host: &str, key: &str, value: Value) {
+        self.add_host(host).vars.insert(key.to_string(), value);
+    }
+
+    // set a host variable found while parsing, recording the conflict when
+    // an earlier definition of the host gave it another value
+    fn define_host_var(&mut self, host: &str, key: &str, value: Value, line: usize) {
+        let previous = self
+            .add_host(host)
+            .vars
+            .insert(key.to_string(), value.clone());
+        if let Some(previous) = previous.filter(|previous| *previous != value) {
+            self.conflicts.push(HostVarConflict {
+                host: host.to_string(),
+                var: key.to_string(),
+                previous,
+                value,
+                line,
+            });
+        }
+    }
+
+    /// Set the variable `key` on `group`, creating the group when needed
+    pub fn set_group_var(&mut self, group: &str, key: &str, value: Value) {
+        self.add_group(group).vars.insert(key.to_string(), value);
+    }
+
+    /// Returns whether `name` is the `all` or `ungrouped` implicit group
+    pub fn is_implicit_group(name: &str) -> bool {
+        name == ALL_GROUP || name == UNGROUPED_GROUP
+    }
+
+    /// Returns the hosts which don't belong to any group but the implicit ones
+    pub fn ungrouped_hosts(&self) -> Vec<String> {
+        let grouped: BTreeSet<&String> = self
+            .groups
+            .iter()
+            .filter(|(name, _)| !Self::is_implicit_group(name))
+            .flat_map(|(_, group)| group.hosts.iter())
+            .collect();
+
+        self.host_names()
+            .into_iter()
+            .filter(|host| !grouped.contains(host))
+            .collect()
+    }
+
+    /// Returns the hosts of `group` and of its descendant groups, in
+    /// definition order
+    pub fn group_hosts(&self, group: &str) -> Vec<String> {
+        match group {
+            ALL_GROUP => self.host_names(),
+            UNGROUPED_GROUP => self.ungrouped_hosts(),
+            _ => {
+                let mut hosts = vec![];
+                let mut visited = BTreeSet::new();
+                self.collect_group_hosts(group, &mut hosts, &mut visited);
+                hosts
+            }
+        }
+    }
+
+    fn collect_group_hosts(
+        &self,
+        group: &str,
+        hosts: &mut Vec<String>,
+        visited: &mut BTreeSet<String>,
+    ) {
+        if !visited.insert(group.to_string()) {
+            return;
+        }
+
+        if let Some(definition) = self.groups.get(group) {
+            for host in &definition.hosts {
+                push_unique(hosts, host);
+            }
+            for child in &definition.children {
+                self.collect_group_hosts(child, hosts, visited);
+            }
+        }
+    }
+
+    /// Returns the parent groups of `group`
+    pub fn parent_groups(&self, group: &str) -> Vec<String> {
+        self.groups
+            .iter()
+            .filter(|(_, definition)| definition.children.iter().any(|child| child == group))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Returns the depth of `group` in the group hierarchy: 0 for `all`, 1 for
+    /// top level groups, and one more than its deepest parent for the others
+    pub fn group_depth(&self, group: &str) -> usize {
+        let mut visited = BTreeSet::new();
+        self.depth_of(group, &mut visited)
+    }
+
+    fn depth_of(&self, group: &str, visited: &mut BTreeSet<String>) -> usize {
+        if group == ALL_GROUP {
+            return 0;
+        }
+        if !visited.insert(group.to_string()) {
+            return 1;
+        }
+
+        let depth = self
+            .parent_groups(group)
+            .iter()
+            .filter(|parent| !Self::is_implicit_group(parent))
+            .map(|parent| self.depth_of(parent, visited) + 1)
+            .max()
+            .unwrap_or(1);
+        visited.remove(group);
+
+        depth
+    }
+
+    /// Returns every group `host` belongs to, directly or through a child
+    /// group, including the implicit ones
+    pub fn host_groups(&self, host: &str) -> Vec<String> {
+        let mut groups = BTreeSet::new();
+        let mut pending: Vec<String> = self
+            .groups
+            .iter()
+            .filter(|(_, definition)| definition.hosts.iter().any(|h| h == host))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        while let Some(group) = pending.pop() {
+            if groups.insert(group.clone()) {
+                pending.append(&mut self.parent_groups(&group));
+            }
+        }
+
+        if self.hosts.contains_key(host) {
+            groups.insert(ALL_GROUP.to_string());
+            if self.ungrouped_hosts().iter().any(|h| h == host) {
+                groups.insert(UNGROUPED_GROUP.to_string());
+            }
+        }
+
+        groups.into_iter().collect()
+    }
+
+    // hosts and children of the implicit groups are already implied by the
+    // model, only their variables are kept
+    fn normalize_implicit_groups(&mut self) {
+        for name in [ALL_GROUP, UNGROUPED_GROUP] {
+            if let Some(group) = self.groups.get_mut(name) {
+                group.hosts.clear();
+                group.children.clear();
+                if group.vars.is_empty() {
+                    self.groups.remove(name);
+                }
+            }
+        }
+    }
+
+    /// Load an inventory file, YAML and JSON ones being recognized by their
+    /// extension. As ansible does, files which are not valid YAML are read as
+    /// INI.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Inventory, Box<dyn Error>> {
+        let path = path.as_ref();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                return Err(format!("(inventory::from_file) {}: {}", path.display(), err).into())
+            }
+        };
+
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        let result = if ["yaml", "yml", "json"].contains(&extension) {
+            Inventory::from_yaml(&content)
+                .or_else(|err| Inventory::from_ini(&content).map_err(|_| err))
+        } else {
+            Inventory::from_ini(&content)
+        };
+
+        match result {
+            Ok(inventory) => Ok(inventory),
+            Err(err) => Err(format!("(inventory::from_file) {}: {}", path.display(), err).into()),
+        }
+    }
+}
@@ -0,0 +1,550 @@
+use super::{Inventory, ALL_GROUP, UNGROUPED_GROUP};
+use regex::Regex;
+use std::error::Error;
+use std::fs;
+
+// hosts ansible adds implicitly when a pattern names them and nothing matches
+const IMPLICIT_LOCALHOST: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+
+/// How a term of a host pattern combines with the hosts selected by the
+/// previous ones
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostPatternOperator {
+    Union,        // `web` on `db:web`, hosts of the term are added
+    Intersection, // `&web` on `db:&web`, only hosts also matched by the term are kept
+    Exclusion,    // `!web` on `db:!web`, hosts matched by the term are removed
+}
+
+/// What a term of a host pattern matches
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostPatternTarget {
+    Name(String),      // group or host name, which may hold `*` and `?` wildcards
+    Regex(String),     // regular expression matched on group and host names, `~regex`
+    RetryFile(String), // file listing hosts, one per line, eg. `@site.retry`
+}
+
+impl From<&str> for HostPatternTarget {
+    fn from(name: &str) -> Self {
+        HostPatternTarget::Name(name.to_string())
+    }
+}
+
+impl From<String> for HostPatternTarget {
+    fn from(name: String) -> Self {
+        HostPatternTarget::Name(name)
+    }
+}
+
+/// Selection of the hosts matched by a term, `web[0]` or `web[0:3]`
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostPatternSubscript {
+    Index(i64),                  // single host, counted from the end when negative
+    Slice(usize, Option<usize>), // hosts from start to end, both inclusive, up to the last host when there is no end
+}
+
+/// Single term of a host pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostPatternTerm {
+    pub operator: HostPatternOperator, // how the term combines with the previous ones
+    pub target: HostPatternTarget,     // what the term matches
+    pub subscript: Option<HostPatternSubscript>, // selection of the matched hosts
+}
+
+impl HostPatternTerm {
+    fn render(&self) -> Result<String, Box<dyn Error>> {
+        let mut term = match self.operator {
+            HostPatternOperator::Union => String::new(),
+            HostPatternOperator::Intersection => "&".to_string(),
+            HostPatternOperator::Exclusion => "!".to_string(),
+        };
+
+        match &self.target {
+            HostPatternTarget::Name(name) => {
+                if name.is_empty() || name.starts_with(['&', '!', '~', '@']) {
+                    return Err(format!("(inventory::to_limit) invalid name '{}'", name).into());
+                }
+                term.push_str(name);
+            }
+            HostPatternTarget::Regex(regex) => {
+                term.push('~');
+                term.push_str(regex);
+            }
+            HostPatternTarget::RetryFile(path) => {
+                if self.operator != HostPatternOperator::Union {
+                    return Err(format!(
+                        "(inventory::to_limit) retry file '{}' can't be intersected or excluded",
+                        path
+                    )
+                    .into());
+                }
+                term.push('@');
+                term.push_str(path);
+            }
+        }
+
+        match (&self.subscript, &self.target) {
+            (None, _) => {}
+            (Some(subscript), HostPatternTarget::Name(_)) => match subscript {
+                HostPatternSubscript::Index(index) => term.push_str(&format!("[{}]", index)),
+                HostPatternSubscript::Slice(start, Some(end)) => {
+                    term.push_str(&format!("[{}:{}]", start, end))
+                }
+                HostPatternSubscript::Slice(start, None) => term.push_str(&format!("[{}:]", start)),
+            },
+            (Some(_), _) => {
+                return Err(format!(
+                    "(inventory::to_limit) subscripts only apply to names, not to '{}'",
+                    term
+                )
+                .into())
+            }
+        }
+
+        Ok(term)
+    }
+}
+
+/// Typed host pattern, as used by `--limit` and the `hosts` of plays, eg.
+/// `HostPattern::new().union("web").intersect("staging").exclude("web1")`
+/// for `web,&staging,!web1`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HostPattern {
+    pub terms: Vec<HostPatternTerm>, // terms, in the order they were written
+}
+
+// whether `pattern` is a single address to ansible's `parse_address`: a host
+// name or IPv4 address, which may hold ranges such as `web[01:20]`, or an IPv6
+// address, with an optional port
+fn is_address(pattern: &str) -> bool {
+    let bracketed_hostport =
+        Regex::new(r"^\[(.+)\]:\d+$").expect("(inventory::parse) bracketed address regex");
+    let hostport =
+        Regex::new(r"^((?:[^:\[\]]|\[[^\]]*\])*):\d+$").expect("(inventory::parse) address regex");
+    let range = r"\[(?:[a-z]:[a-z]|[0-9]+:[0-9]+)(?::[0-9]+)?\]";
+    let label = Regex::new(&format!(r"(?i)^(?:\w|{0})(?:[\w-]|{0})*$", range))
+        .expect("(inventory::parse) host name regex");
+    let hex_range = Regex::new(r"(?i)\[[0-9a-f]+:[0-9a-f]+(?::[0-9]+)?\]")
+        .expect("(inventory::parse) range regex");
+
+    let mut address = pattern;
+    for regex in [&bracketed_hostport, &hostport] {
+        if let Some(captures) = regex.captures(address) {
+            address = captures.get(1).map_or("", |host| host.as_str());
+        }
+    }
+
+    let is_hostname = address
+        .split('.')
+        .all(|name| label.is_match(name) && !name.ends_with(['_', '-']));
+    is_hostname
+        || hex_range
+            .replace_all(address, "0")
+            .parse::<std::net::Ipv6Addr>()
+            .is_ok()
+}
+
+// `web1,web2` is split on commas, and a pattern without comma which is not a
+// single address, such as `fe80::1` or `web1:22`, on colons which are not part
+// of a bracketed subscript
+fn split_host_pattern(pattern: &str) -> Vec<String> {
+    if pattern.contains(',') {
+        return pattern
+            .split(',')
+            .map(|term| term.trim().to_string())
+            .filter(|term| !term.is_empty())
+            .collect();
+    }
+
+    let pattern = pattern.trim();
+    if is_address(pattern) {
+        return vec![pattern.to_string()];
+    }
+
+    let mut terms = vec![];
+    let mut term = String::new();
+    let mut in_brackets = false;
+    for c in pattern.chars() {
+        match c {
+            '[' => in_brackets = true,
+            ']' => in_brackets = false,
+            _ => {}
+        }
+        if !in_brackets && (c == ':' || c.is_whitespace()) {
+            terms.push(std::mem::take(&mut term));
+        } else {
+            term.push(c);
+        }
+    }
+    terms.push(term);
+
+    terms.into_iter().filter(|term| !term.is_empty()).collect()
+}
+
+// `web[0]`, `web[-1]`, `web[0:3]` or `web[1:]`
+fn split_subscript(name: &str) -> (String, Option<HostPatternSubscript>) {
+    let parsed = name
+        .strip_suffix(']')
+        .and_then(|rest| rest.rsplit_once('['))
+        .filter(|(base, _)| !base.is_empty())
+        .and_then(|(base, subscript)| {
+            let subscript = match subscript.split_once(':') {
+                None => HostPatternSubscript::Index(subscript.parse().ok()?),
+                Some((start, "")) => HostPatternSubscript::Slice(start.parse().ok()?, None),
+                Some((start, end)) => {
+                    HostPatternSubscript::Slice(start.parse().ok()?, Some(end.parse().ok()?))
+                }
+            };
+            Some((base.to_string(), subscript))
+        });
+
+    match parsed {
+        Some((base, subscript)) => (base, Some(subscript)),
+        None => (name.to_string(), None),
+    }
+}
+
+// python's `fnmatch.translate`
+fn wildcard_regex(pattern: &str) -> String {
+    let mut regex = String::from("^(?s:");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                let mut class = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == ']' && !class.is_empty() && class != "!" {
+                        closed = true;
+                        break;
+                    }
+                    class.push(c);
+                }
+                if closed {
+                    let class = match class.strip_prefix('!') {
+                        Some(negated) => format!("^{}", negated),
+                        None => class,
+                    };
+                    regex.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+                } else {
+                    regex.push_str("\\[");
+                    regex.push_str(&regex::escape(&class));
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push_str(")$");
+
+    regex
+}
+
+fn apply_subscript(hosts: Vec<String>, subscript: &Option<HostPatternSubscript>) -> Vec<String> {
+    match subscript {
+        None => hosts,
+        Some(HostPatternSubscript::Index(index)) => {
+            let index = if *index < 0 {
+                hosts.len() as i64 + index
+            } else {
+                *index
+            };
+            match usize::try_from(index).ok().and_then(|i| hosts.get(i)) {
+                Some(host) => vec![host.clone()],
+                None => vec![],
+            }
+        }
+        Some(HostPatternSubscript::Slice(start, end)) => {
+            let end = end.map_or(hosts.len(), |end| end + 1).min(hosts.len());
+            hosts
+                .get(*start..end)
+                .map(|h| h.to_vec())
+                .unwrap_or_default()
+        }
+    }
+}
+
+fn push_unique(hosts: &mut Vec<String>, host: String) {
+    if !hosts.contains(&host) {
+        hosts.push(host);
+    }
+}
+
+impl HostPattern {
+    pub fn new() -> Self {
+        HostPattern {
+            ..Default::default()
+        }
+    }
+
+    fn push(mut self, operator: HostPatternOperator, target: HostPatternTarget) -> Self {
+        self.terms.push(HostPatternTerm {
+            operator,
+            target,
+            subscript: None,
+        });
+        self
+    }
+
+    /// Add the hosts matched by `target`
+    pub fn union(self, target: impl Into<HostPatternTarget>) -> Self {
+        self.push(HostPatternOperator::Union, target.into())
+    }
+
+    /// Only keep the hosts also matched by `target`
+    pub fn intersect(self, target: impl Into<HostPatternTarget>) -> Self {
+        self.push(HostPatternOperator::Intersection, target.into())
+    }
+
+    /// Remove the hosts matched by `target`
+    pub fn exclude(self, target: impl Into<HostPatternTarget>) -> Self {
+        self.push(HostPatternOperator::Exclusion, target.into())
+    }
+
+    /// Add the hosts whose name matches the regular expression `regex`
+    pub fn regex(self, regex: &str) -> Self {
+        self.push(
+            HostPatternOperator::Union,
+            HostPatternTarget::Regex(regex.to_string()),
+        )
+    }
+
+    /// Add the hosts listed on the retry file at `path`
+    pub fn retry_file(self, path: &str) -> Self {
+        self.push(
+            HostPatternOperator::Union,
+            HostPatternTarget::RetryFile(path.to_string()),
+        )
+    }
+
+    /// Only keep the host at `index` among the ones matched by the last term
+    pub fn index(mut self, index: i64) -> Self {
+        if let Some(term) = self.terms.last_mut() {
+            term.subscript = Some(HostPatternSubscript::Index(index));
+        }
+        self
+    }
+
+    /// Only keep the hosts from `start` to `end`, both inclusive, among the
+    /// ones matched by the last term
+    pub fn slice(mut self, start: usize, end: Option<usize>) -> Self {
+        if let Some(term) = self.terms.last_mut() {
+            term.subscript = Some(HostPatternSubscript::Slice(start, end));
+        }
+        self
+    }
+
+    /// Parse a host pattern written with ansible's grammar
+    pub fn parse(pattern: &str) -> Result<HostPattern, Box<dyn Error>> {
+        let mut host_pattern = HostPattern::new();
+        for term in split_host_pattern(pattern) {
+            let (operator, target) = if let Some(target) = term.strip_prefix('&') {
+                (HostPatternOperator::Intersection, target)
+            } else if let Some(target) = term.strip_prefix('!') {
+                (HostPatternOperator::Exclusion, target)
+            } else {
+                (HostPatternOperator::Union, term.as_str())
+            };
+
+            let (target, subscript) = if let Some(regex) = target.strip_prefix('~') {
+                (HostPatternTarget::Regex(regex.to_string()), None)
+            } else if let Some(path) = target.strip_prefix('@') {
+                (HostPatternTarget::RetryFile(path.to_string()), None)
+            } else {
+                let (name, subscript) = split_subscript(target);
+                (HostPatternTarget::Name(name), subscript)
+            };
+
+            if target == HostPatternTarget::Name(String::new()) {
+                return Err(format!("(inventory::parse) empty term in '{}'", pattern).into());
+            }
+
+            host_pattern.terms.push(HostPatternTerm {
+                operator,
+                target,
+                subscript,
+            });
+        }
+
+        Ok(host_pattern)
+    }
+
+    /// Render the pattern as a `--limit` value, its terms separated by
+    /// commas. As ansible splits patterns holding commas on commas only,
+    /// terms can't hold one, such as the `{1,3}` of a regex.
+    pub fn to_limit(&self) -> Result<String, Box<dyn Error>> {
+        let mut terms = vec![];
+        for term in &self.terms {
+            let term = term.render()?;
+            if term.contains(',') {
+                return Err(format!("(inventory::to_limit) term '{}' holds a comma", term).into());
+            }
+            terms.push(term);
+        }
+
+        // a single term would be split on its colons, unless followed by a comma
+        match terms.as_slice() {
+            [term] if split_host_pattern(term) != vec![term.clone()] => Ok(format!("{},", term)),
+            _ => Ok(terms.join(",")),
+        }
+    }
+
+    // the host named by a plain term, which ansible resolves to that host
+    // before looking for groups and patterns
+    fn plain_host(inventory: &Inventory, term: &HostPatternTerm) -> Option<String> {
+        match (&term.operator, &term.target, &term.subscript) {
+            (HostPatternOperator::Union, HostPatternTarget::Name(name), None)
+                if inventory.hosts.contains_key(name) =>
+            {
+                Some(name.clone())
+            }
+            _ => None,
+        }
+    }
+
+    // hosts matched by a term, subscript applied
+    fn term_hosts(
+        inventory: &Inventory,
+        term: &HostPatternTerm,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        match Self::plain_host(inventory, term) {
+            Some(host) => Ok(vec![host]),
+            None => Ok(apply_subscript(
+                Self::match_term(inventory, &term.target)?,
+                &term.subscript,
+            )),
+        }
+    }
+
+    // hosts matched by a single term, as ansible's `_enumerate_matches`
+    fn match_term(
+        inventory: &Inventory,
+        target: &HostPatternTarget,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let (expression, name) = match target {
+            HostPatternTarget::Name(name) => (wildcard_regex(name), name.as_str()),
+            HostPatternTarget::Regex(regex) => (format!("^(?:{})", regex), ""),
+            HostPatternTarget::RetryFile(path) => {
+                let content = match fs::read_to_string(path) {
+                    Ok(content) => content,
+                    Err(err) => {
+                        return Err(format!("(inventory::resolve) {}: {}", path, err).into())
+                    }
+                };
+
+                let mut hosts = vec![];
+                for line in content.lines().map(|line| line.trim()) {
+                    if !line.is_empty() {
+                        let term = HostPatternTerm {
+                            operator: HostPatternOperator::Union,
+                            target: HostPatternTarget::Name(line.to_string()),
+                            subscript: None,
+                        };
+                        for host in Self::term_hosts(inventory, &term)? {
+                            push_unique(&mut hosts, host);
+                        }
+                    }
+                }
+                return Ok(hosts);
+            }
+        };
+
+        let regex = match Regex::new(&expression) {
+            Ok(regex) => regex,
+            Err(err) => {
+                return Err(
+                    format!("(inventory::resolve) invalid host list pattern: {}", err).into(),
+                )
+            }
+        };
+
+        let mut groups: Vec<&str> = vec![ALL_GROUP, UNGROUPED_GROUP];
+        groups.extend(
+            inventory
+                .groups
+                .keys()
+                .map(|group| group.as_str())
+                .filter(|group| !Inventory::is_implicit_group(group)),
+        );
+        let matching_groups: Vec<&str> = groups
+            .into_iter()
+            .filter(|group| regex.is_match(group))
+            .collect();
+
+        let mut hosts = vec![];
+        for group in &matching_groups {
+            for host in inventory.group_hosts(group) {
+                push_unique(&mut hosts, host);
+            }
+        }
+
+        let may_match_hosts = matching_groups.is_empty()
+            || matches!(target, HostPatternTarget::Regex(_))
+            || name.contains(['.', '?', '*', '[']);
+        if may_match_hosts {
+            for host in inventory.host_names() {
+                if regex.is_match(&host) {
+                    push_unique(&mut hosts, host);
+                }
+            }
+        }
+
+        if hosts.is_empty() && IMPLICIT_LOCALHOST.contains(&name) {
+            hosts.push(name.to_string());
+        }
+
+        Ok(hosts)
+    }
+
+    /// Returns the hosts of `inventory` the pattern selects. As ansible does,
+    /// a term naming a host selects that host even when a group is named
+    /// alike, intersections and exclusions are applied after every union,
+    /// and a pattern made of intersections or exclusions only starts from
+    /// `all`.
+    pub fn resolve(&self, inventory: &Inventory) -> Result<Vec<String>, Box<dyn Error>> {
+        let unions: Vec<&HostPatternTerm> = self
+            .terms
+            .iter()
+            .filter(|term| term.operator == HostPatternOperator::Union)
+            .collect();
+
+        let mut hosts = vec![];
+        if unions.is_empty() && !self.terms.is_empty() {
+            hosts = inventory.group_hosts(ALL_GROUP);
+        }
+        for term in unions {
+            for host in Self::term_hosts(inventory, term)? {
+                push_unique(&mut hosts, host);
+            }
+        }
+
+        for operator in [
+            HostPatternOperator::Intersection,
+            HostPatternOperator::Exclusion,
+        ] {
+            for term in self.terms.iter().filter(|term| term.operator == operator) {
+                let matched = Self::term_hosts(inventory, term)?;
+                match operator {
+                    HostPatternOperator::Intersection => {
+                        hosts.retain(|host| matched.contains(host))
+                    }
+                    _ => hosts.retain(|host| !matched.contains(host)),
+                }
+            }
+        }
+
+        Ok(hosts)
+    }
+}
+
+impl Inventory {
+    /// Returns the hosts a `--limit` value selects on the inventory, every
+    /// host when the limit is empty
+    pub fn resolve_limit(&self, limit: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        if limit.trim().is_empty() {
+            return Ok(self.group_hosts(ALL_GROUP));
+        }
+
+        HostPattern::parse(limit)?.resolve(self)
+    }
+}
@@ -0,0 +1,92 @@
+use std::error::Error;
+
+const ASCII_LETTERS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// Port of ansible's `expand_hostname_range`: the first range is expanded and
+// the resulting names are expanded in turn
+pub(crate) fn expand_range(pattern: &str) -> Result<Vec<String>, String> {
+    let (head, rest) = match pattern.split_once('[') {
+        Some(split) => split,
+        None => return Ok(vec![pattern.to_string()]),
+    };
+    let (range, tail) = match rest.split_once(']') {
+        Some(split) => split,
+        None => {
+            return Err(format!(
+                "host range '{}' is missing its closing bracket",
+                pattern
+            ))
+        }
+    };
+
+    let bounds: Vec<&str> = range.split(':').collect();
+    let (start, end, step) = match bounds[..] {
+        [start, end] => (start, end, "1"),
+        [start, end, step] => (start, end, step),
+        _ => {
+            return Err(format!(
+                "host range '{}' must be begin:end or begin:end:step",
+                range
+            ))
+        }
+    };
+    let start = if start.is_empty() { "0" } else { start };
+    if end.is_empty() {
+        return Err(format!("host range '{}' must specify end value", range));
+    }
+    let step = match step.parse::<usize>() {
+        Ok(step) if step > 0 => step,
+        _ => return Err(format!("host range '{}' has an invalid step", range)),
+    };
+
+    // `[01:10]` keeps its zero padding
+    let width = if start.len() > 1 && start.starts_with('0') {
+        if start.len() != end.len() {
+            return Err(format!(
+                "host range '{}' must specify equal-length begin and end formats",
+                range
+            ));
+        }
+        start.len()
+    } else {
+        0
+    };
+
+    let items: Vec<String> = match (ASCII_LETTERS.find(start), ASCII_LETTERS.find(end)) {
+        (Some(first), Some(last)) if start.len() == 1 && end.len() == 1 => {
+            if first > last {
+                return Err(format!("host range '{}' must have begin <= end", range));
+            }
+            ASCII_LETTERS[first..=last]
+                .chars()
+                .step_by(step)
+                .map(|c| c.to_string())
+                .collect()
+        }
+        _ => match (start.parse::<u64>(), end.parse::<u64>()) {
+            // as with python's `range`, reversed bounds expand to nothing
+            (Ok(first), Ok(last)) => (first..=last)
+                .step_by(step)
+                .map(|n| format!("{:0width$}", n, width = width))
+                .collect(),
+            _ => return Err(format!("host range '{}' has invalid bounds", range)),
+        },
+    };
+
+    let mut hosts = vec![];
+    for item in items {
+        hosts.append(&mut expand_range(&format!("{}{}{}", head, item, tail))?);
+    }
+
+    Ok(hosts)
+}
+
+/// Expand the host ranges of `pattern` the way ansible does for inventory
+/// host names: `web[01:03]` expands to `web01`, `web02` and `web03`,
+/// `db-[a:f:2]` to `db-a`, `db-c` and `db-e`
+pub fn expand_host_range(pattern: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    match expand_range(pattern) {
+        Ok(hosts) => Ok(hosts),
+        Err(err) => Err(format!("(inventory::expand_host_range) {}", err).into()),
+    }
+}
@@ -0,0 +1,369 @@
+use super::{Inventory, ALL_GROUP, UNGROUPED_GROUP};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+use std::error::Error;
+
+/// File formats an inventory can be rendered to
+#[derive(Debug, Clone, PartialEq)]
+pub enum InventoryFormat {
+    Ini,
+    Yaml,
+    Json,
+}
+
+impl InventoryFormat {
+    /// File extension ansible uses to pick the right inventory plugin
+    pub fn extension(&self) -> &str {
+        match self {
+            InventoryFormat::Ini => "ini",
+            InventoryFormat::Yaml => "yml",
+            InventoryFormat::Json => "json",
+        }
+    }
+}
+
+// python literal of a string, as read back by `ast.literal_eval`
+fn python_string(value: &str) -> String {
+    let mut literal = String::from("'");
+    for c in value.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '\'' => literal.push_str("\\'"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('\'');
+
+    literal
+}
+
+// python literal of a value, as read back by `ast.literal_eval`
+pub(super) fn python_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "None".into(),
+        Value::Bool(true) => "True".into(),
+        Value::Bool(false) => "False".into(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => python_string(s),
+        Value::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(python_literal)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Object(map) => format!(
+            "{{{}}}",
+            map.iter()
+                .map(|(k, v)| format!("{}: {}", python_string(k), python_literal(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+// ansible's INI parser evaluates values as python literals, so strings which
+// would be evaluated to something else must be rendered as string literals
+fn ini_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => {
+            let evaluable = s.is_empty()
+                || s.trim() != s
+                || matches!(s.as_str(), "True" | "False" | "None")
+                || s.replace('_', "").parse::<f64>().is_ok()
+                || s.starts_with(|c: char| "'\"[{(".contains(c))
+                || (s.starts_with('0') && s[1..].starts_with(['x', 'o', 'b', 'X', 'O', 'B']));
+            if evaluable {
+                python_string(s)
+            } else {
+                s.clone()
+            }
+        }
+        value => python_literal(value),
+    }
+}
+
+// host lines are split with shlex, values holding blanks, quotes or comment
+// markers have to be quoted
+fn ini_host_value(value: &Value) -> String {
+    let value = ini_value(value);
+    if value.contains(|c: char| c.is_whitespace() || "'\"#\\".contains(c)) {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value
+    }
+}
+
+impl Inventory {
+    // groups without any parent but the implicit ones
+    pub(super) fn top_level_groups(&self) -> Vec<String> {
+        self.groups
+            .keys()
+            .filter(|name| !Self::is_implicit_group(name))
+            .filter(|name| {
+                self.parent_groups(name)
+                    .iter()
+                    .all(|parent| Self::is_implicit_group(parent))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn host_value(&self, host: &str, placed: &mut BTreeSet<String>) -> Value {
+        if !placed.insert(host.to_string()) {
+            return Value::Null;
+        }
+
+        match self.hosts.get(host) {
+            Some(definition) if !definition.vars.is_empty() => {
+                Value::Object(definition.vars.clone().into_iter().collect())
+            }
+            _ => Value::Null,
+        }
+    }
+
+    fn group_value(
+        &self,
+        name: &str,
+        visited: &mut BTreeSet<String>,
+        placed: &mut BTreeSet<String>,
+    ) -> Value {
+        let mut value = Map::new();
+        if !visited.insert(name.to_string()) {
+            return Value::Object(value);
+        }
+
+        let group = match self.groups.get(name) {
+            Some(group) => group,
+            None => return Value::Object(value),
+        };
+
+        if !group.hosts.is_empty() {
+            let hosts: Map<String, Value> = group
+                .hosts
+                .iter()
+                .map(|host| (host.clone(), self.host_value(host, placed)))
+                .collect();
+            value.insert("hosts".into(), Value::Object(hosts));
+        }
+
+        if !group.vars.is_empty() {
+            value.insert(
+                "vars".into(),
+                Value::Object(group.vars.clone().into_iter().collect()),
+            );
+        }
+
+        if !group.children.is_empty() {
+            let children: Map<String, Value> = group
+                .children
+                .iter()
+                .map(|child| (child.clone(), self.group_value(child, visited, placed)))
+                .collect();
+            value.insert("children".into(), Value::Object(children));
+        }
+
+        Value::Object(value)
+    }
+
+    /// Returns the inventory in the structure read by ansible's YAML
+    /// inventory plugin, also valid for its JSON files
+    pub fn to_value(&self) -> Value {
+        let mut visited = BTreeSet::new();
+        let mut placed = BTreeSet::new();
+        let mut all = Map::new();
+
+        let ungrouped = self.ungrouped_hosts();
+        if !ungrouped.is_empty() {
+            let hosts: Map<String, Value> = ungrouped
+                .iter()
+                .map(|host| (host.clone(), self.host_value(host, &mut placed)))
+                .collect();
+            all.insert("hosts".into(), Value::Object(hosts));
+        }
+
+        if let Some(group) = self.groups.get(ALL_GROUP) {
+            if !group.vars.is_empty() {
+                all.insert(
+                    "vars".into(),
+                    Value::Object(group.vars.clone().into_iter().collect()),
+                );
+            }
+        }
+
+        let mut children = Map::new();
+        if let Some(group) = self.groups.get(UNGROUPED_GROUP) {
+            if !group.vars.is_empty() {
+                let vars = Value::Object(group.vars.clone().into_iter().collect());
+                children.insert(UNGROUPED_GROUP.into(), json!({ "vars": vars }));
+            }
+        }
+        for name in self.top_level_groups() {
+            let value = self.group_value(&name, &mut visited, &mut placed);
+            children.insert(name, value);
+        }
+        // groups only reachable through a cycle of children
+        for name in self.groups.keys() {
+            if !Self::is_implicit_group(name) && !visited.contains(name) {
+                let value = self.group_value(name, &mut visited, &mut placed);
+                children.insert(name.clone(), value);
+            }
+        }
+        if !children.is_empty() {
+            all.insert("children".into(), Value::Object(children));
+        }
+
+        json!({ ALL_GROUP: all })
+    }
+
+    /// Returns the inventory in the structure printed by `--list` on
+    /// inventory scripts, host variables being set under `_meta`
+    pub fn to_script_value(&self) -> Value {
+        let mut script = Map::new();
+
+        let mut all = Map::new();
+        let mut children: Vec<String> = vec![UNGROUPED_GROUP.to_string()];
+        children.append(&mut self.top_level_groups());
+        all.insert("children".into(), json!(children));
+        if let Some(group) = self.groups.get(ALL_GROUP) {
+            if !group.vars.is_empty() {
+                all.insert("vars".into(), json!(group.vars));
+            }
+        }
+        script.insert(ALL_GROUP.into(), Value::Object(all));
+
+        let mut ungrouped = Map::new();
+        ungrouped.insert("hosts".into(), json!(self.ungrouped_hosts()));
+        if let Some(group) = self.groups.get(UNGROUPED_GROUP) {
+            if !group.vars.is_empty() {
+                ungrouped.insert("vars".into(), json!(group.vars));
+            }
+        }
+        script.insert(UNGROUPED_GROUP.into(), Value::Object(ungrouped));
+
+        for (name, group) in &self.groups {
+            if Self::is_implicit_group(name) {
+                continue;
+            }
+
+            let mut value = Map::new();
+            if !group.hosts.is_empty() {
+                value.insert("hosts".into(), json!(group.hosts));
+            }
+            if !group.vars.is_empty() {
+                value.insert("vars".into(), json!(group.vars));
+            }
+            if !group.children.is_empty() {
+                value.insert("children".into(), json!(group.children));
+            }
+            script.insert(name.clone(), Value::Object(value));
+        }
+
+        let hostvars: Map<String, Value> = self
+            .hosts
+            .iter()
+            .map(|(name, host)| (name.clone(), json!(host.vars)))
+            .collect();
+        script.insert("_meta".into(), json!({ "hostvars": hostvars }));
+
+        Value::Object(script)
+    }
+
+    /// Render the inventory in ansible's YAML format
+    pub fn to_yaml(&self) -> Result<String, Box<dyn Error>> {
+        match serde_yaml::to_string(&self.to_value()) {
+            Ok(yaml) => Ok(yaml),
+            Err(err) => Err(format!("(inventory::to_yaml) {}", err).into()),
+        }
+    }
+
+    /// Render the inventory as JSON, in the structure of ansible's YAML format
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        match serde_json::to_string_pretty(&self.to_value()) {
+            Ok(json) => Ok(json),
+            Err(err) => Err(format!("(inventory::to_json) {}", err).into()),
+        }
+    }
+
+    fn ini_host_line(&self, host: &str, placed: &mut BTreeSet<String>) -> String {
+        let mut line = host.to_string();
+        if let Value::Object(vars) = self.host_value(host, placed) {
+            for (key, value) in vars {
+                line.push_str(&format!(" {}={}", key, ini_host_value(&value)));
+            }
+        }
+
+        line
+    }
+
+    /// Render the inventory in ansible's INI format
+    pub fn to_ini(&self) -> String {
+        let mut placed = BTreeSet::new();
+        let mut sections = vec![];
+
+        let ungrouped: Vec<String> = self
+            .ungrouped_hosts()
+            .iter()
+            .map(|host| self.ini_host_line(host, &mut placed))
+            .collect();
+        if !ungrouped.is_empty() {
+            sections.push(ungrouped.join("\n"));
+        }
+
+        for (name, group) in &self.groups {
+            if Self::is_implicit_group(name) {
+                continue;
+            }
+
+            let mut section = vec![format!("[{}]", name)];
+            for host in &group.hosts {
+                section.push(self.ini_host_line(host, &mut placed));
+            }
+            sections.push(section.join("\n"));
+
+            if !group.vars.is_empty() {
+                let mut section = vec![format!("[{}:vars]", name)];
+                for (key, value) in &group.vars {
+                    section.push(format!("{}={}", key, ini_value(value)));
+                }
+                sections.push(section.join("\n"));
+            }
+
+            if !group.children.is_empty() {
+                let mut section = vec![format!("[{}:children]", name)];
+                section.append(&mut group.children.clone());
+                sections.push(section.join("\n"));
+            }
+        }
+
+        for name in [UNGROUPED_GROUP, ALL_GROUP] {
+            if let Some(group) = self.groups.get(name) {
+                if !group.vars.is_empty() {
+                    let mut section = vec![format!("[{}:vars]", name)];
+                    for (key, value) in &group.vars {
+                        section.push(format!("{}={}", key, ini_value(value)));
+                    }
+                    sections.push(section.join("\n"));
+                }
+            }
+        }
+
+        let mut ini = sections.join("\n\n");
+        ini.push('\n');
+
+        ini
+    }
+
+    /// Render the inventory in the given format
+    pub fn render(&self, format: &InventoryFormat) -> Result<String, Box<dyn Error>> {
+        match format {
+            InventoryFormat::Ini => Ok(self.to_ini()),
+            InventoryFormat::Yaml => self.to_yaml(),
+            InventoryFormat::Json => self.to_json(),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use super::{Inventory, InventoryParseError, InventoryVars};
+use serde_json::Value;
+
+// names listed under `key`, which must be a list of strings
+fn name_list(group: &str, key: &str, value: &Value) -> Result<Vec<String>, InventoryParseError> {
+    let items = match value {
+        Value::Null => return Ok(vec![]),
+        Value::Array(items) => items,
+        _ => {
+            return Err(InventoryParseError::new(
+                0,
+                format!("\"{}\" of group \"{}\" should be a list", key, group),
+            ))
+        }
+    };
+
+    items
+        .iter()
+        .map(|item| match item {
+            Value::String(name) => Ok(name.clone()),
+            item => Err(InventoryParseError::new(
+                0,
+                format!(
+                    "invalid name {} in \"{}\" of group \"{}\"",
+                    item, key, group
+                ),
+            )),
+        })
+        .collect()
+}
+
+fn vars_of(owner: &str, value: &Value) -> Result<InventoryVars, InventoryParseError> {
+    match value {
+        Value::Null => Ok(InventoryVars::new()),
+        Value::Object(vars) => Ok(vars.clone().into_iter().collect()),
+        _ => Err(InventoryParseError::new(
+            0,
+            format!("variables of \"{}\" should be a dictionary", owner),
+        )),
+    }
+}
+
+impl Inventory {
+    /// Build an inventory from the structure printed by `--list` on
+    /// inventory scripts and by `ansible-inventory --list`. As ansible does,
+    /// a group may also be given as a plain list of hosts.
+    pub fn from_script_value(value: &Value) -> Result<Inventory, InventoryParseError> {
+        let groups = match value {
+            Value::Object(groups) => groups,
+            _ => {
+                return Err(InventoryParseError::new(
+                    0,
+                    "inventory script output should be a dictionary",
+                ))
+            }
+        };
+
+        let mut inventory = Inventory::new();
+        for (name, definition) in groups {
+            if name == "_meta" {
+                continue;
+            }
+
+            let definition = match definition {
+                Value::Array(_) => {
+                    for host in name_list(name, "hosts", definition)? {
+                        inventory.add_host_to_group(name, &host);
+                    }
+                    continue;
+                }
+                Value::Object(definition) => definition,
+                _ => {
+                    return Err(InventoryParseError::new(
+                        0,
+                        format!("group \"{}\" should be a dictionary or a list", name),
+                    ))
+                }
+            };
+
+            inventory.add_group(name);
+            for (key, value) in definition {
+                match key.as_str() {
+                    "hosts" => {
+                        for host in name_list(name, key, value)? {
+                            inventory.add_host_to_group(name, &host);
+                        }
+                    }
+                    "children" => {
+                        for child in name_list(name, key, value)? {
+                            inventory.add_child_group(name, &child);
+                        }
+                    }
+                    "vars" => inventory.add_group(name).vars.extend(vars_of(name, value)?),
+                    _ => {}
+                }
+            }
+        }
+
+        let hostvars = value.pointer("/_meta/hostvars").unwrap_or(&Value::Null);
+        if let Value::Object(hostvars) = hostvars {
+            for (host, vars) in hostvars {
+                inventory.add_host(host).vars.extend(vars_of(host, vars)?);
+            }
+        }
+
+        inventory.normalize_implicit_groups();
+
+        Ok(inventory)
+    }
+
+    /// Parse the JSON printed by `--list` on inventory scripts and by
+    /// `ansible-inventory --list`
+    pub fn from_script_json(content: &str) -> Result<Inventory, InventoryParseError> {
+        match serde_json::from_str(content) {
+            Ok(value) => Inventory::from_script_value(&value),
+            Err(err) => Err(InventoryParseError::new(err.line(), err.to_string())),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use super::Inventory;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// How hosts are spread over shards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InventoryShardStrategy {
+    ConsistentHash, // by host name, hosts keeping their shard when others are added or removed
+    RoundRobin,     // in turn, in inventory order, for evenly sized shards
+}
+
+/// How to split the hosts of an inventory into shards
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryShardOptions {
+    pub shards: usize,                    // number of shards
+    pub strategy: InventoryShardStrategy, // how hosts are spread over the shards
+    pub pattern: String, // host pattern selecting the hosts to split, every host when empty
+    pub keep_groups_together: bool, // keep the members of each group in the same shard
+}
+
+impl Default for InventoryShardOptions {
+    fn default() -> Self {
+        InventoryShardOptions {
+            shards: 1,
+            strategy: InventoryShardStrategy::ConsistentHash,
+            pattern: String::new(),
+            keep_groups_together: false,
+        }
+    }
+}
+
+// FNV-1a, which unlike the std hasher is stable across releases
+fn stable_hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// rendezvous hashing: the shard scoring the highest with `key` wins
+fn hash_shard(key: &str, shards: usize) -> usize {
+    (0..shards)
+        .max_by_key(|shard| stable_hash(&format!("{}#{}", key, shard)))
+        .unwrap_or(0)
+}
+
+fn find(parents: &mut [usize], index: usize) -> usize {
+    let mut root = index;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[index] = root;
+
+    root
+}
+
+impl Inventory {
+    // hosts linked by the groups they share, in the order of their first host.
+    // Groups holding every selected host are ignored, as they would keep all
+    // of them together.
+    fn host_units(&self, hosts: &[String]) -> Vec<Vec<String>> {
+        let positions: BTreeMap<&String, usize> = hosts
+            .iter()
+            .enumerate()
+            .map(|(i, host)| (host, i))
+            .collect();
+        let mut parents: Vec<usize> = (0..hosts.len()).collect();
+
+        for group in self.groups.keys() {
+            if Self::is_implicit_group(group) {
+                continue;
+            }
+            let members: Vec<usize> = self
+                .group_hosts(group)
+                .iter()
+                .filter_map(|host| positions.get(host).copied())
+                .collect();
+            if members.len() == hosts.len() {
+                continue;
+            }
+            for member in members.iter().skip(1) {
+                let (a, b) = (find(&mut parents, members[0]), find(&mut parents, *member));
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+
+        let mut units: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for (index, host) in hosts.iter().enumerate() {
+            let root = find(&mut parents, index);
+            units.entry(root).or_default().push(host.clone());
+        }
+
+        units.into_values().collect()
+    }
+
+    /// Split `hosts` into shards as described by `options`, whose pattern is
+    /// ignored. Some shards may be empty when there are few hosts.
+    pub fn shard_hosts(
+        &self,
+        hosts: &[String],
+        options: &InventoryShardOptions,
+    ) -> Vec<Vec<String>> {
+        let count = options.shards.max(1);
+        let mut shards: Vec<Vec<String>> = vec![vec![]; count];
+
+        let mut units = if options.keep_groups_together {
+            self.host_units(hosts)
+        } else {
+            hosts.iter().map(|host| vec![host.clone()]).collect()
+        };
+
+        match options.strategy {
+            InventoryShardStrategy::ConsistentHash => {
+                for unit in units {
+                    // units are keyed by their first host
+                    let shard = hash_shard(&unit[0], count);
+                    shards[shard].extend(unit);
+                }
+            }
+            InventoryShardStrategy::RoundRobin if options.keep_groups_together => {
+                // the largest units first, each one to the smallest shard
+                units.sort_by_key(|unit| std::cmp::Reverse(unit.len()));
+                for unit in units {
+                    let shard = (0..count).min_by_key(|&i| shards[i].len()).unwrap_or(0);
+                    shards[shard].extend(unit);
+                }
+            }
+            InventoryShardStrategy::RoundRobin => {
+                for (index, unit) in units.into_iter().enumerate() {
+                    shards[index % count].extend(unit);
+                }
+            }
+        }
+
+        shards
+    }
+
+    /// Split the hosts selected by the pattern of `options` into shards. Some
+    /// shards may be empty when there are few hosts.
+    pub fn shard(
+        &self,
+        options: &InventoryShardOptions,
+    ) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        if options.shards == 0 {
+            return Err("(inventory::shard) the number of shards should be positive".into());
+        }
+
+        let hosts = match self.resolve_limit(&options.pattern) {
+            Ok(hosts) => hosts,
+            Err(err) => return Err(format!("(inventory::shard) {}", err).into()),
+        };
+
+        Ok(self.shard_hosts(&hosts, options))
+    }
+}
@@ -0,0 +1,346 @@
+use super::{
+    diff_vars, push_unique, DynamicInventory, Inventory, InventoryVarChange, InventoryVars,
+};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// files ansible skips when reading an inventory directory, the default
+// `INVENTORY_IGNORE_EXTS`: `REJECT_EXTS` and `.orig`, `.cfg` and `.retry`
+const IGNORED_EXTENSIONS: [&str; 12] = [
+    ".pyc", ".pyo", ".swp", ".bak", "~", ".rpm", ".md", ".txt", ".rst", ".orig", ".cfg", ".retry",
+];
+
+/// Inventory source given to ansible with `--inventory`, ansible merging
+/// several of them in the order they are given
+#[derive(Debug, Clone)]
+pub enum InventorySource {
+    Path(String), // inventory file, directory or script; given as is, so `web1,web2,` host lists are accepted too
+    Hosts(Vec<String>), // inline list of hosts
+    Model(Inventory), // in-memory inventory written to a temporary file for the run
+    Dynamic(DynamicInventory), // inventory provided by Rust code through a generated inventory script
+}
+
+impl From<&str> for InventorySource {
+    fn from(path: &str) -> Self {
+        InventorySource::Path(path.to_string())
+    }
+}
+
+impl From<String> for InventorySource {
+    fn from(path: String) -> Self {
+        InventorySource::Path(path)
+    }
+}
+
+impl From<Inventory> for InventorySource {
+    fn from(inventory: Inventory) -> Self {
+        InventorySource::Model(inventory)
+    }
+}
+
+impl From<DynamicInventory> for InventorySource {
+    fn from(inventory: DynamicInventory) -> Self {
+        InventorySource::Dynamic(inventory)
+    }
+}
+
+impl InventorySource {
+    /// Returns the `--inventory` argument of the source, `None` for the
+    /// in-memory and dynamic ones which are only written out when the
+    /// command runs
+    pub fn argument(&self) -> Option<String> {
+        match self {
+            InventorySource::Path(path) => Some(path.clone()),
+            // the trailing comma makes ansible read a single host as a list
+            InventorySource::Hosts(hosts) => Some(format!("{},", hosts.join(","))),
+            InventorySource::Model(_) | InventorySource::Dynamic(_) => None,
+        }
+    }
+
+    // name of the source in conflict reports, `index` telling apart the
+    // in-memory ones
+    fn label(&self, index: usize) -> String {
+        match self {
+            InventorySource::Model(_) => format!("inventory model #{}", index),
+            InventorySource::Dynamic(_) => format!("dynamic inventory #{}", index),
+            source => source.argument().unwrap_or_default(),
+        }
+    }
+
+    /// Load the inventory of the source without running ansible. Paths are
+    /// read as ansible does: directories file by file, executable files as
+    /// inventory scripts, and missing paths holding a comma as host lists.
+    /// The `group_vars/` and `host_vars/` of a directory, or of the directory
+    /// of a file, are attached too. Variables the files of a directory
+    /// disagree on are reported by `merge_inventory_sources`.
+    pub fn load(&self) -> Result<Inventory, Box<dyn Error>> {
+        self.load_source(&mut vec![])
+    }
+
+    // load the source, recording the variables the files of a directory
+    // source give different values to
+    fn load_source(
+        &self,
+        conflicts: &mut Vec<InventorySourceConflict>,
+    ) -> Result<Inventory, Box<dyn Error>> {
+        match self {
+            InventorySource::Path(path) => load_path(Path::new(path), conflicts)
+                .map_err(|err| format!("(inventory::load) {}: {}", path, err).into()),
+            InventorySource::Hosts(hosts) => Ok(host_list(hosts)),
+            InventorySource::Model(inventory) => Ok(inventory.clone()),
+            InventorySource::Dynamic(inventory) => match (inventory.provider)() {
+                Ok(inventory) => Ok(inventory),
+                Err(err) => Err(format!("(inventory::load) {}", err).into()),
+            },
+        }
+    }
+}
+
+fn host_list<S: AsRef<str>>(hosts: &[S]) -> Inventory {
+    let mut inventory = Inventory::new();
+    for host in hosts.iter().map(|host| host.as_ref().trim()) {
+        if !host.is_empty() {
+            inventory.add_host(host);
+        }
+    }
+
+    inventory
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
+
+// the vars directories of a source are next to its files, or within it for
+// a directory, as for ansible's `host_group_vars` plugin
+fn load_path(
+    path: &Path,
+    conflicts: &mut Vec<InventorySourceConflict>,
+) -> Result<Inventory, Box<dyn Error>> {
+    let text = path.to_string_lossy();
+    if !path.exists() && text.contains(',') {
+        return Ok(host_list(&text.split(',').collect::<Vec<_>>()));
+    }
+
+    let mut inventory = load_entry(path, conflicts)?;
+    let basedir = if path.is_dir() {
+        path
+    } else {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        }
+    };
+    inventory.load_vars(basedir)?;
+
+    Ok(inventory)
+}
+
+fn load_entry(
+    path: &Path,
+    conflicts: &mut Vec<InventorySourceConflict>,
+) -> Result<Inventory, Box<dyn Error>> {
+    if path.is_dir() {
+        let mut files: Vec<_> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|file| {
+                let name = file
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                !name.starts_with('.')
+                    && name != "group_vars"
+                    && name != "host_vars"
+                    && !IGNORED_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+            })
+            .collect();
+        files.sort();
+
+        let mut inventory = Inventory::new();
+        let mut origins = BTreeMap::new();
+        for file in files {
+            let loaded = load_entry(&file, conflicts)?;
+            inventory.merge(&loaded, &file.to_string_lossy(), &mut origins, conflicts);
+        }
+
+        return Ok(inventory);
+    }
+
+    if is_executable(path) {
+        let output = Command::new(path).arg("--list").output()?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().into());
+        }
+        return Ok(Inventory::from_script_json(&String::from_utf8_lossy(
+            &output.stdout,
+        ))?);
+    }
+
+    Inventory::from_file(path)
+}
+
+/// Host or group a merged variable is set on
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InventoryVarOwner {
+    Host(String),
+    Group(String),
+}
+
+impl fmt::Display for InventoryVarOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InventoryVarOwner::Host(host) => write!(f, "host '{}'", host),
+            InventoryVarOwner::Group(group) => write!(f, "group '{}'", group),
+        }
+    }
+}
+
+/// Variable given different values by several inventory sources, the value
+/// of the latest source being kept as ansible does
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventorySourceConflict {
+    pub owner: InventoryVarOwner, // host or group the variable is set on
+    pub var: String,              // variable given conflicting values
+    pub previous_source: String,  // source of the overridden value
+    pub previous: Value,          // overridden value
+    pub source: String,           // source of the kept value
+    pub value: Value,             // kept value
+    pub changes: Vec<InventoryVarChange>, // differences between both values, nested ones included
+}
+
+impl fmt::Display for InventorySourceConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} has conflicting values for '{}': {} in '{}' is overridden by {} in '{}'",
+            self.owner, self.var, self.previous, self.previous_source, self.value, self.source
+        )
+    }
+}
+
+/// Inventory made of several sources, and the variables they disagree on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InventoryMerge {
+    pub inventory: Inventory,                    // merged inventory
+    pub conflicts: Vec<InventorySourceConflict>, // variables set differently by several sources, in merge order
+}
+
+// variables of hosts and groups, and the source which set them last
+type InventoryVarOrigins = BTreeMap<(InventoryVarOwner, String), String>;
+
+// override `vars` with `other`, recording the variables set differently
+fn merge_vars(
+    owner: InventoryVarOwner,
+    vars: &mut InventoryVars,
+    other: &InventoryVars,
+    source: &str,
+    origins: &mut InventoryVarOrigins,
+    conflicts: &mut Vec<InventorySourceConflict>,
+) {
+    for (name, value) in other {
+        let key = (owner.clone(), name.clone());
+        if let Some(previous) = vars.get(name).filter(|previous| *previous != value) {
+            conflicts.push(InventorySourceConflict {
+                owner: owner.clone(),
+                var: name.clone(),
+                previous_source: origins.get(&key).cloned().unwrap_or_default(),
+                previous: previous.clone(),
+                source: source.to_string(),
+                value: value.clone(),
+                changes: diff_vars(
+                    &[(name.clone(), previous.clone())].into(),
+                    &[(name.clone(), value.clone())].into(),
+                ),
+            });
+        }
+        vars.insert(name.clone(), value.clone());
+        origins.insert(key, source.to_string());
+    }
+}
+
+impl Inventory {
+    // add the hosts, groups and variables of `other`, its variables
+    // overriding the current ones
+    fn merge(
+        &mut self,
+        other: &Inventory,
+        source: &str,
+        origins: &mut InventoryVarOrigins,
+        conflicts: &mut Vec<InventorySourceConflict>,
+    ) {
+        self.warnings.extend(other.warnings.iter().cloned());
+        for name in other.host_names() {
+            merge_vars(
+                InventoryVarOwner::Host(name.clone()),
+                &mut self.add_host(&name).vars,
+                &other.hosts[&name].vars,
+                source,
+                origins,
+                conflicts,
+            );
+        }
+
+        for (name, group) in &other.groups {
+            // groups stay undeclared when no source declares them
+            if !other.undeclared_groups.contains(name) {
+                self.undeclared_groups.remove(name);
+            } else if !self.groups.contains_key(name) {
+                self.undeclared_groups.insert(name.clone());
+            }
+            let merged = self.groups.entry(name.clone()).or_default();
+            for host in &group.hosts {
+                push_unique(&mut merged.hosts, host);
+            }
+            for child in &group.children {
+                push_unique(&mut merged.children, child);
+            }
+            merge_vars(
+                InventoryVarOwner::Group(name.clone()),
+                &mut merged.vars,
+                &group.vars,
+                source,
+                origins,
+                conflicts,
+            );
+        }
+    }
+}
+
+/// Load every source and merge them offline in the order ansible does: hosts
+/// and groups are combined, and variables set by a later source override
+/// the earlier ones. Variables given different values are reported, the
+/// ones the files of a directory source disagree on included.
+pub fn merge_inventory_sources(
+    sources: &[InventorySource],
+) -> Result<InventoryMerge, Box<dyn Error>> {
+    let mut merge = InventoryMerge::default();
+    let mut origins = BTreeMap::new();
+
+    for (index, source) in sources.iter().enumerate() {
+        let inventory = match source.load_source(&mut merge.conflicts) {
+            Ok(inventory) => inventory,
+            Err(err) => return Err(format!("(inventory::merge_inventory_sources) {}", err).into()),
+        };
+        merge.inventory.merge(
+            &inventory,
+            &source.label(index),
+            &mut origins,
+            &mut merge.conflicts,
+        );
+    }
+
+    Ok(merge)
+}
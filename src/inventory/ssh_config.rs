@@ -0,0 +1,305 @@
+use super::Inventory;
+use serde_json::{json, Value};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ssh gives up on deeper includes
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// `Host` block, or the options preceding the first one, which apply to every
+// host. `Match` blocks have no patterns, their options being skipped.
+#[derive(Debug, Default)]
+struct SshConfigBlock {
+    patterns: Vec<String>,
+    options: Vec<(String, Vec<String>)>,
+}
+
+// arguments of a line, double quotes grouping words
+fn split_args(value: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+
+    for c in value.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    args.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        args.push(current);
+    }
+
+    args
+}
+
+// `keyword value` or `keyword=value`, keywords being case insensitive
+fn split_line(line: &str) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let end = line
+        .find(|c: char| c.is_whitespace() || c == '=')
+        .unwrap_or(line.len());
+    let (keyword, rest) = line.split_at(end);
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix('=').unwrap_or(rest);
+
+    Some((keyword.to_lowercase(), split_args(rest)))
+}
+
+// whether `text` matches the ssh pattern `pattern`, made of `*` and `?`
+// wildcards
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn is_wildcard(pattern: &str) -> bool {
+    pattern.contains(['*', '?']) || pattern.starts_with('!')
+}
+
+// a host matches a block when one of its patterns matches, and none of its
+// negated ones do
+fn block_matches(patterns: &[String], host: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildcard_match(negated, host) => return false,
+            Some(_) => {}
+            None => matched = matched || wildcard_match(pattern, host),
+        }
+    }
+
+    matched
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+// files an `Include` argument refers to, wildcards being allowed in the file
+// name, in name order
+fn include_files(argument: &str, base_dir: &Path) -> Vec<PathBuf> {
+    let path = expand_home(argument);
+    let path = if path.is_absolute() {
+        path
+    } else {
+        base_dir.join(path)
+    };
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if !is_wildcard(&name) {
+        return vec![path];
+    }
+
+    let directory = path.parent().unwrap_or(base_dir);
+    let mut files: Vec<PathBuf> = fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| wildcard_match(&name, &entry.file_name().to_string_lossy()))
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+
+    files
+}
+
+struct SshConfigParser {
+    base_dir: PathBuf,
+    blocks: Vec<SshConfigBlock>,
+    current: usize, // block the options being read belong to
+    warnings: Vec<String>,
+}
+
+impl SshConfigParser {
+    fn parse(&mut self, content: &str, depth: usize) -> Result<(), Box<dyn Error>> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err("too many nested includes".into());
+        }
+
+        for (keyword, args) in content.lines().filter_map(split_line) {
+            match keyword.as_str() {
+                "host" => {
+                    self.blocks.push(SshConfigBlock {
+                        patterns: args,
+                        ..Default::default()
+                    });
+                    self.current = self.blocks.len() - 1;
+                }
+                "match" => {
+                    self.warnings.push(format!(
+                        "Skipping \"Match {}\" block of ssh_config, Match blocks are not supported",
+                        args.join(" ")
+                    ));
+                    self.blocks.push(SshConfigBlock::default());
+                    self.current = self.blocks.len() - 1;
+                }
+                "include" => {
+                    // the options following an `Include` belong to the block
+                    // it was found in, not to the last one of the included files
+                    let current = self.current;
+                    for argument in &args {
+                        for path in include_files(argument, &self.base_dir) {
+                            // as ssh does, missing files are ignored
+                            if let Ok(included) = fs::read_to_string(&path) {
+                                self.parse(&included, depth + 1)?;
+                            }
+                        }
+                    }
+                    self.current = current;
+                }
+                _ => {
+                    if let Some(block) = self.blocks.get_mut(self.current) {
+                        block.options.push((keyword, args));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // value of `keyword` for `host`, the first one found winning as in ssh
+    fn option(&self, host: &str, keyword: &str) -> Option<Vec<String>> {
+        self.blocks
+            .iter()
+            .filter(|block| block_matches(&block.patterns, host))
+            .flat_map(|block| block.options.iter())
+            .find(|(key, _)| key == keyword)
+            .map(|(_, args)| args.clone())
+    }
+
+    fn inventory(&self) -> Inventory {
+        let mut inventory = Inventory::new();
+        inventory.warnings = self.warnings.clone();
+
+        let hosts = self
+            .blocks
+            .iter()
+            .flat_map(|block| block.patterns.iter())
+            .filter(|pattern| !is_wildcard(pattern));
+        for host in hosts {
+            inventory.add_host(host);
+            let first = |keyword: &str| {
+                self.option(host, keyword)
+                    .and_then(|args| args.into_iter().next())
+            };
+
+            if let Some(hostname) = first("hostname") {
+                let hostname = hostname.replace("%h", host);
+                inventory.set_host_var(host, "ansible_host", json!(hostname));
+            }
+            if let Some(user) = first("user") {
+                inventory.set_host_var(host, "ansible_user", json!(user));
+            }
+            if let Some(port) = first("port") {
+                let port = port.parse::<u16>().map_or(json!(port), Value::from);
+                inventory.set_host_var(host, "ansible_port", port);
+            }
+            if let Some(key) = first("identityfile").filter(|key| key != "none") {
+                inventory.set_host_var(host, "ansible_ssh_private_key_file", json!(key));
+            }
+            if let Some(jump) = first("proxyjump").filter(|jump| jump != "none") {
+                inventory.set_host_var(
+                    host,
+                    "ansible_ssh_common_args",
+                    json!(format!("-o ProxyJump={}", jump)),
+                );
+            }
+        }
+
+        inventory
+    }
+}
+
+impl Inventory {
+    /// Build an inventory from the `Host` blocks of an OpenSSH client
+    /// configuration. Every host alias which is not a pattern becomes a
+    /// host, its connection variables being resolved as ssh does, the first
+    /// value found in a matching block winning. `Include` files are looked
+    /// up relative to `base_dir`, and `Match` blocks are skipped with a
+    /// warning.
+    pub fn from_ssh_config(
+        content: &str,
+        base_dir: impl AsRef<Path>,
+    ) -> Result<Inventory, Box<dyn Error>> {
+        let mut parser = SshConfigParser {
+            base_dir: base_dir.as_ref().to_path_buf(),
+            // options before the first `Host` line apply to every host
+            blocks: vec![SshConfigBlock {
+                patterns: vec!["*".into()],
+                ..Default::default()
+            }],
+            current: 0,
+            warnings: vec![],
+        };
+
+        match parser.parse(content, 0) {
+            Ok(()) => Ok(parser.inventory()),
+            Err(err) => Err(format!("(inventory::from_ssh_config) {}", err).into()),
+        }
+    }
+
+    /// Read an OpenSSH client configuration file, such as `~/.ssh/config`,
+    /// included files being looked up relative to its directory
+    pub fn from_ssh_config_file(path: impl AsRef<Path>) -> Result<Inventory, Box<dyn Error>> {
+        let path = path.as_ref();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                return Err(
+                    format!("(inventory::from_ssh_config) {}: {}", path.display(), err).into(),
+                )
+            }
+        };
+
+        Inventory::from_ssh_config(&content, path.parent().unwrap_or(Path::new(".")))
+    }
+}
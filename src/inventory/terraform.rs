@@ -0,0 +1,188 @@
+use super::{safe_group_name, DynamicInventory, Inventory};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+/// Groups made from the values of a resource attribute. A string value
+/// makes a single group, a list one group per item, and a map, such as
+/// `tags`, one group per `key_value` pair.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TerraformGroupBy {
+    pub attribute: String, // attribute the groups are made from, such as `tags.Role`
+    pub prefix: String,    // prefix of the group names, joined with `_`
+}
+
+/// How the instances of a Terraform resource type become hosts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TerraformRule {
+    pub resource_type: String, // resource type the rule applies to, such as `aws_instance`
+    pub name_attribute: String, // attribute naming the host, such as `tags.Name`; the resource name and index when empty or missing
+    pub groups: Vec<String>,    // groups every host of the rule belongs to
+    pub group_by: Vec<TerraformGroupBy>, // groups made from attribute values
+    pub vars: BTreeMap<String, String>, // host variables and the attributes they are read from, such as `ansible_host` from `private_ip`
+}
+
+/// Inventory built from a local Terraform state file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TerraformInventory {
+    pub state: PathBuf,            // path of the `terraform.tfstate` file
+    pub rules: Vec<TerraformRule>, // how resources become hosts
+}
+
+// value found at `path` within `attributes`, keys being separated with dots
+// and list items being selected by their index
+fn attribute<'a>(attributes: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(attributes, |value, key| match value {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+            _ => None,
+        })
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn prefixed(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        safe_group_name(name)
+    } else {
+        safe_group_name(&format!("{}_{}", prefix, name))
+    }
+}
+
+fn group_names(group_by: &TerraformGroupBy, value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(scalar)
+            .map(|item| prefixed(&group_by.prefix, &item))
+            .collect(),
+        Value::Object(map) => map
+            .iter()
+            .filter_map(|(key, value)| scalar(value).map(|value| format!("{}_{}", key, value)))
+            .map(|pair| prefixed(&group_by.prefix, &pair))
+            .collect(),
+        value => scalar(value)
+            .map(|value| vec![prefixed(&group_by.prefix, &value)])
+            .unwrap_or_default(),
+    }
+}
+
+// host named after the resource when no attribute names it: `web` for a
+// single instance, `web_0` or `web_key` for `count` and `for_each` ones
+fn resource_host_name(resource: &str, index_key: Option<&Value>) -> String {
+    match index_key.and_then(scalar) {
+        Some(key) => format!("{}_{}", resource, key),
+        None => resource.to_string(),
+    }
+}
+
+impl Inventory {
+    /// Build an inventory from the content of a Terraform state file, in its
+    /// version 4 JSON format. Only the managed resources some rule applies to
+    /// become hosts, data sources being ignored.
+    pub fn from_terraform_state(
+        content: &str,
+        rules: &[TerraformRule],
+    ) -> Result<Inventory, Box<dyn Error>> {
+        let state: Value = match serde_json::from_str(content) {
+            Ok(state) => state,
+            Err(err) => return Err(format!("(inventory::from_terraform_state) {}", err).into()),
+        };
+        match state.get("version").and_then(|version| version.as_u64()) {
+            Some(4) => {}
+            version => {
+                return Err(format!(
+                    "(inventory::from_terraform_state) unsupported state version {}",
+                    version.map_or("unknown".to_string(), |v| v.to_string())
+                )
+                .into())
+            }
+        }
+
+        let mut inventory = Inventory::new();
+        let resources = state
+            .get("resources")
+            .and_then(|resources| resources.as_array())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for resource in resources {
+            if resource.get("mode").and_then(|mode| mode.as_str()) != Some("managed") {
+                continue;
+            }
+            let resource_type = resource
+                .get("type")
+                .and_then(|t| t.as_str())
+                .unwrap_or_default();
+            let name = resource
+                .get("name")
+                .and_then(|n| n.as_str())
+                .unwrap_or_default();
+            let instances = resource
+                .get("instances")
+                .and_then(|instances| instances.as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+
+            for rule in rules
+                .iter()
+                .filter(|rule| rule.resource_type == resource_type)
+            {
+                for instance in instances {
+                    let attributes = instance.get("attributes").unwrap_or(&Value::Null);
+                    let host = attribute(attributes, &rule.name_attribute)
+                        .and_then(scalar)
+                        .unwrap_or_else(|| resource_host_name(name, instance.get("index_key")));
+
+                    inventory.add_host(&host);
+                    for group in &rule.groups {
+                        inventory.add_host_to_group(group, &host);
+                    }
+                    for group_by in &rule.group_by {
+                        if let Some(value) = attribute(attributes, &group_by.attribute) {
+                            for group in group_names(group_by, value) {
+                                inventory.add_host_to_group(&group, &host);
+                            }
+                        }
+                    }
+                    for (var, path) in &rule.vars {
+                        if let Some(value) = attribute(attributes, path) {
+                            inventory.set_host_var(&host, var, value.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(inventory)
+    }
+}
+
+impl TerraformInventory {
+    /// Read the state file and build the inventory
+    pub fn load(&self) -> Result<Inventory, Box<dyn Error>> {
+        let content = match fs::read_to_string(&self.state) {
+            Ok(content) => content,
+            Err(err) => {
+                return Err(format!("(inventory::load) {}: {}", self.state.display(), err).into())
+            }
+        };
+
+        Inventory::from_terraform_state(&content, &self.rules)
+    }
+
+    /// Dynamic inventory reading the state file when the playbook runs
+    pub fn dynamic(&self) -> DynamicInventory {
+        let terraform = self.clone();
+        DynamicInventory::new(move || terraform.load().map_err(|err| err.to_string().into()))
+    }
+}
@@ -0,0 +1,206 @@
+use super::{HostVarConflict, Inventory};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// How to handle the issues found in an in-memory inventory before a run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InventoryCheck {
+    Skip,   // don't validate the inventory
+    Warn,   // run anyway, reporting every issue on the warnings of the execution
+    Reject, // refuse to run when an error is found, the other issues being reported as warnings
+}
+
+/// Issue found by the inventory validator
+#[derive(Debug, Clone, PartialEq)]
+pub enum InventoryIssue {
+    ConflictingHostVar(HostVarConflict), // host defined again with another value for a variable
+    UndefinedChildGroup { parent: String, child: String }, // child group which is neither declared nor given hosts, children or vars
+    CyclicChildren(Vec<String>), // groups of a cycle, the first one being repeated at the end
+    InvalidHostName(String),     // host which is neither a hostname nor an IP address
+    InvalidGroupName { group: String, safe_name: String }, // group name ansible warns about, and the name it would be turned into
+    GroupHostNameClash(String),                            // name used by both a group and a host
+}
+
+impl InventoryIssue {
+    /// Returns whether the issue breaks the inventory, the others being
+    /// warnings ansible also reports
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            InventoryIssue::InvalidGroupName { .. } | InventoryIssue::GroupHostNameClash(_)
+        )
+    }
+}
+
+impl fmt::Display for InventoryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InventoryIssue::ConflictingHostVar(conflict) => {
+                write!(
+                    f,
+                    "host '{}' is defined again with a conflicting value for '{}': {} then {}",
+                    conflict.host, conflict.var, conflict.previous, conflict.value
+                )?;
+                if conflict.line > 0 {
+                    write!(f, " (line {})", conflict.line)?;
+                }
+                Ok(())
+            }
+            InventoryIssue::UndefinedChildGroup { parent, child } => {
+                write!(f, "child group '{}' of '{}' is not defined", child, parent)
+            }
+            InventoryIssue::CyclicChildren(cycle) => {
+                write!(f, "cyclic group children: {}", cycle.join(" -> "))
+            }
+            InventoryIssue::InvalidHostName(host) => write!(f, "invalid host name '{}'", host),
+            InventoryIssue::InvalidGroupName { group, safe_name } => write!(
+                f,
+                "invalid characters in group name '{}', ansible may replace it with '{}'",
+                group, safe_name
+            ),
+            InventoryIssue::GroupHostNameClash(name) => {
+                write!(f, "found both group and host with same name: {}", name)
+            }
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// hostname label as ansible parses it: word characters and dashes, not
+// ending with a dash or an underscore
+fn is_valid_label(label: &str) -> bool {
+    let first = match label.chars().next() {
+        Some(c) => c,
+        None => return false,
+    };
+
+    is_word_char(first)
+        && label.chars().all(|c| is_word_char(c) || c == '-')
+        && !label.ends_with(['-', '_'])
+}
+
+/// Returns whether `name` is a hostname or an IP address ansible can connect to
+pub fn is_valid_host_name(name: &str) -> bool {
+    name.parse::<Ipv4Addr>().is_ok()
+        || name.parse::<Ipv6Addr>().is_ok()
+        || name.split('.').all(is_valid_label)
+}
+
+/// Returns the name ansible turns a group name into when it transforms
+/// invalid group characters: every character which can't be used in a
+/// python identifier becomes `_`
+pub fn safe_group_name(name: &str) -> String {
+    name.chars()
+        .enumerate()
+        .map(|(index, c)| {
+            if !is_word_char(c) || (index == 0 && c.is_ascii_digit()) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+impl Inventory {
+    // cycles of the group hierarchy, one per child link closing a cycle, each
+    // one starting from its smallest group
+    fn group_cycles(&self) -> Vec<Vec<String>> {
+        let mut cycles = BTreeSet::new();
+        let mut explored = BTreeSet::new();
+        let mut path = vec![];
+        for group in self.groups.keys() {
+            self.find_cycles(group, &mut path, &mut explored, &mut cycles);
+        }
+
+        cycles.into_iter().collect()
+    }
+
+    fn find_cycles(
+        &self,
+        group: &str,
+        path: &mut Vec<String>,
+        explored: &mut BTreeSet<String>,
+        cycles: &mut BTreeSet<Vec<String>>,
+    ) {
+        if let Some(start) = path.iter().position(|g| g == group) {
+            let mut cycle = path[start..].to_vec();
+            let smallest = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap_or(0);
+            cycle.rotate_left(smallest);
+            cycle.push(cycle[0].clone());
+            cycles.insert(cycle);
+            return;
+        }
+        if explored.contains(group) {
+            return;
+        }
+
+        if let Some(definition) = self.groups.get(group) {
+            path.push(group.to_string());
+            for child in &definition.children {
+                self.find_cycles(child, path, explored, cycles);
+            }
+            path.pop();
+        }
+        explored.insert(group.to_string());
+    }
+
+    /// Check the inventory for the mistakes ansible silently accepts or only
+    /// warns about: conflicting host definitions, undefined or cyclic child
+    /// groups, invalid host names and group names
+    pub fn validate(&self) -> Vec<InventoryIssue> {
+        let mut issues: Vec<InventoryIssue> = self
+            .conflicts
+            .iter()
+            .cloned()
+            .map(InventoryIssue::ConflictingHostVar)
+            .collect();
+
+        for host in self.hosts.keys() {
+            if !is_valid_host_name(host) {
+                issues.push(InventoryIssue::InvalidHostName(host.clone()));
+            }
+        }
+
+        for (name, group) in &self.groups {
+            // children only referenced by a `:children` section are created
+            // empty, while declared groups may be left empty
+            for child in &group.children {
+                let defined = !self.undeclared_groups.contains(child)
+                    || self.groups.get(child).is_some_and(|child| {
+                        !child.hosts.is_empty()
+                            || !child.children.is_empty()
+                            || !child.vars.is_empty()
+                    });
+                if !defined && !Self::is_implicit_group(child) {
+                    issues.push(InventoryIssue::UndefinedChildGroup {
+                        parent: name.clone(),
+                        child: child.clone(),
+                    });
+                }
+            }
+
+            let safe_name = safe_group_name(name);
+            if safe_name != *name {
+                issues.push(InventoryIssue::InvalidGroupName {
+                    group: name.clone(),
+                    safe_name,
+                });
+            }
+
+            if self.hosts.contains_key(name) {
+                issues.push(InventoryIssue::GroupHostNameClash(name.clone()));
+            }
+        }
+
+        for cycle in self.group_cycles() {
+            issues.push(InventoryIssue::CyclicChildren(cycle));
+        }
+
+        issues
+    }
+}
@@ -0,0 +1,225 @@
+use super::yaml::yaml_to_json;
+use super::{Inventory, InventoryVars, ALL_GROUP, UNGROUPED_GROUP};
+use serde_json::Value;
+use serde_yaml::Value as YamlValue;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Key ansible represents vault encrypted values with once converted to JSON
+pub const VAULT_KEY: &str = "__ansible_vault";
+/// Key ansible represents `!unsafe` values with once converted to JSON
+pub const UNSAFE_KEY: &str = "__ansible_unsafe";
+/// Header of vault encrypted files
+pub const VAULT_HEADER: &str = "$ANSIBLE_VAULT;";
+
+// extensions of vars files, the file or directory without extension being
+// looked up first
+const VARS_EXTENSIONS: [&str; 4] = ["", ".yml", ".yaml", ".json"];
+
+/// File read from a `group_vars/` or `host_vars/` directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryVarsFile {
+    pub path: PathBuf,   // path of the file
+    pub entity: String,  // group or host the variables are set on
+    pub encrypted: bool, // vault encrypted file, whose variables can't be read
+}
+
+/// Variables read from the `group_vars/` and `host_vars/` directories found
+/// next to an inventory or a playbook
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InventoryVarsTree {
+    pub group_vars: BTreeMap<String, InventoryVars>, // variables per group
+    pub host_vars: BTreeMap<String, InventoryVars>,  // variables per host
+    pub files: Vec<InventoryVarsFile>,               // files read, in loading order
+}
+
+/// Returns whether `content` is a vault encrypted file
+pub fn is_vault_encrypted(content: &str) -> bool {
+    content.trim_start().starts_with(VAULT_HEADER)
+}
+
+// hidden files and editor backups are skipped
+fn is_ignored(name: &str) -> bool {
+    name.starts_with('.') || name.ends_with('~')
+}
+
+// files of a vars directory, recursively and in name order
+fn directory_files(path: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+
+    let mut files = vec![];
+    for entry in entries {
+        let name = entry
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if is_ignored(&name) {
+            continue;
+        }
+
+        let extension = entry
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        if entry.is_dir() && extension.is_empty() {
+            files.append(&mut directory_files(&entry)?);
+        } else if entry.is_file() && VARS_EXTENSIONS.contains(&extension.as_str()) {
+            files.push(entry);
+        }
+    }
+
+    Ok(files)
+}
+
+// `web.yml`, `web.yaml`, `web.json` and `web` are the vars of `web`
+fn entity_name(name: &str) -> &str {
+    VARS_EXTENSIONS[1..]
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .filter(|entity| !entity.is_empty())
+        .unwrap_or(name)
+}
+
+// files holding the variables of every entity of a vars directory, the first
+// of `name`, `name.yml`, `name.yaml` and `name.json` found being used
+fn entity_files(directory: &Path) -> Result<BTreeMap<String, Vec<PathBuf>>, Box<dyn Error>> {
+    let mut entities: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    if !directory.is_dir() {
+        return Ok(entities);
+    }
+
+    let mut names: Vec<String> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !is_ignored(name))
+        .map(|name| entity_name(&name).to_string())
+        .collect();
+    names.sort();
+    names.dedup();
+
+    for name in names {
+        for extension in VARS_EXTENSIONS {
+            let path = directory.join(format!("{}{}", name, extension));
+            if path.is_dir() {
+                entities.insert(name.clone(), directory_files(&path)?);
+                break;
+            } else if path.is_file() {
+                entities.insert(name.clone(), vec![path]);
+                break;
+            }
+        }
+    }
+
+    Ok(entities)
+}
+
+// variables of a single file, `None` when it is vault encrypted
+fn read_vars_file(path: &Path) -> Result<Option<InventoryVars>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    if is_vault_encrypted(&content) {
+        return Ok(None);
+    }
+
+    let document: YamlValue = serde_yaml::from_str(&content)?;
+    match yaml_to_json(&document) {
+        Value::Null => Ok(Some(InventoryVars::new())),
+        Value::Object(vars) => Ok(Some(vars.into_iter().collect())),
+        _ => Err("variables file should hold a dictionary".into()),
+    }
+}
+
+impl InventoryVarsTree {
+    fn load_directory(&mut self, directory: &Path, host_vars: bool) -> Result<(), Box<dyn Error>> {
+        for (entity, files) in entity_files(directory)? {
+            let mut vars = InventoryVars::new();
+            for path in files {
+                let loaded = match read_vars_file(&path) {
+                    Ok(loaded) => loaded,
+                    Err(err) => {
+                        return Err(format!("(inventory::load) {}: {}", path.display(), err).into())
+                    }
+                };
+
+                self.files.push(InventoryVarsFile {
+                    path,
+                    entity: entity.clone(),
+                    encrypted: loaded.is_none(),
+                });
+                // later files override the variables of the previous ones
+                vars.extend(loaded.unwrap_or_default());
+            }
+
+            let target = if host_vars {
+                &mut self.host_vars
+            } else {
+                &mut self.group_vars
+            };
+            target.insert(entity, vars);
+        }
+
+        Ok(())
+    }
+
+    /// Read the `group_vars/` and `host_vars/` directories of `basedir`,
+    /// either of them may be missing. Vault encrypted files are listed
+    /// without their variables.
+    pub fn load(basedir: impl AsRef<Path>) -> Result<InventoryVarsTree, Box<dyn Error>> {
+        let basedir = basedir.as_ref();
+        let mut tree = InventoryVarsTree {
+            ..Default::default()
+        };
+
+        tree.load_directory(&basedir.join("group_vars"), false)?;
+        tree.load_directory(&basedir.join("host_vars"), true)?;
+
+        Ok(tree)
+    }
+
+    /// Returns the vault encrypted files whose variables could not be read
+    pub fn encrypted_files(&self) -> Vec<&Path> {
+        self.files
+            .iter()
+            .filter(|file| file.encrypted)
+            .map(|file| file.path.as_path())
+            .collect()
+    }
+}
+
+impl Inventory {
+    /// Attach the variables of `tree` to the hosts and groups of the
+    /// inventory, overriding the ones set by the inventory itself. As
+    /// ansible does, variables of unknown hosts and groups are ignored.
+    pub fn apply_vars(&mut self, tree: &InventoryVarsTree) {
+        for (group, vars) in &tree.group_vars {
+            let known =
+                group == ALL_GROUP || group == UNGROUPED_GROUP || self.groups.contains_key(group);
+            if known && !vars.is_empty() {
+                self.add_group(group).vars.extend(vars.clone());
+            }
+        }
+
+        for (host, vars) in &tree.host_vars {
+            if let Some(definition) = self.hosts.get_mut(host) {
+                definition.vars.extend(vars.clone());
+            }
+        }
+    }
+
+    /// Load the `group_vars/` and `host_vars/` directories of `basedir` and
+    /// attach their variables to the inventory
+    pub fn load_vars(
+        &mut self,
+        basedir: impl AsRef<Path>,
+    ) -> Result<InventoryVarsTree, Box<dyn Error>> {
+        let tree = InventoryVarsTree::load(basedir)?;
+        self.apply_vars(&tree);
+
+        Ok(tree)
+    }
+}
@@ -0,0 +1,271 @@
+use super::ini::split_host_port;
+use super::range::expand_range;
+use super::vars::VAULT_KEY;
+use super::{Inventory, InventoryParseError};
+use serde_json::{json, Value};
+use serde_yaml::{Mapping, Value as YamlValue};
+use std::collections::BTreeMap;
+
+const GROUP_KEYS: [&str; 3] = ["hosts", "vars", "children"];
+
+// serde_yaml values don't keep their location, semantic errors are reported
+// on the first line defining `key`
+fn key_line(content: &str, key: &str) -> usize {
+    key_line_after(content, key, 0)
+}
+
+// first line defining `key` after the line `after`
+fn key_line_after(content: &str, key: &str, after: usize) -> usize {
+    content
+        .lines()
+        .enumerate()
+        .skip(after)
+        .find(|(_, line)| {
+            let line = line.trim_start().trim_start_matches("- ");
+            [
+                key.to_string(),
+                format!("'{}'", key),
+                format!("\"{}\"", key),
+            ]
+            .iter()
+            .any(|k| {
+                line.strip_prefix(k.as_str())
+                    .is_some_and(|rest| rest.trim_start().starts_with(':'))
+            })
+        })
+        .map_or(0, |(index, _)| index + 1)
+}
+
+fn scalar_key(key: &YamlValue) -> Option<String> {
+    match key {
+        YamlValue::String(s) => Some(s.clone()),
+        YamlValue::Number(n) => Some(n.to_string()),
+        YamlValue::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Convert a YAML value to JSON as ansible does: `!vault` values become
+/// `{"__ansible_vault": ciphertext}`, other tags such as `!unsafe` are dropped
+pub(crate) fn yaml_to_json(value: &YamlValue) -> Value {
+    match value {
+        YamlValue::Null => Value::Null,
+        YamlValue::Bool(b) => Value::Bool(*b),
+        YamlValue::Number(n) => serde_json::to_value(n).unwrap_or(Value::Null),
+        YamlValue::String(s) => Value::String(s.clone()),
+        YamlValue::Sequence(items) => Value::Array(items.iter().map(yaml_to_json).collect()),
+        YamlValue::Mapping(mapping) => Value::Object(
+            mapping
+                .iter()
+                .map(|(key, value)| {
+                    let key = match scalar_key(key) {
+                        Some(key) => key,
+                        None => yaml_to_json(key).to_string(),
+                    };
+                    (key, yaml_to_json(value))
+                })
+                .collect(),
+        ),
+        YamlValue::Tagged(tagged) if tagged.tag == "vault" => {
+            json!({ VAULT_KEY: yaml_to_json(&tagged.value) })
+        }
+        YamlValue::Tagged(tagged) => yaml_to_json(&tagged.value),
+    }
+}
+
+fn type_name(value: &YamlValue) -> &str {
+    match value {
+        YamlValue::Null => "null",
+        YamlValue::Bool(_) => "bool",
+        YamlValue::Number(_) => "number",
+        YamlValue::String(_) => "string",
+        YamlValue::Sequence(_) => "list",
+        YamlValue::Mapping(_) => "dictionary",
+        YamlValue::Tagged(_) => "tagged value",
+    }
+}
+
+struct YamlInventoryParser<'a> {
+    content: &'a str,
+    inventory: Inventory,
+    host_lines: BTreeMap<String, usize>, // line of the latest definition of each host pattern
+}
+
+impl YamlInventoryParser<'_> {
+    fn error(&self, key: &str, message: String) -> InventoryParseError {
+        InventoryParseError::new(key_line(self.content, key), message)
+    }
+
+    // entries of the `hosts`, `vars` and `children` keys, which may be left empty
+    fn entries<'v>(
+        &self,
+        group: &str,
+        key: &str,
+        value: &'v YamlValue,
+    ) -> Result<Vec<(String, &'v YamlValue)>, InventoryParseError> {
+        let mapping = match value {
+            YamlValue::Null => return Ok(vec![]),
+            YamlValue::Mapping(mapping) => mapping,
+            value => {
+                return Err(self.error(
+                    group,
+                    format!(
+                        "Invalid \"{}\" entry for \"{}\" group, requires a dictionary, found {} instead",
+                        key,
+                        group,
+                        type_name(value)
+                    ),
+                ))
+            }
+        };
+
+        let mut entries = vec![];
+        for (name, value) in mapping {
+            match scalar_key(name) {
+                Some(name) => entries.push((name, value)),
+                None => {
+                    return Err(self.error(
+                        group,
+                        format!(
+                            "Invalid \"{}\" entry for \"{}\" group: {:?}",
+                            key, group, name
+                        ),
+                    ))
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn vars(
+        &self,
+        key: &str,
+        value: &YamlValue,
+    ) -> Result<Vec<(String, Value)>, InventoryParseError> {
+        let mut vars = vec![];
+        for (name, value) in self.entries(key, "vars", value)? {
+            vars.push((name, yaml_to_json(value)));
+        }
+
+        Ok(vars)
+    }
+
+    fn parse_group(&mut self, group: &str, value: &YamlValue) -> Result<(), InventoryParseError> {
+        self.inventory.add_group(group);
+
+        let mapping: &Mapping = match value {
+            YamlValue::Null => return Ok(()),
+            YamlValue::Mapping(mapping) => mapping,
+            value => {
+                return Err(self.error(
+                    group,
+                    format!(
+                    "Invalid definition of group \"{}\", requires a dictionary, found {} instead",
+                    group,
+                    type_name(value)
+                ),
+                ))
+            }
+        };
+
+        for (key, value) in mapping {
+            let key = scalar_key(key).unwrap_or_default();
+            match key.as_str() {
+                "hosts" => {
+                    for (pattern, host_vars) in self.entries(group, "hosts", value)? {
+                        let (pattern, port) =
+                            split_host_port(&pattern).map_err(|err| self.error(&pattern, err))?;
+                        let hosts =
+                            expand_range(&pattern).map_err(|err| self.error(&pattern, err))?;
+                        let mut vars = self.vars(&pattern, host_vars)?;
+                        if let Some(port) = port {
+                            vars.insert(0, ("ansible_port".to_string(), Value::from(port)));
+                        }
+
+                        let after = self.host_lines.get(&pattern).copied().unwrap_or(0);
+                        let line = key_line_after(self.content, &pattern, after);
+                        self.host_lines.insert(pattern.clone(), line);
+                        for host in hosts {
+                            self.inventory.add_host_to_group(group, &host);
+                            for (name, value) in &vars {
+                                self.inventory
+                                    .define_host_var(&host, name, value.clone(), line);
+                            }
+                        }
+                    }
+                }
+                "vars" => {
+                    for (name, value) in self.vars(group, value)? {
+                        self.inventory.set_group_var(group, &name, value);
+                    }
+                }
+                "children" => {
+                    for (child, definition) in self.entries(group, "children", value)? {
+                        self.parse_group(&child, definition)?;
+                        self.inventory.add_child_group(group, &child);
+                    }
+                }
+                key => self.inventory.warnings.push(format!(
+                    "Skipping unexpected key \"{}\" in group \"{}\", only \"{}\" are valid",
+                    key,
+                    group,
+                    GROUP_KEYS.join("\", \"")
+                )),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Inventory {
+    /// Parse an inventory in the format of ansible's YAML inventory plugin,
+    /// which JSON inventories also use
+    pub fn from_yaml(content: &str) -> Result<Inventory, InventoryParseError> {
+        let document: YamlValue = match serde_yaml::from_str(content) {
+            Ok(document) => document,
+            Err(err) => {
+                let line = err.location().map_or(0, |location| location.line());
+                return Err(InventoryParseError::new(line, err.to_string()));
+            }
+        };
+
+        let mapping = match document {
+            YamlValue::Mapping(mapping) => mapping,
+            YamlValue::Null => return Err(InventoryParseError::new(0, "Parsed empty YAML file")),
+            value => {
+                return Err(InventoryParseError::new(
+                    1,
+                    format!(
+                        "YAML inventory has invalid structure, it should be a dictionary, got: {}",
+                        type_name(&value)
+                    ),
+                ))
+            }
+        };
+
+        let mut parser = YamlInventoryParser {
+            content,
+            inventory: Inventory::new(),
+            host_lines: BTreeMap::new(),
+        };
+        for (group, definition) in &mapping {
+            let group = match scalar_key(group) {
+                Some(group) => group,
+                None => {
+                    return Err(InventoryParseError::new(
+                        0,
+                        format!("Invalid group name: {:?}", group),
+                    ))
+                }
+            };
+            parser.parse_group(&group, definition)?;
+        }
+
+        let mut inventory = parser.inventory;
+        inventory.normalize_implicit_groups();
+
+        Ok(inventory)
+    }
+}
@@ -1,7 +1,33 @@
+mod analyze;
+mod config;
+mod doc;
+mod embed;
 mod executor;
+mod galaxy;
+mod inventory;
+mod listing;
+mod model;
 mod options;
 mod playbook;
+mod pull;
+mod shard;
+mod source;
+mod variables;
+mod version;
 
+pub use analyze::*;
+pub use config::*;
+pub use doc::*;
+pub use embed::*;
 pub use executor::*;
+pub use galaxy::*;
+pub use inventory::*;
+pub use listing::*;
+pub use model::*;
 pub use options::*;
 pub use playbook::*;
+pub use pull::*;
+pub use shard::*;
+pub use source::*;
+pub use variables::*;
+pub use version::*;
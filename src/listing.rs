@@ -0,0 +1,267 @@
+use crate::playbook::{AnsiblePlaybookCmd, AnsiblePlaybookOptions};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::error::Error;
+
+/// Task of a play as listed by `--list-tasks`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListedTask {
+    pub name: String,      // name of the task, its action when it has none
+    pub role: String,      // role the task belongs to, empty for the tasks of the play
+    pub tags: Vec<String>, // tags of the task, the inherited ones included
+}
+
+/// Play as listed by `--list-hosts`, `--list-tasks` and `--list-tags`, the
+/// sections of the flags which were not given being empty
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListedPlay {
+    pub playbook: String,       // playbook the play belongs to
+    pub number: usize,          // position of the play in the playbook, from 1
+    pub name: String,           // name of the play
+    pub host_list: String,      // hosts of the play as written, comma separated, templates included
+    pub tags: Vec<String>,      // tags of the play
+    pub pattern: Vec<String>,   // host patterns of the play, from `--list-hosts`
+    pub hosts: Vec<String>,     // hosts the play targets, from `--list-hosts`
+    pub tasks: Vec<ListedTask>, // tasks of the play, from `--list-tasks`
+    pub task_tags: Vec<String>, // tags of the play and its tasks, from `--list-tags`
+}
+
+/// Plays listed by `ansible-playbook` for every playbook it was given
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaybookListing {
+    pub plays: Vec<ListedPlay>, // plays of every playbook, in order
+}
+
+impl PlaybookListing {
+    /// Returns the playbooks listed, in order
+    pub fn playbooks(&self) -> Vec<String> {
+        let mut playbooks: Vec<String> = vec![];
+        for play in &self.plays {
+            if !playbooks.contains(&play.playbook) {
+                playbooks.push(play.playbook.clone());
+            }
+        }
+
+        playbooks
+    }
+
+    /// Returns the hosts targeted by any play
+    pub fn hosts(&self) -> BTreeSet<String> {
+        self.plays
+            .iter()
+            .flat_map(|play| play.hosts.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the tags of every play, from `--list-tags`
+    pub fn tags(&self) -> BTreeSet<String> {
+        self.plays
+            .iter()
+            .flat_map(|play| play.task_tags.iter().cloned())
+            .collect()
+    }
+}
+
+// section of a play the lines being parsed belong to
+#[derive(PartialEq)]
+enum ListingSection {
+    Play,
+    Hosts(usize), // number of hosts announced
+    Tasks,
+}
+
+// items of a `[a, b]` tag list, ansible separating the play tags with a
+// comma only and the task tags with a comma and a space
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+// items of the python list ansible prints as host pattern, eg. `['web', '!db']`
+fn split_pattern(pattern: &str) -> Vec<String> {
+    let pattern = pattern.trim();
+    let pattern = pattern
+        .strip_prefix('[')
+        .and_then(|pattern| pattern.strip_suffix(']'))
+        .unwrap_or(pattern);
+
+    pattern
+        .split(',')
+        .map(|item| {
+            item.trim()
+                .trim_matches(|c| c == '\'' || c == '"')
+                .to_string()
+        })
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+// task of a `--list-tasks` line, unnamed tasks being printed as their action
+// and the named tasks of roles as `role : name`
+fn listed_task(name: &str, tags: &str) -> ListedTask {
+    let name = name.trim();
+    let (role, name) = match name.split_once(" : ") {
+        Some((role, name)) => (role.to_string(), name.to_string()),
+        None => (String::new(), name.to_string()),
+    };
+
+    ListedTask {
+        name,
+        role,
+        tags: split_tags(tags),
+    }
+}
+
+fn check_hosts(play: Option<&ListedPlay>, section: &ListingSection) -> Result<(), String> {
+    match (play, section) {
+        (Some(play), ListingSection::Hosts(count)) if play.hosts.len() != *count => Err(format!(
+            "play #{} lists {} hosts out of {}",
+            play.number,
+            play.hosts.len(),
+            count
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Parse the output of `ansible-playbook` run with any of `--list-hosts`,
+/// `--list-tasks` and `--list-tags`, as printed by ansible-core 2.12 and
+/// later
+pub fn parse_playbook_listing(output: &str) -> Result<PlaybookListing, Box<dyn Error>> {
+    let play_re = Regex::new(r"^play #(\d+) \((.*?)\): (.*?)[\t ]+TAGS: \[(.*)\]$")
+        .expect("(listing::parse_playbook_listing) play regex");
+    let hosts_re =
+        Regex::new(r"^hosts \((\d+)\):$").expect("(listing::parse_playbook_listing) hosts regex");
+    let tagged_re = Regex::new(r"^(.*?)[\t ]+TAGS: \[(.*)\]$")
+        .expect("(listing::parse_playbook_listing) task regex");
+
+    let mut listing = PlaybookListing::default();
+    let mut playbook = String::new();
+    let mut section = ListingSection::Play;
+
+    let invalid = |number: usize, line: &str, reason: &str| -> Box<dyn Error> {
+        format!(
+            "(listing::parse_playbook_listing) line {}: {}: '{}'",
+            number + 1,
+            reason,
+            line
+        )
+        .into()
+    };
+
+    for (number, line) in output.lines().enumerate() {
+        let line = line.trim();
+        // warnings may be printed along with the listing
+        if line.is_empty()
+            || line.starts_with("[WARNING]")
+            || line.starts_with("[DEPRECATION WARNING]")
+        {
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix("playbook: ") {
+            check_hosts(listing.plays.last(), &section)
+                .map_err(|err| invalid(number, line, &err))?;
+            playbook = path.to_string();
+            section = ListingSection::Play;
+            continue;
+        }
+
+        if let Some(captures) = play_re.captures(line) {
+            check_hosts(listing.plays.last(), &section)
+                .map_err(|err| invalid(number, line, &err))?;
+            listing.plays.push(ListedPlay {
+                playbook: playbook.clone(),
+                number: captures[1]
+                    .parse()
+                    .map_err(|_| invalid(number, line, "invalid play number"))?,
+                name: captures[3].to_string(),
+                host_list: captures[2].to_string(),
+                tags: split_tags(&captures[4]),
+                ..Default::default()
+            });
+            section = ListingSection::Play;
+            continue;
+        }
+
+        let play = match listing.plays.last_mut() {
+            Some(play) => play,
+            None => return Err(invalid(number, line, "expected a play")),
+        };
+
+        // the pattern and host count only follow the play line, so that
+        // tasks and hosts may be named alike
+        let header = section == ListingSection::Play;
+        if let Some(pattern) = line.strip_prefix("pattern: ").filter(|_| header) {
+            play.pattern = split_pattern(pattern);
+        } else if let Some(captures) = hosts_re.captures(line).filter(|_| header) {
+            let count = captures[1]
+                .parse()
+                .map_err(|_| invalid(number, line, "invalid host count"))?;
+            section = ListingSection::Hosts(count);
+        } else if line == "tasks:" {
+            check_hosts(Some(play), &section).map_err(|err| invalid(number, line, &err))?;
+            section = ListingSection::Tasks;
+        } else if let Some(tags) = line
+            .strip_prefix("TASK TAGS: [")
+            .and_then(|tags| tags.strip_suffix(']'))
+        {
+            check_hosts(Some(play), &section).map_err(|err| invalid(number, line, &err))?;
+            play.task_tags = split_tags(tags);
+            section = ListingSection::Play;
+        } else if section == ListingSection::Tasks {
+            match tagged_re.captures(line) {
+                Some(captures) => play.tasks.push(listed_task(&captures[1], &captures[2])),
+                None => return Err(invalid(number, line, "expected a task")),
+            }
+        } else if let ListingSection::Hosts(_) = section {
+            play.hosts.push(line.to_string());
+        } else {
+            return Err(invalid(number, line, "unexpected line"));
+        }
+    }
+    check_hosts(listing.plays.last(), &section)
+        .map_err(|err| format!("(listing::parse_playbook_listing) {}", err))?;
+
+    Ok(listing)
+}
+
+impl AnsiblePlaybookCmd {
+    /// Run `ansible-playbook` with the listing flags set on the options, or
+    /// all of `--list-hosts`, `--list-tasks` and `--list-tags` when none is,
+    /// and return the parsed listing
+    pub fn list_playbooks(&self) -> Result<PlaybookListing, Box<dyn Error>> {
+        let mut options = AnsiblePlaybookOptions {
+            ..self.options.clone()
+        };
+        if !options.list_hosts && !options.list_tasks && !options.list_tags {
+            options.list_hosts = true;
+            options.list_tasks = true;
+            options.list_tags = true;
+        }
+        let list = AnsiblePlaybookCmd {
+            options,
+            ..self.clone()
+        };
+
+        let child = match list.run() {
+            Ok(child) => child,
+            Err(err) => return Err(format!("(listing::list_playbooks) {}", err).into()),
+        };
+        let output = match child.wait_with_output() {
+            Ok(output) => output,
+            Err(err) => return Err(format!("(listing::list_playbooks) {}", err).into()),
+        };
+        if !output.status.success() {
+            return Err(format!(
+                "(listing::list_playbooks) {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+
+        parse_playbook_listing(&String::from_utf8_lossy(&output.stdout))
+    }
+}
@@ -0,0 +1,671 @@
+use crate::inventory::{yaml_to_json, UNSAFE_KEY, VAULT_KEY};
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Mapping, Value as YamlValue};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// keywords of tasks, the other key of a task being its module
+const TASK_KEYWORDS: [&str; 42] = [
+    "action",
+    "any_errors_fatal",
+    "args",
+    "async",
+    "become",
+    "become_exe",
+    "become_flags",
+    "become_method",
+    "become_user",
+    "changed_when",
+    "check_mode",
+    "collections",
+    "connection",
+    "debugger",
+    "delay",
+    "delegate_facts",
+    "delegate_to",
+    "diff",
+    "environment",
+    "failed_when",
+    "ignore_errors",
+    "ignore_unreachable",
+    "listen",
+    "local_action",
+    "loop",
+    "loop_control",
+    "module_defaults",
+    "name",
+    "no_log",
+    "notify",
+    "poll",
+    "port",
+    "register",
+    "remote_user",
+    "retries",
+    "run_once",
+    "tags",
+    "throttle",
+    "timeout",
+    "until",
+    "vars",
+    "when",
+];
+
+// module argument holding the free-form arguments, as in `command: ls -l`
+pub(crate) const RAW_PARAMS: &str = "_raw_params";
+
+/// Task, or block of tasks, found in a task list
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskEntry {
+    Task(Task),
+    Block(Block),
+}
+
+/// Task running a module
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Task {
+    pub name: String,                 // name of the task
+    pub module: String,               // module run by the task, such as `ansible.builtin.copy`
+    pub args: Map<String, Value>, // module arguments, the free-form ones being kept as `_raw_params`
+    pub when: Vec<String>,        // conditions which must all be true for the task to run
+    pub loop_items: Option<Value>, // `loop` items, or the expression returning them
+    pub register: String,         // variable the result of the task is registered in
+    pub notify: Vec<String>,      // handlers notified when the task changes something
+    pub tags: Vec<String>,        // tags of the task
+    pub do_become: Option<bool>, // `become`, run the task with privilege escalation, templated values being left in `keywords`
+    pub become_user: String,     // user the task becomes
+    pub delegate_to: String,     // host the task runs on instead of the current one
+    pub vars: Map<String, Value>, // variables of the task
+    pub keywords: Map<String, Value>, // other keywords, such as `retries` or `with_items`
+}
+
+/// Block grouping tasks, with the tasks run when one of them fails and the
+/// ones always run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Block {
+    pub name: String,                 // name of the block
+    pub block: Vec<TaskEntry>,        // tasks of the block
+    pub rescue: Vec<TaskEntry>,       // tasks run when a task of the block fails
+    pub always: Vec<TaskEntry>,       // tasks run whatever the result of the block
+    pub when: Vec<String>,            // conditions which must all be true for the block to run
+    pub tags: Vec<String>,            // tags inherited by the tasks of the block
+    pub do_become: Option<bool>, // `become`, run the tasks with privilege escalation, templated values being left in `keywords`
+    pub become_user: String,     // user the tasks become
+    pub delegate_to: String,     // host the tasks run on instead of the current one
+    pub vars: Map<String, Value>, // variables of the block
+    pub keywords: Map<String, Value>, // other keywords, such as `ignore_errors`
+}
+
+/// Handler, a task run once at the end of the play when notified
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Handler {
+    pub task: Task,          // task run by the handler, notified through its name
+    pub listen: Vec<String>, // topics the handler is notified through too
+}
+
+/// Role applied by a play
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoleRef {
+    pub role: String,                 // name or path of the role
+    pub vars: Map<String, Value>,     // variables given to the role
+    pub when: Vec<String>,            // conditions which must all be true for the role to run
+    pub tags: Vec<String>,            // tags inherited by the tasks of the role
+    pub do_become: Option<bool>, // `become`, run the role with privilege escalation, templated values being left in `keywords`
+    pub become_user: String,     // user the role becomes
+    pub keywords: Map<String, Value>, // other keywords, and the role parameters given next to `role`
+}
+
+/// Play of a playbook, or import of another playbook when `import_playbook`
+/// is set
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Play {
+    pub name: String,                 // name of the play
+    pub hosts: String,                // pattern of the hosts the play targets
+    pub import_playbook: String,      // playbook imported in place of the play
+    pub gather_facts: Option<bool>, // gather facts before running the tasks, templated values being left in `keywords`
+    pub do_become: Option<bool>, // `become`, run the play with privilege escalation, templated values being left in `keywords`
+    pub become_user: String,     // user the play becomes
+    pub remote_user: String,     // user connecting to the hosts
+    pub connection: String,      // connection plugin, such as `local`
+    pub vars: Map<String, Value>, // variables of the play
+    pub vars_files: Vec<String>, // files the variables of the play are read from
+    pub roles: Vec<RoleRef>,     // roles applied before the tasks
+    pub pre_tasks: Vec<TaskEntry>, // tasks run before the roles
+    pub tasks: Vec<TaskEntry>,   // tasks run after the roles
+    pub post_tasks: Vec<TaskEntry>, // tasks run after the tasks
+    pub handlers: Vec<Handler>,  // handlers of the play
+    pub tags: Vec<String>,       // tags inherited by every task of the play
+    pub keywords: Map<String, Value>, // other keywords, such as `serial` or `strategy`
+}
+
+/// Playbook, the list of its plays
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Playbook {
+    pub plays: Vec<Play>,
+}
+
+// string of a scalar value, keywords such as `become_user` accepting numbers
+fn scalar_string(value: Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        value => Err(format!("expected a string, got {}", value)),
+    }
+}
+
+// `key` removed from `map`, each helper converting the value of a keyword
+fn take_string(map: &mut Map<String, Value>, key: &str) -> Result<String, String> {
+    match map.remove(key) {
+        None | Some(Value::Null) => Ok(String::new()),
+        Some(value) => scalar_string(value).map_err(|err| format!("'{}': {}", key, err)),
+    }
+}
+
+// a string or a list of strings, comma separated strings being split when
+// `split` is set as ansible does for tags
+fn take_list(map: &mut Map<String, Value>, key: &str, split: bool) -> Result<Vec<String>, String> {
+    let items = match map.remove(key) {
+        None | Some(Value::Null) => return Ok(vec![]),
+        Some(Value::Array(items)) => items,
+        Some(value) => vec![value],
+    };
+
+    let mut list = vec![];
+    for item in items {
+        let item = scalar_string(item).map_err(|err| format!("'{}': {}", key, err))?;
+        if split {
+            list.extend(item.split(',').map(|i| i.trim().to_string()));
+        } else {
+            list.push(item);
+        }
+    }
+
+    Ok(list)
+}
+
+// templated booleans, such as `become: "{{ use_become }}"`, are left in `map`
+// to be kept along with the other keywords
+fn take_bool(map: &mut Map<String, Value>, key: &str) -> Result<Option<bool>, String> {
+    match map.remove(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bool(b)) => Ok(Some(b)),
+        Some(Value::String(s)) => match s.to_lowercase().as_str() {
+            "yes" | "true" | "on" | "y" => Ok(Some(true)),
+            "no" | "false" | "off" | "n" => Ok(Some(false)),
+            _ if s.contains("{{") => {
+                map.insert(key.to_string(), Value::String(s));
+                Ok(None)
+            }
+            _ => Err(format!("'{}': expected a boolean, got '{}'", key, s)),
+        },
+        Some(value) => Err(format!("'{}': expected a boolean, got {}", key, value)),
+    }
+}
+
+fn take_map(map: &mut Map<String, Value>, key: &str) -> Result<Map<String, Value>, String> {
+    match map.remove(key) {
+        None | Some(Value::Null) => Ok(Map::new()),
+        Some(Value::Object(vars)) => Ok(vars),
+        Some(value) => Err(format!("'{}': expected a mapping, got {}", key, value)),
+    }
+}
+
+fn take_items<T>(
+    map: &mut Map<String, Value>,
+    key: &str,
+    from_value: fn(Value) -> Result<T, String>,
+) -> Result<Vec<T>, String> {
+    let items = match map.remove(key) {
+        None | Some(Value::Null) => return Ok(vec![]),
+        Some(Value::Array(items)) => items,
+        Some(value) => return Err(format!("'{}': expected a list, got {}", key, value)),
+    };
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| from_value(item).map_err(|err| format!("{}[{}]: {}", key, index, err)))
+        .collect()
+}
+
+fn into_map(value: Value, what: &str) -> Result<Map<String, Value>, String> {
+    match value {
+        Value::Object(map) => Ok(map),
+        value => Err(format!("expected a {}, got {}", what, value)),
+    }
+}
+
+// setters of the keywords which are set, in the order they are written in
+fn put_string(map: &mut Map<String, Value>, key: &str, value: &str) {
+    if !value.is_empty() {
+        map.insert(key.to_string(), Value::String(value.to_string()));
+    }
+}
+
+fn put_list(map: &mut Map<String, Value>, key: &str, list: &[String]) {
+    if !list.is_empty() {
+        map.insert(key.to_string(), Value::from(list.to_vec()));
+    }
+}
+
+// a single condition is written as a string, as usually done
+fn put_conditions(map: &mut Map<String, Value>, key: &str, conditions: &[String]) {
+    match conditions {
+        [] => {}
+        [condition] => put_string(map, key, condition),
+        conditions => put_list(map, key, conditions),
+    }
+}
+
+fn put_bool(map: &mut Map<String, Value>, key: &str, value: Option<bool>) {
+    if let Some(value) = value {
+        map.insert(key.to_string(), Value::Bool(value));
+    }
+}
+
+fn put_map(map: &mut Map<String, Value>, key: &str, value: &Map<String, Value>) {
+    if !value.is_empty() {
+        map.insert(key.to_string(), Value::Object(value.clone()));
+    }
+}
+
+fn put_items<T>(map: &mut Map<String, Value>, key: &str, items: &[T], to_value: fn(&T) -> Value) {
+    if !items.is_empty() {
+        map.insert(key.to_string(), items.iter().map(to_value).collect());
+    }
+}
+
+fn is_task_keyword(key: &str) -> bool {
+    TASK_KEYWORDS.contains(&key) || key.starts_with("with_")
+}
+
+impl TaskEntry {
+    fn from_value(value: Value) -> Result<TaskEntry, String> {
+        match value.get("block") {
+            Some(_) => Block::from_value(value).map(TaskEntry::Block),
+            None => Task::from_value(value).map(TaskEntry::Task),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            TaskEntry::Task(task) => task.to_value(),
+            TaskEntry::Block(block) => block.to_value(),
+        }
+    }
+}
+
+impl Task {
+    fn from_value(value: Value) -> Result<Task, String> {
+        let mut map = into_map(value, "task")?;
+        let mut task = Task {
+            name: take_string(&mut map, "name")?,
+            when: take_list(&mut map, "when", false)?,
+            loop_items: map.remove("loop"),
+            register: take_string(&mut map, "register")?,
+            notify: take_list(&mut map, "notify", false)?,
+            tags: take_list(&mut map, "tags", true)?,
+            do_become: take_bool(&mut map, "become")?,
+            become_user: take_string(&mut map, "become_user")?,
+            delegate_to: take_string(&mut map, "delegate_to")?,
+            vars: take_map(&mut map, "vars")?,
+            ..Default::default()
+        };
+
+        let modules: Vec<String> = map
+            .keys()
+            .filter(|key| !is_task_keyword(key))
+            .cloned()
+            .collect();
+        match modules.as_slice() {
+            [module] => {
+                task.args = match map.remove(module) {
+                    Some(Value::Object(args)) => args,
+                    Some(Value::Null) | None => Map::new(),
+                    Some(value) => {
+                        let raw =
+                            scalar_string(value).map_err(|err| format!("'{}': {}", module, err))?;
+                        Map::from_iter([(RAW_PARAMS.to_string(), Value::String(raw))])
+                    }
+                };
+                task.module = module.clone();
+            }
+            // `action` and `local_action` name the module in their value
+            [] if map.contains_key("action") || map.contains_key("local_action") => {}
+            [] => return Err("no module found in task".into()),
+            modules => return Err(format!("conflicting modules: {}", modules.join(", "))),
+        }
+        task.keywords = map;
+
+        Ok(task)
+    }
+
+    fn to_value(&self) -> Value {
+        let mut map = Map::new();
+        put_string(&mut map, "name", &self.name);
+        if !self.module.is_empty() {
+            let args = match self.args.get(RAW_PARAMS) {
+                Some(raw) if self.args.len() == 1 => raw.clone(),
+                _ if self.args.is_empty() => Value::Null,
+                _ => Value::Object(self.args.clone()),
+            };
+            map.insert(self.module.clone(), args);
+        }
+        put_conditions(&mut map, "when", &self.when);
+        if let Some(items) = &self.loop_items {
+            map.insert("loop".into(), items.clone());
+        }
+        put_string(&mut map, "register", &self.register);
+        put_list(&mut map, "notify", &self.notify);
+        put_list(&mut map, "tags", &self.tags);
+        put_bool(&mut map, "become", self.do_become);
+        put_string(&mut map, "become_user", &self.become_user);
+        put_string(&mut map, "delegate_to", &self.delegate_to);
+        put_map(&mut map, "vars", &self.vars);
+        map.extend(self.keywords.clone());
+
+        Value::Object(map)
+    }
+}
+
+impl Block {
+    fn from_value(value: Value) -> Result<Block, String> {
+        let mut map = into_map(value, "block")?;
+        Ok(Block {
+            name: take_string(&mut map, "name")?,
+            block: take_items(&mut map, "block", TaskEntry::from_value)?,
+            rescue: take_items(&mut map, "rescue", TaskEntry::from_value)?,
+            always: take_items(&mut map, "always", TaskEntry::from_value)?,
+            when: take_list(&mut map, "when", false)?,
+            tags: take_list(&mut map, "tags", true)?,
+            do_become: take_bool(&mut map, "become")?,
+            become_user: take_string(&mut map, "become_user")?,
+            delegate_to: take_string(&mut map, "delegate_to")?,
+            vars: take_map(&mut map, "vars")?,
+            keywords: map,
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let mut map = Map::new();
+        put_string(&mut map, "name", &self.name);
+        map.insert(
+            "block".into(),
+            self.block.iter().map(TaskEntry::to_value).collect(),
+        );
+        put_items(&mut map, "rescue", &self.rescue, TaskEntry::to_value);
+        put_items(&mut map, "always", &self.always, TaskEntry::to_value);
+        put_conditions(&mut map, "when", &self.when);
+        put_list(&mut map, "tags", &self.tags);
+        put_bool(&mut map, "become", self.do_become);
+        put_string(&mut map, "become_user", &self.become_user);
+        put_string(&mut map, "delegate_to", &self.delegate_to);
+        put_map(&mut map, "vars", &self.vars);
+        map.extend(self.keywords.clone());
+
+        Value::Object(map)
+    }
+}
+
+impl Handler {
+    fn from_value(value: Value) -> Result<Handler, String> {
+        let mut map = into_map(value, "handler")?;
+        let listen = take_list(&mut map, "listen", false)?;
+
+        Ok(Handler {
+            task: Task::from_value(Value::Object(map))?,
+            listen,
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let mut value = self.task.to_value();
+        if let Value::Object(map) = &mut value {
+            put_list(map, "listen", &self.listen);
+        }
+
+        value
+    }
+}
+
+impl RoleRef {
+    fn from_value(value: Value) -> Result<RoleRef, String> {
+        let mut map = match value {
+            Value::String(role) => {
+                return Ok(RoleRef {
+                    role,
+                    ..Default::default()
+                })
+            }
+            value => into_map(value, "role")?,
+        };
+
+        let mut role = take_string(&mut map, "role")?;
+        if role.is_empty() {
+            role = take_string(&mut map, "name")?;
+        }
+        if role.is_empty() {
+            return Err("no role name found".into());
+        }
+
+        Ok(RoleRef {
+            role,
+            vars: take_map(&mut map, "vars")?,
+            when: take_list(&mut map, "when", false)?,
+            tags: take_list(&mut map, "tags", true)?,
+            do_become: take_bool(&mut map, "become")?,
+            become_user: take_string(&mut map, "become_user")?,
+            keywords: map,
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let mut map = Map::new();
+        put_string(&mut map, "role", &self.role);
+        put_map(&mut map, "vars", &self.vars);
+        put_conditions(&mut map, "when", &self.when);
+        put_list(&mut map, "tags", &self.tags);
+        put_bool(&mut map, "become", self.do_become);
+        put_string(&mut map, "become_user", &self.become_user);
+        map.extend(self.keywords.clone());
+
+        // a role without keywords is written as its name
+        match map.len() {
+            1 => Value::String(self.role.clone()),
+            _ => Value::Object(map),
+        }
+    }
+}
+
+impl Play {
+    fn from_value(value: Value) -> Result<Play, String> {
+        let mut map = into_map(value, "play")?;
+        // a list of host patterns is the same as their comma separated union
+        let hosts = take_list(&mut map, "hosts", false)?.join(",");
+
+        Ok(Play {
+            name: take_string(&mut map, "name")?,
+            hosts,
+            import_playbook: take_string(&mut map, "import_playbook")?,
+            gather_facts: take_bool(&mut map, "gather_facts")?,
+            do_become: take_bool(&mut map, "become")?,
+            become_user: take_string(&mut map, "become_user")?,
+            remote_user: take_string(&mut map, "remote_user")?,
+            connection: take_string(&mut map, "connection")?,
+            vars: take_map(&mut map, "vars")?,
+            vars_files: take_list(&mut map, "vars_files", false)?,
+            roles: take_items(&mut map, "roles", RoleRef::from_value)?,
+            pre_tasks: take_items(&mut map, "pre_tasks", TaskEntry::from_value)?,
+            tasks: take_items(&mut map, "tasks", TaskEntry::from_value)?,
+            post_tasks: take_items(&mut map, "post_tasks", TaskEntry::from_value)?,
+            handlers: take_items(&mut map, "handlers", Handler::from_value)?,
+            tags: take_list(&mut map, "tags", true)?,
+            keywords: map,
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        let mut map = Map::new();
+        put_string(&mut map, "name", &self.name);
+        put_string(&mut map, "import_playbook", &self.import_playbook);
+        put_string(&mut map, "hosts", &self.hosts);
+        put_bool(&mut map, "gather_facts", self.gather_facts);
+        put_bool(&mut map, "become", self.do_become);
+        put_string(&mut map, "become_user", &self.become_user);
+        put_string(&mut map, "remote_user", &self.remote_user);
+        put_string(&mut map, "connection", &self.connection);
+        put_list(&mut map, "tags", &self.tags);
+        put_map(&mut map, "vars", &self.vars);
+        put_list(&mut map, "vars_files", &self.vars_files);
+        map.extend(self.keywords.clone());
+        put_items(&mut map, "roles", &self.roles, RoleRef::to_value);
+        put_items(&mut map, "pre_tasks", &self.pre_tasks, TaskEntry::to_value);
+        put_items(&mut map, "tasks", &self.tasks, TaskEntry::to_value);
+        put_items(
+            &mut map,
+            "post_tasks",
+            &self.post_tasks,
+            TaskEntry::to_value,
+        );
+        put_items(&mut map, "handlers", &self.handlers, Handler::to_value);
+
+        Value::Object(map)
+    }
+}
+
+impl Playbook {
+    fn from_value(value: Value) -> Result<Playbook, String> {
+        match value {
+            // an empty file is an empty playbook
+            Value::Null => Ok(Playbook::default()),
+            Value::Array(plays) => Ok(Playbook {
+                plays: plays
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, play)| {
+                        Play::from_value(play).map_err(|err| format!("play {}: {}", index, err))
+                    })
+                    .collect::<Result<_, _>>()?,
+            }),
+            value => Err(format!("expected a list of plays, got {}", value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        self.plays.iter().map(Play::to_value).collect()
+    }
+
+    /// Parse a playbook from its YAML content, `!vault` and `!unsafe` values
+    /// being kept as the [`VAULT_KEY`] and [`UNSAFE_KEY`] objects ansible
+    /// converts them to, and tagged again by `to_yaml`
+    pub fn from_yaml(content: &str) -> Result<Playbook, Box<dyn Error>> {
+        match serde_yaml::from_str(content) {
+            Ok(playbook) => Ok(playbook),
+            Err(err) => Err(format!("(model::from_yaml) {}", err).into()),
+        }
+    }
+
+    /// Read a playbook file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Playbook, Box<dyn Error>> {
+        let path = path.as_ref();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                return Err(format!("(model::from_file) {}: {}", path.display(), err).into())
+            }
+        };
+
+        match Playbook::from_yaml(&content) {
+            Ok(playbook) => Ok(playbook),
+            Err(err) => Err(format!("(model::from_file) {}: {}", path.display(), err).into()),
+        }
+    }
+
+    /// Render the playbook as YAML, keywords being written in the order
+    /// ansible documents them: names first and task lists last
+    pub fn to_yaml(&self) -> Result<String, Box<dyn Error>> {
+        match serde_yaml::to_string(self) {
+            Ok(yaml) => Ok(format!("---\n{}", yaml)),
+            Err(err) => Err(format!("(model::to_yaml) {}", err).into()),
+        }
+    }
+}
+
+// JSON value of playbook content, `!vault` and `!unsafe` values being
+// represented as ansible does once converted to JSON
+pub(crate) fn playbook_json(value: YamlValue) -> Value {
+    fn mark_unsafe(value: YamlValue) -> YamlValue {
+        match value {
+            YamlValue::Sequence(items) => {
+                YamlValue::Sequence(items.into_iter().map(mark_unsafe).collect())
+            }
+            YamlValue::Mapping(mapping) => YamlValue::Mapping(
+                mapping
+                    .into_iter()
+                    .map(|(key, value)| (key, mark_unsafe(value)))
+                    .collect(),
+            ),
+            YamlValue::Tagged(tagged) if tagged.tag == "unsafe" => {
+                YamlValue::Mapping(Mapping::from_iter([(UNSAFE_KEY.into(), tagged.value)]))
+            }
+            YamlValue::Tagged(tagged) => YamlValue::Tagged(Box::new(TaggedValue {
+                tag: tagged.tag,
+                value: mark_unsafe(tagged.value),
+            })),
+            value => value,
+        }
+    }
+
+    yaml_to_json(&mark_unsafe(value))
+}
+
+// YAML value of playbook content, tagging back the `!vault` and `!unsafe`
+// values
+fn playbook_yaml(value: &Value) -> YamlValue {
+    match value {
+        Value::Object(map) => match map.iter().next() {
+            Some((key, inner))
+                if map.len() == 1 && [VAULT_KEY, UNSAFE_KEY].contains(&key.as_str()) =>
+            {
+                YamlValue::Tagged(Box::new(TaggedValue {
+                    tag: Tag::new(key.trim_start_matches("__ansible_")),
+                    value: playbook_yaml(inner),
+                }))
+            }
+            _ => YamlValue::Mapping(
+                map.iter()
+                    .map(|(key, value)| (YamlValue::String(key.clone()), playbook_yaml(value)))
+                    .collect(),
+            ),
+        },
+        Value::Array(items) => YamlValue::Sequence(items.iter().map(playbook_yaml).collect()),
+        value => serde_yaml::to_value(value).unwrap_or(YamlValue::Null),
+    }
+}
+
+// (de)serialization through the YAML structure of the type
+macro_rules! serde_through_value {
+    ($($model:ty),*) => {
+        $(
+            impl Serialize for $model {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    playbook_yaml(&self.to_value()).serialize(serializer)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $model {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    <$model>::from_value(playbook_json(YamlValue::deserialize(deserializer)?))
+                        .map_err(de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+serde_through_value!(Playbook, Play, TaskEntry, Task, Block, Handler, RoleRef);
@@ -22,6 +22,7 @@ pub fn ansible_set_env(key: &str, value: &str) {
 pub struct AnsibleConnectionOptions {
     pub ask_pass: bool,
     pub connection: String,
+    pub connection_password_file: String,
     pub private_key: String,
     pub scp_extra_args: String,
     pub sftp_extra_args: String,
@@ -36,6 +37,7 @@ impl Default for AnsibleConnectionOptions {
         AnsibleConnectionOptions {
             ask_pass: false,
             connection: String::new(),
+            connection_password_file: String::new(),
             private_key: String::new(),
             scp_extra_args: String::new(),
             sftp_extra_args: String::new(),
@@ -50,6 +52,7 @@ impl Default for AnsibleConnectionOptions {
 impl AnsibleConnectionOptions {
     const ASK_PASS_FLAG: &str = "--ask-pass";
     const CONNECTION_FLAG: &str = "--connection";
+    const CONNECTION_PASSWORD_FILE_FLAG: &str = "--connection-password-file";
     const PRIVATE_KEY_FLAG: &str = "--private-key";
     const SCP_EXTRA_ARGS_FLAG: &str = "--scp-extra-args";
     const SFTP_EXTRA_ARGS_FLAG: &str = "--sftp-extra-args";
@@ -72,6 +75,11 @@ impl AnsibleConnectionOptions {
             cmd.push(self.connection.clone());
         }
 
+        if !self.connection_password_file.is_empty() {
+            cmd.push(Self::CONNECTION_PASSWORD_FILE_FLAG.to_string());
+            cmd.push(self.connection_password_file.clone());
+        }
+
         if !self.private_key.is_empty() {
             cmd.push(Self::PRIVATE_KEY_FLAG.to_string());
             cmd.push(self.private_key.clone());
@@ -142,6 +150,7 @@ pub struct AnsiblePrivilegeEscalationOptions {
     pub ask_become_pass: bool,
     pub do_become: bool,
     pub become_method: String,
+    pub become_password_file: String,
     pub become_user: String,
 }
 
@@ -149,6 +158,7 @@ impl AnsiblePrivilegeEscalationOptions {
     const ASK_BECOME_PASS_FLAG: &str = "--ask-become-pass";
     const BECOME_FLAG: &str = "--become";
     const BECOME_METHOD_FLAG: &str = "--become-method";
+    const BECOME_PASSWORD_FILE_FLAG: &str = "--become-password-file";
     const BECOME_USER_FLAG: &str = "--become-user";
 
     /// returns a list of privilege escalation options flags to be used on
@@ -169,6 +179,11 @@ impl AnsiblePrivilegeEscalationOptions {
             cmd.push(self.become_method.clone());
         }
 
+        if !self.become_password_file.is_empty() {
+            cmd.push(Self::BECOME_PASSWORD_FILE_FLAG.to_string());
+            cmd.push(self.become_password_file.clone());
+        }
+
         if !self.become_user.is_empty() {
             cmd.push(Self::BECOME_USER_FLAG.to_string());
             cmd.push(self.become_user.clone());
@@ -1,15 +1,25 @@
-use crate::executor::{verify_binary, DefaultExecutor};
+use crate::executor::{
+    create_temp_dir, verify_binary, write_temp_file, AnsibleChild, DefaultExecutor,
+};
+use crate::inventory::{Inventory, InventoryCheck, InventoryFormat, InventorySource};
 use crate::options::{AnsibleConnectionOptions, AnsiblePrivilegeEscalationOptions};
+use crate::source::PlaybookSource;
+use crate::version::{
+    cache_version, cached_version, parse_version, AnsibleVersion, AnsibleVersionCheck,
+};
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::error::Error;
-use std::process::Child;
+use std::fs;
+use std::path::PathBuf;
+use which::which;
 
 /// Parameters described on `Options` section within
 /// ansible-playbook's man page, and which defines which should be
 /// the ansible-playbook execution behavior.
 #[derive(Debug, Clone)]
 pub struct AnsiblePlaybookOptions {
-    pub ask_vault_password: bool,      // ask for vault password
+    pub ask_vault_password: bool,        // ask for vault password
     pub check: bool, // don't make any changes; instead, try to predict some of the changes that may occur
     pub diff: bool, // when changing (small) files and templates, show the differences in those files; works great with --check
     pub extra_vars: serde_json::Value, // is a map of extra variables used on ansible-playbook execution
@@ -17,7 +27,7 @@ pub struct AnsiblePlaybookOptions {
     pub flush_cache: bool,             // is the flush cache flag for ansible-playbook
     pub force_handlers: bool,          // run handlers even if a task fails
     pub forks: String,                 // specify number of parallel processes to use (default=50)
-    pub inventory: String,             // specify inventory host path
+    pub inventory: Vec<InventorySource>, // inventory sources, merged by ansible in order
     pub limit: String,                 // is selected hosts additional pattern
     pub list_hosts: bool,              // outputs a list of matching hosts
     pub list_tags: bool,               // is the list tags flag for ansible-playbook
@@ -49,7 +59,7 @@ impl Default for AnsiblePlaybookOptions {
             flush_cache: false,
             force_handlers: false,
             forks: String::new(),
-            inventory: String::new(),
+            inventory: vec![],
             limit: String::new(),
             list_hosts: false,
             list_tags: false,
@@ -162,9 +172,18 @@ impl AnsiblePlaybookOptions {
             cmd.push(self.forks.clone());
         }
 
-        if !self.inventory.is_empty() {
-            cmd.push(Self::INVENTORY_FLAG.to_string());
-            cmd.push(self.inventory.clone());
+        // in-memory and dynamic sources have no argument until they are
+        // written out, which `AnsiblePlaybookCmd::run` does
+        for source in &self.inventory {
+            match source.argument() {
+                Some(inventory) => {
+                    cmd.push(Self::INVENTORY_FLAG.to_string());
+                    cmd.push(inventory);
+                }
+                None => {
+                    return Err("(playbook::gen_opts) in-memory and dynamic inventories are only written out when the command runs".into())
+                }
+            }
         }
 
         if !self.limit.is_empty() {
@@ -233,6 +252,13 @@ impl AnsiblePlaybookOptions {
 
         Ok(cmd)
     }
+
+    /// Returns the hosts of `inventory` selected by `limit`, every host when
+    /// it is empty. As for ansible, a trailing `[..]` on a limit term is a
+    /// subscript, so `web[0:1]` selects the first two hosts of `web`.
+    pub fn limit_hosts(&self, inventory: &Inventory) -> Result<Vec<String>, Box<dyn Error>> {
+        inventory.resolve_limit(&self.limit)
+    }
 }
 
 /// Ansible-playbook command representation and how to execute it
@@ -240,10 +266,15 @@ impl AnsiblePlaybookOptions {
 pub struct AnsiblePlaybookCmd {
     pub binary: String,                               // Ansible binary
     pub executor: DefaultExecutor,                    // Ansible binary
-    pub playbooks: Vec<String>,                       // playbooks list to be run
+    pub playbooks: Vec<PlaybookSource>,               // playbooks list to be run
     pub options: AnsiblePlaybookOptions,              // playbook options
     pub connection_options: AnsibleConnectionOptions, // specific options for connection
     pub privilege_escalation_options: AnsiblePrivilegeEscalationOptions, // playbook's privilege escalation options
+    pub env: BTreeMap<String, String>, // extra environment variables set for the execution
+    pub version_check: AnsibleVersionCheck, // how to handle options unsupported by the installed version
+    pub inventory_format: InventoryFormat,  // format the in-memory inventory sources are written in
+    pub inventory_check: InventoryCheck, // how to handle the issues found in the in-memory inventory sources
+    pub keep_on_failure: bool, // keep the temporary directory of the in-memory playbooks when the run fails, for debugging, its path being given in the warnings of the execution
 }
 
 const DEFAULT_ANSIBLE_PLAYBOOK_BINARY: &str = "ansible-playbook";
@@ -262,26 +293,221 @@ impl Default for AnsiblePlaybookCmd {
             privilege_escalation_options: AnsiblePrivilegeEscalationOptions {
                 ..Default::default()
             },
+            env: BTreeMap::new(),
+            version_check: AnsibleVersionCheck::Skip,
+            inventory_format: InventoryFormat::Yaml,
+            inventory_check: InventoryCheck::Skip,
+            keep_on_failure: false,
         }
     }
 }
 
 impl AnsiblePlaybookCmd {
     /// run playbooks
-    pub fn run(&self) -> Result<Child, Box<dyn Error + '_>> {
+    pub fn run(&self) -> Result<AnsibleChild, Box<dyn Error + '_>> {
         if let Err(err) = verify_binary(&self.binary) {
             return Err(format!("(playbook::run) {}", err).into());
         }
 
-        match self.command() {
-            Ok(command) => {
-                return Ok(self
-                    .executor
-                    .run(command)
-                    .expect("(executor::run) Run playbook"))
+        let mut warnings = vec![];
+        if self.inventory_check != InventoryCheck::Skip {
+            for source in &self.options.inventory {
+                let inventory = match source {
+                    InventorySource::Model(inventory) => inventory,
+                    _ => continue,
+                };
+                let issues = inventory.validate();
+                let errors: Vec<String> = issues
+                    .iter()
+                    .filter(|issue| issue.is_error())
+                    .map(|issue| issue.to_string())
+                    .collect();
+                if self.inventory_check == InventoryCheck::Reject && !errors.is_empty() {
+                    return Err(format!(
+                        "(playbook::run) invalid inventory: {}",
+                        errors.join("; ")
+                    )
+                    .into());
+                }
+                warnings.extend(issues.iter().map(|issue| issue.to_string()));
+            }
+        }
+
+        let (playbooks, playbook_dir) = match self.materialize_playbooks() {
+            Ok(materialized) => materialized,
+            Err(err) => return Err(format!("(playbook::run) {}", err).into()),
+        };
+
+        // in-memory sources are written to temporary files and dynamic ones
+        // get a shim, which is removed, and its socket closed, once dropped
+        let mut temp_files = vec![];
+        let mut shims = vec![];
+        let mut sources = vec![];
+        for source in &self.options.inventory {
+            let written = match source {
+                InventorySource::Model(inventory) => inventory
+                    .render(&self.inventory_format)
+                    .and_then(|content| {
+                        write_temp_file("inventory", self.inventory_format.extension(), &content)
+                    })
+                    .inspect(|path| temp_files.push(path.clone())),
+                InventorySource::Dynamic(inventory) => inventory.materialize().map(|shim| {
+                    let path = shim.path.clone();
+                    shims.push(shim);
+                    path
+                }),
+                source => {
+                    sources.push(source.clone());
+                    continue;
+                }
+            };
+
+            match written {
+                Ok(path) => sources.push(InventorySource::Path(path.to_string_lossy().to_string())),
+                Err(err) => {
+                    remove_temp(temp_files, playbook_dir);
+                    return Err(format!("(playbook::run) {}", err).into());
+                }
+            }
+        }
+
+        let mut materialized = self.clone();
+        materialized.playbooks = playbooks;
+        materialized.options.inventory = sources;
+        let command = match materialized.checked_command(&mut warnings) {
+            Ok(command) => command,
+            Err(err) => {
+                remove_temp(temp_files, playbook_dir);
+                return Err(format!("(playbook::run) {}", err).into());
+            }
+        };
+
+        let child = match self.executor.run_with_env(command, &self.env) {
+            Ok(child) => child,
+            Err(err) => {
+                remove_temp(temp_files, playbook_dir);
+                return Err(format!("(playbook::run) {}", err).into());
+            }
+        };
+
+        let keep_on_failure = self.keep_on_failure;
+        if let Some(directory) = playbook_dir.as_ref().filter(|_| keep_on_failure) {
+            warnings.push(format!(
+                "The in-memory playbooks are written in {}, which is kept if the run fails",
+                directory.display()
+            ));
+        }
+        let mut child = AnsibleChild::new(child);
+        child.warnings = warnings;
+        child.on_exit(move |status| {
+            drop(shims);
+            let failed = !status.is_some_and(|status| status.success());
+            match playbook_dir {
+                Some(_) if keep_on_failure && failed => remove_temp(temp_files, None),
+                playbook_dir => remove_temp(temp_files, playbook_dir),
+            }
+        });
+
+        Ok(child)
+    }
+
+    // command line of a materialized command, checked against the installed
+    // ansible version as `version_check` asks
+    fn checked_command(&self, warnings: &mut Vec<String>) -> Result<Vec<String>, Box<dyn Error>> {
+        let command = self.command()?;
+        if self.version_check == AnsibleVersionCheck::Skip {
+            return Ok(command);
+        }
+
+        let unsupported = self.detect_version()?.unsupported_flags(&command);
+        if !unsupported.is_empty() {
+            let message = format!(
+                "options not supported by the installed ansible: {}",
+                unsupported.join(", ")
+            );
+            if self.version_check == AnsibleVersionCheck::Reject {
+                return Err(message.into());
+            }
+            warnings.push(message);
+        }
+
+        Ok(command)
+    }
+
+    // write the in-memory playbooks to a private temporary directory, each of
+    // them in its own subdirectory, returning the paths of all the playbooks
+    fn materialize_playbooks(
+        &self,
+    ) -> Result<(Vec<PlaybookSource>, Option<PathBuf>), Box<dyn Error>> {
+        if self
+            .playbooks
+            .iter()
+            .all(|playbook| playbook.path().is_some())
+        {
+            return Ok((self.playbooks.clone(), None));
+        }
+
+        let directory = create_temp_dir("playbook")?;
+        let mut playbooks = vec![];
+        for (index, playbook) in self.playbooks.iter().enumerate() {
+            match playbook.materialize(&directory.join(index.to_string())) {
+                Ok(path) => {
+                    playbooks.push(PlaybookSource::Path(path.to_string_lossy().to_string()))
+                }
+                Err(err) => {
+                    let _ = fs::remove_dir_all(&directory);
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok((playbooks, Some(directory)))
+    }
+
+    /// Detect the installed ansible version from `ansible-playbook --version`.
+    /// Versions are cached per binary path, so the binary only runs once.
+    pub fn detect_version(&self) -> Result<AnsibleVersion, Box<dyn Error>> {
+        if let Err(err) = verify_binary(&self.binary) {
+            return Err(format!("(playbook::detect_version) {}", err).into());
+        }
+
+        let path = match which(&self.binary) {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(err) => return Err(format!("(playbook::detect_version) {}", err).into()),
+        };
+        if let Some(version) = cached_version(&path) {
+            return Ok(version);
+        }
+
+        let mut cmd = vec![self.binary.clone()];
+        cmd.append(
+            &mut AnsiblePlaybookOptions {
+                version: true,
+                ..Default::default()
             }
-            Err(err) => Err(err),
+            .gen_opts()?,
+        );
+
+        let output = self.executor.output_with_env(cmd, &self.env)?;
+        if !output.status.success() {
+            return Err(format!(
+                "(playbook::detect_version) {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
         }
+
+        let version = parse_version(&String::from_utf8_lossy(&output.stdout))?;
+        cache_version(&path, &version);
+
+        Ok(version)
+    }
+
+    /// Returns the flags of the command line the installed ansible version
+    /// does not support
+    pub fn unsupported_flags(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let version = self.detect_version()?;
+        Ok(version.unsupported_flags(&self.command()?))
     }
 
     /// generate command line
@@ -290,12 +516,7 @@ impl AnsiblePlaybookCmd {
 
         cmd.push(self.binary.clone());
 
-        cmd.append(
-            &mut self
-                .options
-                .gen_opts()
-                .expect("(playbook::command) Generate command options"),
-        );
+        cmd.append(&mut self.options.gen_opts()?);
         cmd.append(
             &mut self
                 .connection_options
@@ -308,15 +529,31 @@ impl AnsiblePlaybookCmd {
                 .gen_cmd_privesc_opts()
                 .expect("(playbook::command) Generate privilige escalation options"),
         );
-        cmd.append(&mut self.playbooks.clone());
+        // in-memory playbooks have no path until they are written out, which
+        // `run` does
+        for playbook in &self.playbooks {
+            match playbook.path() {
+                Some(path) => cmd.push(path),
+                None => {
+                    return Err("(playbook::command) in-memory playbooks are only written out when the command runs".into())
+                }
+            }
+        }
 
         return Ok(cmd);
     }
 
     pub fn to_string(&self) -> Result<String, Box<dyn Error>> {
-        return Ok(self
-            .command()
-            .expect("(playbook::to_string) generate options")
-            .join(" "));
+        return Ok(self.command()?.join(" "));
+    }
+}
+
+// remove the temporary files and directory written for a run
+fn remove_temp(files: Vec<PathBuf>, directory: Option<PathBuf>) {
+    for file in files {
+        let _ = fs::remove_file(file);
+    }
+    if let Some(directory) = directory {
+        let _ = fs::remove_dir_all(directory);
     }
 }
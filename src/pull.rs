@@ -0,0 +1,188 @@
+use crate::executor::{verify_binary, DefaultExecutor};
+use crate::options::AnsibleConnectionOptions;
+use crate::playbook::AnsiblePlaybookOptions;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::process::Child;
+
+/// Parameters described on ansible-pull's man page which are specific to
+/// pull mode, and which define where and how the repository is checked out.
+#[derive(Debug, Default, Clone)]
+pub struct AnsiblePullOptions {
+    pub accept_host_key: bool, // adds the hostkey for the repo url if not already added
+    pub checkout: String, // branch, tag or commit to checkout, defaults to the repository module default
+    pub clean: bool,      // modified files in the working repository will be discarded
+    pub directory: String, // path to the directory to which ansible-pull will checkout the repository
+    pub only_if_changed: bool, // only run the playbook if the repository has been updated
+    pub purge: bool,       // purge checkout after playbook run
+    pub sleep: String, // sleep for random interval (between 0 and n number of seconds) before starting
+    pub url: String,   // url of the playbook repository
+}
+
+impl AnsiblePullOptions {
+    const ACCEPT_HOST_KEY_FLAG: &str = "--accept-host-key";
+    const CHECKOUT_FLAG: &str = "--checkout";
+    const CLEAN_FLAG: &str = "--clean";
+    const DIRECTORY_FLAG: &str = "--directory";
+    const ONLY_IF_CHANGED_FLAG: &str = "--only-if-changed";
+    const PURGE_FLAG: &str = "--purge";
+    const SLEEP_FLAG: &str = "--sleep";
+    const URL_FLAG: &str = "--url";
+
+    /// Returns a list of options flags to be used on ansible-pull execution
+    pub fn gen_opts(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut cmd: Vec<String> = Vec::new();
+
+        if self.url.is_empty() {
+            return Err("(pull::gen_opts) url is required".into());
+        }
+
+        if self.accept_host_key {
+            cmd.push(Self::ACCEPT_HOST_KEY_FLAG.to_string());
+        }
+
+        if !self.checkout.is_empty() {
+            cmd.push(Self::CHECKOUT_FLAG.to_string());
+            cmd.push(self.checkout.clone());
+        }
+
+        if self.clean {
+            cmd.push(Self::CLEAN_FLAG.to_string());
+        }
+
+        if !self.directory.is_empty() {
+            cmd.push(Self::DIRECTORY_FLAG.to_string());
+            cmd.push(self.directory.clone());
+        }
+
+        if self.only_if_changed {
+            cmd.push(Self::ONLY_IF_CHANGED_FLAG.to_string());
+        }
+
+        if self.purge {
+            cmd.push(Self::PURGE_FLAG.to_string());
+        }
+
+        if !self.sleep.is_empty() {
+            cmd.push(Self::SLEEP_FLAG.to_string());
+            cmd.push(self.sleep.clone());
+        }
+
+        cmd.push(Self::URL_FLAG.to_string());
+        cmd.push(self.url.clone());
+
+        Ok(cmd)
+    }
+}
+
+/// Returns the ansible-playbook options set on `options` which ansible-pull
+/// does not accept
+fn unsupported_playbook_opts(options: &AnsiblePlaybookOptions) -> Vec<&str> {
+    let mut unsupported = vec![];
+
+    if options.flush_cache {
+        unsupported.push("flush_cache");
+    }
+    if options.force_handlers {
+        unsupported.push("force_handlers");
+    }
+    if !options.forks.is_empty() {
+        unsupported.push("forks");
+    }
+    // only sources given on the command line reach the pulled playbook
+    if options
+        .inventory
+        .iter()
+        .any(|source| source.argument().is_none())
+    {
+        unsupported.push("inventory");
+    }
+    if options.list_tags {
+        unsupported.push("list_tags");
+    }
+    if options.list_tasks {
+        unsupported.push("list_tasks");
+    }
+    if !options.start_at_task.is_empty() {
+        unsupported.push("start_at_task");
+    }
+    if options.step {
+        unsupported.push("step");
+    }
+    if options.syntax_check {
+        unsupported.push("syntax_check");
+    }
+
+    unsupported
+}
+
+/// Ansible-pull command representation and how to execute it
+#[derive(Debug, Clone)]
+pub struct AnsiblePullCmd {
+    pub binary: String,                               // Ansible pull binary
+    pub executor: DefaultExecutor,                    // Executor used to run the command
+    pub playbooks: Vec<String>, // playbooks, relative to the checkout, to be run
+    pub pull_options: AnsiblePullOptions, // options specific to pull mode
+    pub options: AnsiblePlaybookOptions, // options shared with ansible-playbook
+    pub connection_options: AnsibleConnectionOptions, // specific options for connection
+    pub env: BTreeMap<String, String>, // extra environment variables set for the execution
+}
+
+const DEFAULT_ANSIBLE_PULL_BINARY: &str = "ansible-pull";
+impl Default for AnsiblePullCmd {
+    fn default() -> Self {
+        AnsiblePullCmd {
+            binary: DEFAULT_ANSIBLE_PULL_BINARY.into(),
+            executor: DefaultExecutor {},
+            playbooks: vec![],
+            pull_options: AnsiblePullOptions {
+                ..Default::default()
+            },
+            options: AnsiblePlaybookOptions {
+                ..Default::default()
+            },
+            connection_options: AnsibleConnectionOptions {
+                ..Default::default()
+            },
+            env: BTreeMap::new(),
+        }
+    }
+}
+
+impl AnsiblePullCmd {
+    /// run ansible-pull
+    pub fn run(&self) -> Result<Child, Box<dyn Error>> {
+        if let Err(err) = verify_binary(&self.binary) {
+            return Err(format!("(pull::run) {}", err).into());
+        }
+
+        let command = self.command()?;
+        self.executor.run_with_env(command, &self.env)
+    }
+
+    /// generate command line
+    pub fn command(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let unsupported = unsupported_playbook_opts(&self.options);
+        if !unsupported.is_empty() {
+            return Err(format!(
+                "(pull::command) options not supported by ansible-pull: {}",
+                unsupported.join(", ")
+            )
+            .into());
+        }
+
+        let mut cmd = vec![];
+
+        cmd.push(self.binary.clone());
+        cmd.append(&mut self.pull_options.gen_opts()?);
+        cmd.append(&mut self.options.gen_opts()?);
+        cmd.append(&mut self.connection_options.gen_conn_opts()?);
+        cmd.append(&mut self.playbooks.clone());
+
+        Ok(cmd)
+    }
+
+    pub fn to_string(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.command()?.join(" "))
+    }
+}
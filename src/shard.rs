@@ -0,0 +1,270 @@
+use crate::inventory::{Inventory, InventoryShardOptions};
+use crate::playbook::AnsiblePlaybookCmd;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Counters of a host on the `PLAY RECAP` of an ansible-playbook run
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlaybookHostRecap {
+    pub ok: u32,          // tasks which ran without changes
+    pub changed: u32,     // tasks which changed the host
+    pub unreachable: u32, // tasks which could not reach the host
+    pub failed: u32,      // failed tasks
+    pub skipped: u32,     // skipped tasks
+    pub rescued: u32,     // failed tasks a rescue section recovered from
+    pub ignored: u32,     // failed tasks whose errors were ignored
+}
+
+impl PlaybookHostRecap {
+    /// Returns whether the run failed, or could not reach, the host
+    pub fn is_failed(&self) -> bool {
+        self.failed > 0 || self.unreachable > 0
+    }
+
+    fn add(&mut self, other: &PlaybookHostRecap) {
+        self.ok += other.ok;
+        self.changed += other.changed;
+        self.unreachable += other.unreachable;
+        self.failed += other.failed;
+        self.skipped += other.skipped;
+        self.rescued += other.rescued;
+        self.ignored += other.ignored;
+    }
+}
+
+impl fmt::Display for PlaybookHostRecap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ok={} changed={} unreachable={} failed={} skipped={} rescued={} ignored={}",
+            self.ok,
+            self.changed,
+            self.unreachable,
+            self.failed,
+            self.skipped,
+            self.rescued,
+            self.ignored
+        )
+    }
+}
+
+/// Parse the `PLAY RECAP` section of an ansible-playbook output, colored or not
+pub fn parse_play_recap(output: &str) -> BTreeMap<String, PlaybookHostRecap> {
+    let colors = Regex::new(r"\x1b\[[0-9;]*m").expect("(shard::parse_play_recap) colors regex");
+    let line_re = Regex::new(r"^(\S+)\s+:\s+((?:\w+=\d+\s*)+)$")
+        .expect("(shard::parse_play_recap) recap regex");
+
+    let mut recaps: BTreeMap<String, PlaybookHostRecap> = BTreeMap::new();
+    let mut in_recap = false;
+    for line in output.lines() {
+        let line = colors.replace_all(line, "");
+        let line = line.trim();
+        if line.starts_with("PLAY RECAP") {
+            in_recap = true;
+            continue;
+        }
+        if !in_recap {
+            continue;
+        }
+
+        let captures = match line_re.captures(line) {
+            Some(captures) => captures,
+            None => continue,
+        };
+        let mut recap = PlaybookHostRecap::default();
+        for counter in captures[2].split_whitespace() {
+            let (name, value) = counter.split_once('=').unwrap_or_default();
+            let value = value.parse().unwrap_or_default();
+            match name {
+                "ok" => recap.ok = value,
+                "changed" => recap.changed = value,
+                "unreachable" => recap.unreachable = value,
+                "failed" => recap.failed = value,
+                "skipped" => recap.skipped = value,
+                "rescued" => recap.rescued = value,
+                "ignored" => recap.ignored = value,
+                _ => {}
+            }
+        }
+        recaps
+            .entry(captures[1].to_string())
+            .or_default()
+            .add(&recap);
+    }
+
+    recaps
+}
+
+/// Outcome of the run of a shard
+#[derive(Debug, Clone, Default)]
+pub struct ShardResult {
+    pub limit: String,                              // `--limit` of the shard
+    pub status: Option<ExitStatus>, // exit status, `None` when the command could not run
+    pub stdout: String,             // standard output of the run
+    pub stderr: String,             // standard error of the run
+    pub error: Option<String>,      // why the command could not run
+    pub recap: BTreeMap<String, PlaybookHostRecap>, // `PLAY RECAP` of the run
+}
+
+impl ShardResult {
+    /// Returns whether the shard ran successfully
+    pub fn success(&self) -> bool {
+        self.error.is_none() && self.status.is_some_and(|status| status.success())
+    }
+}
+
+/// Merged outcome of the runs of every shard
+#[derive(Debug, Clone, Default)]
+pub struct ShardSummary {
+    pub shards: Vec<ShardResult>, // result of each shard, in the order of the commands
+    pub hosts: BTreeMap<String, PlaybookHostRecap>, // merged `PLAY RECAP` of every shard
+}
+
+impl ShardSummary {
+    /// Returns whether every shard ran successfully
+    pub fn success(&self) -> bool {
+        self.shards.iter().all(|shard| shard.success())
+    }
+
+    /// Returns the hosts the runs failed on or could not reach
+    pub fn failed_hosts(&self) -> Vec<String> {
+        self.hosts
+            .iter()
+            .filter(|(_, recap)| recap.is_failed())
+            .map(|(host, _)| host.clone())
+            .collect()
+    }
+}
+
+impl fmt::Display for ShardSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, shard) in self.shards.iter().enumerate() {
+            let outcome = match (&shard.error, shard.status) {
+                (Some(error), _) => format!("error: {}", error),
+                (None, Some(status)) => match status.code() {
+                    Some(code) => format!("exit code {}", code),
+                    None => "killed".to_string(),
+                },
+                (None, None) => "not run".to_string(),
+            };
+            writeln!(
+                f,
+                "shard {}: {} hosts, {}",
+                index,
+                shard.limit.split(',').count(),
+                outcome
+            )?;
+        }
+
+        writeln!(f, "PLAY RECAP")?;
+        let width = self.hosts.keys().map(|host| host.len()).max().unwrap_or(0);
+        for (host, recap) in &self.hosts {
+            writeln!(f, "{:width$} : {}", host, recap, width = width)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl AnsiblePlaybookCmd {
+    /// Split the hosts of `inventory` the command targets into shards, and
+    /// returns a copy of the command per shard limited to its hosts. Empty
+    /// shards are left out, as an empty limit would select every host.
+    pub fn shard(
+        &self,
+        inventory: &Inventory,
+        options: &InventoryShardOptions,
+    ) -> Result<Vec<AnsiblePlaybookCmd>, Box<dyn Error>> {
+        if options.shards == 0 {
+            return Err("(shard::shard) the number of shards should be positive".into());
+        }
+
+        let (hosts, limited) = match (
+            inventory.resolve_limit(&options.pattern),
+            inventory.resolve_limit(&self.options.limit),
+        ) {
+            (Ok(hosts), Ok(limited)) => (hosts, limited),
+            (Err(err), _) | (_, Err(err)) => return Err(format!("(shard::shard) {}", err).into()),
+        };
+        let limited: BTreeSet<String> = limited.into_iter().collect();
+        let hosts: Vec<String> = hosts
+            .into_iter()
+            .filter(|host| limited.contains(host))
+            .collect();
+
+        Ok(inventory
+            .shard_hosts(&hosts, options)
+            .into_iter()
+            .filter(|shard| !shard.is_empty())
+            .map(|shard| {
+                let mut cmd = self.clone();
+                cmd.options.limit = shard.join(",");
+                cmd
+            })
+            .collect())
+    }
+}
+
+fn run_shard(cmd: &AnsiblePlaybookCmd) -> ShardResult {
+    let mut result = ShardResult {
+        limit: cmd.options.limit.clone(),
+        ..Default::default()
+    };
+
+    let output = match cmd.run() {
+        Ok(child) => child.wait_with_output(),
+        Err(err) => {
+            result.error = Some(err.to_string());
+            return result;
+        }
+    };
+    match output {
+        Ok(output) => {
+            result.status = Some(output.status);
+            result.stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            result.stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            result.recap = parse_play_recap(&result.stdout);
+        }
+        Err(err) => result.error = Some(err.to_string()),
+    }
+
+    result
+}
+
+/// Run the commands in parallel, `workers` of them at most at a time, and
+/// merge their results
+pub fn run_shards(commands: &[AnsiblePlaybookCmd], workers: usize) -> ShardSummary {
+    let results: Mutex<Vec<Option<ShardResult>>> = Mutex::new(vec![None; commands.len()]);
+    let next = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, commands.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let cmd = match commands.get(index) {
+                    Some(cmd) => cmd,
+                    None => break,
+                };
+                let result = run_shard(cmd);
+                results.lock().expect("(shard::run_shards) results")[index] = Some(result);
+            });
+        }
+    });
+
+    let mut summary = ShardSummary::default();
+    for result in results.into_inner().expect("(shard::run_shards) results") {
+        let result = result.unwrap_or_default();
+        for (host, recap) in &result.recap {
+            summary.hosts.entry(host.clone()).or_default().add(recap);
+        }
+        summary.shards.push(result);
+    }
+
+    summary
+}
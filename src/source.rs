@@ -0,0 +1,109 @@
+use crate::executor::{create_private_dirs, write_private_file};
+use crate::model::Playbook;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Component, Path, PathBuf};
+
+// name of the playbook file written for YAML and typed playbooks
+const PLAYBOOK_FILE: &str = "playbook.yml";
+
+/// Playbook run by `AnsiblePlaybookCmd`. Sources other than paths are
+/// written to a private temporary directory when the command runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaybookSource {
+    Path(String),         // playbook file
+    Yaml(String),         // content of a playbook
+    Model(Playbook),      // typed playbook
+    Files(PlaybookFiles), // playbook written along with the files it uses
+}
+
+/// Playbook along with the files it uses, such as roles, templates and vars
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaybookFiles {
+    pub files: BTreeMap<String, Vec<u8>>, // content of the files, by path relative to the playbook directory
+    pub playbook: String,                 // path of the playbook to run, one of `files`
+}
+
+impl From<&str> for PlaybookSource {
+    fn from(path: &str) -> Self {
+        PlaybookSource::Path(path.to_string())
+    }
+}
+
+impl From<String> for PlaybookSource {
+    fn from(path: String) -> Self {
+        PlaybookSource::Path(path)
+    }
+}
+
+impl From<Playbook> for PlaybookSource {
+    fn from(playbook: Playbook) -> Self {
+        PlaybookSource::Model(playbook)
+    }
+}
+
+impl From<PlaybookFiles> for PlaybookSource {
+    fn from(files: PlaybookFiles) -> Self {
+        PlaybookSource::Files(files)
+    }
+}
+
+// `name` as a path within the playbook directory, which it cannot escape
+fn relative_path(name: &str) -> Result<&Path, Box<dyn Error>> {
+    let path = Path::new(name);
+    let relative = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !relative || name.is_empty() {
+        return Err(format!("'{}' is not a path within the playbook directory", name).into());
+    }
+
+    Ok(path)
+}
+
+fn write_file(directory: &Path, name: &str, content: &[u8]) -> Result<PathBuf, Box<dyn Error>> {
+    let path = directory.join(relative_path(name)?);
+    if let Some(parent) = path.parent() {
+        create_private_dirs(parent)?;
+    }
+    write_private_file(&path, content)?;
+
+    Ok(path)
+}
+
+impl PlaybookSource {
+    /// Returns the path of a playbook file, `None` for the other sources
+    /// which are only written out when the command runs
+    pub fn path(&self) -> Option<String> {
+        match self {
+            PlaybookSource::Path(path) => Some(path.clone()),
+            _ => None,
+        }
+    }
+
+    // write the playbook within `directory`, returning the path of the
+    // playbook to run
+    pub(crate) fn materialize(&self, directory: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        match self {
+            PlaybookSource::Path(path) => Ok(PathBuf::from(path)),
+            PlaybookSource::Yaml(content) => {
+                write_file(directory, PLAYBOOK_FILE, content.as_bytes())
+            }
+            PlaybookSource::Model(playbook) => {
+                write_file(directory, PLAYBOOK_FILE, playbook.to_yaml()?.as_bytes())
+            }
+            PlaybookSource::Files(files) => {
+                if !files.files.contains_key(&files.playbook) {
+                    return Err(
+                        format!("playbook '{}' is not one of the files", files.playbook).into(),
+                    );
+                }
+                for (name, content) in &files.files {
+                    write_file(directory, name, content)?;
+                }
+
+                Ok(directory.join(relative_path(&files.playbook)?))
+            }
+        }
+    }
+}
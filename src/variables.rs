@@ -0,0 +1,302 @@
+use crate::inventory::{
+    is_vault_encrypted, yaml_to_json, Inventory, InventoryVars, InventoryVarsTree, ALL_GROUP,
+};
+use crate::playbook::AnsiblePlaybookOptions;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+
+/// Layers of ansible's variable precedence taken into account offline, from
+/// the lowest to the highest precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VariableLayer {
+    InventoryGroupVars,       // group vars set by the inventory file or script
+    InventoryGroupVarsAll,    // inventory `group_vars/all`
+    PlaybookGroupVarsAll,     // playbook `group_vars/all`
+    InventoryGroupVarsGroups, // inventory `group_vars/*`
+    PlaybookGroupVarsGroups,  // playbook `group_vars/*`
+    InventoryHostVars,        // host vars set by the inventory file or script
+    InventoryHostVarsHosts,   // inventory `host_vars/*`
+    PlaybookHostVarsHosts,    // playbook `host_vars/*`
+    PlayVars,                 // `vars` of the play
+    PlayVarsFiles,            // `vars_files` of the play
+    ExtraVars,                // `--extra-vars`, which always win
+}
+
+impl fmt::Display for VariableLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VariableLayer::InventoryGroupVars => "inventory file or script group vars",
+            VariableLayer::InventoryGroupVarsAll => "inventory group_vars/all",
+            VariableLayer::PlaybookGroupVarsAll => "playbook group_vars/all",
+            VariableLayer::InventoryGroupVarsGroups => "inventory group_vars/*",
+            VariableLayer::PlaybookGroupVarsGroups => "playbook group_vars/*",
+            VariableLayer::InventoryHostVars => "inventory file or script host vars",
+            VariableLayer::InventoryHostVarsHosts => "inventory host_vars/*",
+            VariableLayer::PlaybookHostVarsHosts => "playbook host_vars/*",
+            VariableLayer::PlayVars => "play vars",
+            VariableLayer::PlayVarsFiles => "play vars_files",
+            VariableLayer::ExtraVars => "extra vars",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Value a variable is given by a layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableDefinition {
+    pub layer: VariableLayer, // precedence layer of the definition
+    pub source: String,       // group, host or file defining the variable
+    pub value: Value,         // value given to the variable
+}
+
+/// Effective value of a variable for a host, along with the definitions it
+/// shadows
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedVariable {
+    pub name: String,                      // name of the variable
+    pub value: Value,                      // effective value
+    pub winner: VariableDefinition,        // definition the value comes from
+    pub shadowed: Vec<VariableDefinition>, // overridden definitions, from the highest precedence
+}
+
+impl fmt::Display for ResolvedVariable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} = {} ({}: {})",
+            self.name, self.value, self.winner.layer, self.winner.source
+        )?;
+        for definition in &self.shadowed {
+            write!(
+                f,
+                "\n  shadows {} ({}: {})",
+                definition.value, definition.layer, definition.source
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Offline resolver of the effective value of variables for a host. The
+/// inventory must not have its `group_vars/` and `host_vars/` applied yet,
+/// as they are distinct layers.
+#[derive(Debug, Clone, Default)]
+pub struct VariableResolver {
+    pub inventory: Inventory, // inventory, with the vars set by the inventory file only
+    pub inventory_vars: InventoryVarsTree, // `group_vars/` and `host_vars/` next to the inventory
+    pub playbook_vars: InventoryVarsTree, // `group_vars/` and `host_vars/` next to the playbook
+    pub play_vars: InventoryVars, // `vars` of the play
+    pub play_vars_files: Vec<String>, // `vars_files` of the play, in order
+    pub extra_vars: Value,    // extra variables, as on `AnsiblePlaybookOptions`
+    pub extra_vars_file: Vec<String>, // extra variables files or values, as on `AnsiblePlaybookOptions`
+}
+
+// layer, source and the variables it defines
+type LayerVars = (VariableLayer, String, InventoryVars);
+
+// variables of a YAML or JSON file holding a dictionary
+fn read_vars(path: &str) -> Result<InventoryVars, Box<dyn Error>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => return Err(format!("{}: {}", path, err).into()),
+    };
+    parse_vars(&content).map_err(|err| format!("{}: {}", path, err).into())
+}
+
+fn parse_vars(content: &str) -> Result<InventoryVars, String> {
+    if is_vault_encrypted(content) {
+        return Err("vault encrypted variables can't be resolved".into());
+    }
+
+    match serde_yaml::from_str(content).map(|document| yaml_to_json(&document)) {
+        Ok(Value::Object(vars)) => Ok(vars.into_iter().collect()),
+        Ok(Value::Null) => Ok(InventoryVars::new()),
+        Ok(_) => Err("variables should be a dictionary".into()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+// `key=value` pairs given to `--extra-vars`
+fn parse_key_values(value: &str) -> InventoryVars {
+    value
+        .split_whitespace()
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_string(), json!(value)))
+        .collect()
+}
+
+impl VariableResolver {
+    /// Resolver whose extra variables are the ones of `options`
+    pub fn from_options(options: &AnsiblePlaybookOptions) -> Self {
+        VariableResolver {
+            extra_vars: options.extra_vars.clone(),
+            extra_vars_file: options.extra_vars_file.clone(),
+            ..Default::default()
+        }
+    }
+
+    // groups of `host` in the order ansible applies their variables: by
+    // depth, then by `ansible_group_priority`, then by name
+    fn sorted_groups(&self, host: &str) -> Vec<String> {
+        let mut groups = self.inventory.host_groups(host);
+        let priority = |group: &str| {
+            self.inventory
+                .groups
+                .get(group)
+                .and_then(|g| g.vars.get("ansible_group_priority"))
+                .and_then(|p| p.as_i64())
+                .unwrap_or(1)
+        };
+        groups.sort_by(|a, b| {
+            (self.inventory.group_depth(a), priority(a), a).cmp(&(
+                self.inventory.group_depth(b),
+                priority(b),
+                b,
+            ))
+        });
+
+        groups
+    }
+
+    // every layer defining variables for `host`, from the lowest precedence
+    fn layers(&self, host: &str) -> Result<Vec<LayerVars>, Box<dyn Error>> {
+        let groups = self.sorted_groups(host);
+        let mut layers = vec![];
+
+        for group in &groups {
+            if let Some(definition) = self.inventory.groups.get(group) {
+                layers.push((
+                    VariableLayer::InventoryGroupVars,
+                    group.clone(),
+                    definition.vars.clone(),
+                ));
+            }
+        }
+
+        for (layer, tree) in [
+            (VariableLayer::InventoryGroupVarsAll, &self.inventory_vars),
+            (VariableLayer::PlaybookGroupVarsAll, &self.playbook_vars),
+        ] {
+            if let Some(vars) = tree.group_vars.get(ALL_GROUP) {
+                layers.push((layer, ALL_GROUP.to_string(), vars.clone()));
+            }
+        }
+
+        for (layer, tree) in [
+            (
+                VariableLayer::InventoryGroupVarsGroups,
+                &self.inventory_vars,
+            ),
+            (VariableLayer::PlaybookGroupVarsGroups, &self.playbook_vars),
+        ] {
+            for group in groups.iter().filter(|group| *group != ALL_GROUP) {
+                if let Some(vars) = tree.group_vars.get(group) {
+                    layers.push((layer, group.clone(), vars.clone()));
+                }
+            }
+        }
+
+        if let Some(definition) = self.inventory.hosts.get(host) {
+            layers.push((
+                VariableLayer::InventoryHostVars,
+                host.to_string(),
+                definition.vars.clone(),
+            ));
+        }
+        for (layer, tree) in [
+            (VariableLayer::InventoryHostVarsHosts, &self.inventory_vars),
+            (VariableLayer::PlaybookHostVarsHosts, &self.playbook_vars),
+        ] {
+            if let Some(vars) = tree.host_vars.get(host) {
+                layers.push((layer, host.to_string(), vars.clone()));
+            }
+        }
+
+        layers.push((
+            VariableLayer::PlayVars,
+            "vars".to_string(),
+            self.play_vars.clone(),
+        ));
+        for path in &self.play_vars_files {
+            layers.push((VariableLayer::PlayVarsFiles, path.clone(), read_vars(path)?));
+        }
+
+        // inline extra variables come before the files, as on the command line
+        if let Value::Object(vars) = &self.extra_vars {
+            let vars = vars.clone().into_iter().collect();
+            layers.push((VariableLayer::ExtraVars, "extra_vars".to_string(), vars));
+        }
+        for extra in &self.extra_vars_file {
+            let vars = match extra.strip_prefix('@') {
+                Some(path) => read_vars(path)?,
+                None => parse_vars(extra).unwrap_or_else(|_| parse_key_values(extra)),
+            };
+            layers.push((VariableLayer::ExtraVars, extra.clone(), vars));
+        }
+
+        Ok(layers)
+    }
+
+    /// Returns the effective value of every variable defined for `host`
+    pub fn resolve_host(
+        &self,
+        host: &str,
+    ) -> Result<BTreeMap<String, ResolvedVariable>, Box<dyn Error>> {
+        if !self.inventory.hosts.contains_key(host) {
+            return Err(format!("(variables::resolve_host) unknown host '{}'", host).into());
+        }
+
+        let layers = match self.layers(host) {
+            Ok(layers) => layers,
+            Err(err) => return Err(format!("(variables::resolve_host) {}", err).into()),
+        };
+
+        let mut definitions: BTreeMap<String, Vec<VariableDefinition>> = BTreeMap::new();
+        for (layer, source, vars) in layers {
+            for (name, value) in vars {
+                definitions
+                    .entry(name)
+                    .or_default()
+                    .push(VariableDefinition {
+                        layer,
+                        source: source.clone(),
+                        value,
+                    });
+            }
+        }
+
+        let mut resolved = BTreeMap::new();
+        for (name, mut definitions) in definitions {
+            let winner = match definitions.pop() {
+                Some(winner) => winner,
+                None => continue,
+            };
+            definitions.reverse();
+
+            resolved.insert(
+                name.clone(),
+                ResolvedVariable {
+                    name,
+                    value: winner.value.clone(),
+                    winner,
+                    shadowed: definitions,
+                },
+            );
+        }
+
+        Ok(resolved)
+    }
+
+    /// Returns the effective value of the variable `name` for `host`, if it
+    /// is defined
+    pub fn resolve(
+        &self,
+        host: &str,
+        name: &str,
+    ) -> Result<Option<ResolvedVariable>, Box<dyn Error>> {
+        Ok(self.resolve_host(host)?.remove(name))
+    }
+}
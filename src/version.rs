@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Mutex, OnceLock};
+
+/// Flags which are only available starting from a given ansible-core
+/// release, as `(flag, major, minor)`
+const VERSIONED_FLAGS: &[(&str, u32, u32)] = &[
+    ("--become-password-file", 2, 12),
+    ("--connection-password-file", 2, 12),
+];
+
+/// What to do when a command uses options the installed ansible does not support
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnsibleVersionCheck {
+    Skip,   // don't detect the installed version
+    Warn,   // run anyway, reporting the options on the warnings of the execution
+    Reject, // refuse to run the command
+}
+
+/// Capabilities of an ansible installation, as described by the output of
+/// `ansible-playbook --version`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnsibleVersion {
+    pub core_version: String,             // ansible-core version, eg. `2.15.5`
+    pub config_file: Option<String>,      // configuration file in use, if any
+    pub module_search_path: Vec<String>,  // configured module search path
+    pub module_location: String,          // ansible python module location
+    pub collection_location: Vec<String>, // ansible collection location
+    pub executable_location: String,      // ansible-playbook executable location
+    pub python_version: String,           // version of the python interpreter running ansible
+    pub python_path: String,              // path of the python interpreter running ansible
+    pub jinja_version: String,            // version of jinja used for templating
+}
+
+impl AnsibleVersion {
+    /// Numeric `(major, minor, patch)` of the core version, pre-release
+    /// suffixes such as `rc1` being ignored
+    pub fn core(&self) -> (u32, u32, u32) {
+        let mut parts = self.core_version.split('.').map(|part| {
+            part.chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse::<u32>()
+                .unwrap_or(0)
+        });
+
+        (
+            parts.next().unwrap_or(0),
+            parts.next().unwrap_or(0),
+            parts.next().unwrap_or(0),
+        )
+    }
+
+    /// Whether the core version is at least `major.minor`
+    pub fn at_least(&self, major: u32, minor: u32) -> bool {
+        let (core_major, core_minor, _) = self.core();
+        (core_major, core_minor) >= (major, minor)
+    }
+
+    /// Returns the flags of `command` the installed version does not support
+    pub fn unsupported_flags(&self, command: &[String]) -> Vec<String> {
+        VERSIONED_FLAGS
+            .iter()
+            .filter(|(flag, major, minor)| {
+                command.iter().any(|arg| arg == flag) && !self.at_least(*major, *minor)
+            })
+            .map(|(flag, major, minor)| {
+                format!("{} (requires ansible-core {}.{})", flag, major, minor)
+            })
+            .collect()
+    }
+}
+
+// `['/a', '/b']` on module search path, `/a:/b` on collection location
+fn parse_path_list(value: &str) -> Vec<String> {
+    let value = value.trim();
+    if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        return list
+            .split(',')
+            .map(|path| path.trim().trim_matches('\'').trim_matches('"').to_string())
+            .filter(|path| !path.is_empty())
+            .collect();
+    }
+
+    value
+        .split(':')
+        .filter(|path| !path.is_empty())
+        .map(|path| path.to_string())
+        .collect()
+}
+
+/// Parse the output of `ansible-playbook --version`
+pub fn parse_version(output: &str) -> Result<AnsibleVersion, Box<dyn Error>> {
+    let mut lines = output.lines();
+    let header = match lines.next() {
+        Some(header) => header,
+        None => return Err("(version::parse_version) empty version output".into()),
+    };
+
+    // `ansible-playbook [core 2.15.5]` or `ansible-playbook 2.9.27` on older releases
+    let core_version = match header.split_once("[core ") {
+        Some((_, version)) => version.trim_end().trim_end_matches(']'),
+        None => header.split_whitespace().nth(1).unwrap_or_default(),
+    };
+    if core_version.is_empty() {
+        return Err(format!("(version::parse_version) invalid header '{}'", header).into());
+    }
+
+    let mut version = AnsibleVersion {
+        core_version: core_version.to_string(),
+        ..Default::default()
+    };
+
+    for line in lines {
+        let (key, value) = match line.split_once(" = ") {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+
+        match key {
+            "config file" if value != "None" => version.config_file = Some(value.to_string()),
+            "configured module search path" => version.module_search_path = parse_path_list(value),
+            "ansible python module location" => version.module_location = value.to_string(),
+            "ansible collection location" => version.collection_location = parse_path_list(value),
+            "executable location" => version.executable_location = value.to_string(),
+            "python version" => {
+                // `3.11.2 (main, Mar 13 2023, 12:18:29) [GCC 12.2.0] (/usr/bin/python3)`
+                version.python_version = value
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                if let Some(start) = value.rfind('(') {
+                    let path = value[start + 1..].trim_end_matches(')');
+                    if path.starts_with('/') {
+                        version.python_path = path.to_string();
+                    }
+                }
+            }
+            "jinja version" => version.jinja_version = value.to_string(),
+            _ => {}
+        }
+    }
+
+    Ok(version)
+}
+
+fn version_cache() -> &'static Mutex<HashMap<String, AnsibleVersion>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, AnsibleVersion>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Returns the version cached for the binary at `path`
+pub(crate) fn cached_version(path: &str) -> Option<AnsibleVersion> {
+    version_cache().lock().ok()?.get(path).cloned()
+}
+
+/// Caches the version detected for the binary at `path`
+pub(crate) fn cache_version(path: &str, version: &AnsibleVersion) {
+    if let Ok(mut cache) = version_cache().lock() {
+        cache.insert(path.to_string(), version.clone());
+    }
+}
@@ -0,0 +1,266 @@
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+    use rs_ansible::*;
+    use std::fs;
+
+    const SITE: &str = "tests/sites/analyze";
+
+    fn task(name: &str, module: &str, role: &str, tags: &[&str], file: &str) -> AnalyzedTask {
+        AnalyzedTask {
+            name: name.into(),
+            module: module.into(),
+            role: role.into(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            file: format!("{}/{}", SITE, file),
+            unresolvable: None,
+        }
+    }
+
+    fn unresolvable(task: AnalyzedTask) -> AnalyzedTask {
+        AnalyzedTask {
+            unresolvable: Some(
+                "dynamic include, its tasks are only known when the play runs".into(),
+            ),
+            ..task
+        }
+    }
+
+    #[test]
+    fn analyze_playbook() {
+        let analysis = PlaybookAnalyzer::default()
+            .analyze(format!("{}/site.yml", SITE))
+            .expect("analyze playbook");
+        assert_eq!(analysis.plays.len(), 3);
+
+        let base = &analysis.plays[0];
+        assert_eq!(base.name, "Gather facts");
+        assert_eq!(base.hosts, "all");
+        assert_eq!(base.file, format!("{}/base.yml", SITE));
+        assert_eq!(base.tags, vec!["base"]);
+        assert_eq!(
+            base.tasks,
+            vec![task(
+                "Collect facts",
+                "ansible.builtin.setup",
+                "",
+                &["base", "facts"],
+                "base.yml"
+            )]
+        );
+
+        let deploy = &analysis.plays[1];
+        assert_eq!(deploy.name, "Deploy web servers");
+        assert_eq!(deploy.hosts, "web:!web2");
+        assert_eq!(deploy.tags, vec!["deploy"]);
+        let common = "roles/common/tasks/main.yml";
+        assert_eq!(
+            deploy.tasks,
+            vec![
+                task(
+                    "Wait for connection",
+                    "ansible.builtin.wait_for_connection",
+                    "",
+                    &["deploy"],
+                    "site.yml"
+                ),
+                // dependencies run first, and roles already applied are skipped
+                task(
+                    "Install common packages",
+                    "ansible.builtin.package",
+                    "common",
+                    &["deploy", "web", "packages"],
+                    common
+                ),
+                task(
+                    "Install nginx",
+                    "ansible.builtin.package",
+                    "web",
+                    &["deploy", "web"],
+                    "roles/web/tasks/main.yml"
+                ),
+                task(
+                    "Start nginx",
+                    "ansible.builtin.service",
+                    "web",
+                    &["deploy", "web", "service"],
+                    "roles/web/tasks/service.yml"
+                ),
+                task(
+                    "Write configuration",
+                    "ansible.builtin.template",
+                    "",
+                    &["deploy", "config", "files"],
+                    "tasks/configure.yml"
+                ),
+                // the rescue and always tasks of the block are not listed
+                unresolvable(task(
+                    "Include system tasks",
+                    "ansible.builtin.include_tasks",
+                    "",
+                    &["deploy", "config"],
+                    "site.yml"
+                )),
+                // imported roles run again
+                task(
+                    "Install common packages",
+                    "ansible.builtin.package",
+                    "common",
+                    &["deploy", "again", "packages"],
+                    common
+                ),
+                unresolvable(task(
+                    "include_role",
+                    "include_role",
+                    "",
+                    &["deploy"],
+                    "site.yml"
+                )),
+                task(
+                    "ansible.builtin.ping",
+                    "ansible.builtin.ping",
+                    "",
+                    &["deploy"],
+                    "site.yml"
+                ),
+            ]
+        );
+        assert_eq!(
+            deploy.task_tags().into_iter().collect::<Vec<_>>(),
+            vec!["again", "config", "deploy", "files", "packages", "service", "web"]
+        );
+
+        let extra = &analysis.plays[2];
+        assert_eq!(extra.name, "{{ extra_playbook }}");
+        assert!(extra.tasks.is_empty());
+        assert_eq!(
+            extra.unresolvable.as_deref(),
+            Some("templated playbook import")
+        );
+
+        assert_eq!(
+            analysis.tags().into_iter().collect::<Vec<_>>(),
+            vec![
+                "again", "base", "config", "deploy", "facts", "files", "packages", "service", "web"
+            ]
+        );
+        assert_eq!(analysis.unresolvable_tasks().len(), 2);
+    }
+
+    #[test]
+    fn resolve_play_hosts() {
+        let mut inventory = Inventory::new();
+        for host in ["web1", "web2", "web3"] {
+            inventory.add_host_to_group("web", host);
+        }
+        inventory.add_host_to_group("db", "db1");
+
+        let analysis = PlaybookAnalyzer::default()
+            .analyze(format!("{}/site.yml", SITE))
+            .expect("analyze playbook");
+
+        struct ResolveHostsTest {
+            play: usize,
+            limit: &'static str,
+            expected: Vec<&'static str>,
+        }
+
+        let tests = vec![
+            ResolveHostsTest {
+                play: 0,
+                limit: "",
+                expected: vec!["web1", "web2", "web3", "db1"],
+            },
+            ResolveHostsTest {
+                play: 1,
+                limit: "",
+                expected: vec!["web1", "web3"],
+            },
+            ResolveHostsTest {
+                play: 1,
+                limit: "web3,db1",
+                expected: vec!["web3"],
+            },
+        ];
+
+        for test in tests {
+            let hosts = analysis.plays[test.play]
+                .resolve_hosts(&inventory, test.limit)
+                .expect("resolve hosts");
+            assert_eq!(
+                hosts, test.expected,
+                "play {} limit '{}'",
+                test.play, test.limit
+            );
+        }
+
+        let templated = AnalyzedPlay {
+            hosts: "{{ target }}".into(),
+            ..Default::default()
+        };
+        assert!(templated.resolve_hosts(&inventory, "").is_err());
+    }
+
+    #[test]
+    fn analyze_roles_path_and_errors() {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(format!("{}/shared/base/tasks", dir)).expect("create roles path");
+        fs::write(
+            format!("{}/shared/base/tasks/main.yml", dir),
+            "- name: Shared task\n  ansible.builtin.debug:\n    msg: !unsafe '{{ shared }}'\n",
+        )
+        .expect("write role");
+        fs::write(
+            format!("{}/site.yml", dir),
+            "- hosts: all\n  roles:\n    - base\n    - community.general.tools\n",
+        )
+        .expect("write playbook");
+        fs::write(
+            format!("{}/missing.yml", dir),
+            "- hosts: all\n  roles:\n    - missing\n",
+        )
+        .expect("write playbook");
+        fs::write(
+            format!("{}/loop.yml", dir),
+            "- hosts: all\n  tasks:\n    - import_tasks: loop_tasks.yml\n",
+        )
+        .expect("write playbook");
+        fs::write(
+            format!("{}/loop_tasks.yml", dir),
+            "- import_tasks: loop_tasks.yml\n",
+        )
+        .expect("write tasks");
+
+        // roles are only found through the roles path
+        assert!(PlaybookAnalyzer::default()
+            .analyze(format!("{}/site.yml", dir))
+            .is_err());
+
+        let analyzer = PlaybookAnalyzer {
+            roles_path: vec![format!("{}/shared", dir)],
+        };
+        let analysis = analyzer
+            .analyze(format!("{}/site.yml", dir))
+            .expect("analyze playbook");
+        let tasks = &analysis.plays[0].tasks;
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].name, "Shared task");
+        assert_eq!(tasks[0].role, "base");
+        assert_eq!(tasks[1].name, "community.general.tools");
+        assert!(tasks[1].unresolvable.is_some());
+
+        for (playbook, expected) in [
+            ("missing.yml", "role 'missing' not found"),
+            ("loop.yml", "too many nested imports"),
+            ("absent.yml", "No such file or directory"),
+        ] {
+            let err = analyzer
+                .analyze(format!("{}/{}", dir, playbook))
+                .expect_err(playbook);
+            assert!(err.to_string().starts_with("(analyze::analyze)"), "{}", err);
+            assert!(err.to_string().contains(expected), "{}", err);
+        }
+
+        fs::remove_dir_all(&dir).expect("remove playbook dir");
+    }
+}
@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+    use rs_ansible::*;
+    use serde_json::json;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Create a fake ansible installation directory holding an
+    /// `ansible-config` script which dumps the `ANSIBLE_FORKS` variable
+    fn fake_ansible_dir() -> String {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create fake ansible dir");
+
+        let script = format!("{}/ansible-config", dir);
+        fs::write(
+            &script,
+            "#!/bin/sh\necho '[{\"name\": \"DEFAULT_FORKS\", \"value\": '\"$ANSIBLE_FORKS\"', \"origin\": \"env: ANSIBLE_FORKS\", \"type\": \"integer\"}]'\n",
+        )
+        .expect("write fake ansible-config");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        dir
+    }
+
+    #[test]
+    fn generate_command() {
+        let cmd = AnsibleConfigCmd {
+            options: AnsibleConfigOptions {
+                config: "ansible.cfg".into(),
+                config_type: "base".into(),
+                format: "json".into(),
+                only_changed: true,
+            },
+            ..Default::default()
+        };
+
+        let expected = vec![
+            "ansible-config",
+            "dump",
+            "--config",
+            "ansible.cfg",
+            "--type",
+            "base",
+            "--format",
+            "json",
+            "--only-changed",
+        ];
+
+        match cmd.command() {
+            Ok(res) => assert_eq!(res, expected),
+            _ => panic!("generate AnsibleConfigCmd command"),
+        }
+    }
+
+    #[test]
+    fn should_fail_to_view_with_format() {
+        let cmd = AnsibleConfigCmd {
+            action: AnsibleConfigAction::View,
+            options: AnsibleConfigOptions {
+                format: "json".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(cmd.command().is_err());
+    }
+
+    #[test]
+    fn parse_dump() {
+        let output = r#"[
+            {"name": "DEFAULT_FORKS", "value": 10, "origin": "/etc/ansible/ansible.cfg", "type": "integer"},
+            {"name": "HOST_KEY_CHECKING", "value": false, "origin": "env: ANSIBLE_HOST_KEY_CHECKING"},
+            {"name": "COLLECTIONS_PATHS", "value": ["/usr/share/ansible/collections"], "origin": "default", "type": "pathspec"}
+        ]"#;
+
+        match parse_config_dump(output) {
+            Ok(settings) => {
+                assert_eq!(settings.len(), 3);
+                assert_eq!(
+                    settings["DEFAULT_FORKS"],
+                    AnsibleConfigSetting {
+                        value: json!(10),
+                        origin: "/etc/ansible/ansible.cfg".into(),
+                        setting_type: "integer".into(),
+                    }
+                );
+                assert_eq!(settings["HOST_KEY_CHECKING"].setting_type, "bool");
+                assert_eq!(settings["COLLECTIONS_PATHS"].origin, "default");
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn parse_dump_all_types() {
+        let output = r#"{
+            "base": [{"name": "DEFAULT_FORKS", "value": 5, "origin": "default", "type": "integer"}],
+            "become": {"sudo": [{"name": "become_exe", "value": "sudo", "origin": "default", "type": "string"}]}
+        }"#;
+
+        match parse_config_dump(output) {
+            Ok(settings) => {
+                assert_eq!(settings["DEFAULT_FORKS"].value, json!(5));
+                assert_eq!(settings["become.sudo.become_exe"].value, json!("sudo"));
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn parse_list() {
+        let output = r#"{
+            "DEFAULT_FORKS": {
+                "default": 5,
+                "description": ["Maximum number of forks Ansible will use to execute tasks on target hosts."],
+                "env": [{"name": "ANSIBLE_FORKS"}],
+                "ini": [{"key": "forks", "section": "defaults"}],
+                "name": "Number of task forks",
+                "type": "integer"
+            }
+        }"#;
+
+        match parse_config_list(output) {
+            Ok(list) => {
+                let forks = &list["DEFAULT_FORKS"];
+                assert_eq!(forks.default, json!(5));
+                assert_eq!(forks.setting_type, Some("integer".into()));
+                assert_eq!(forks.env, vec!["ANSIBLE_FORKS"]);
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn dump_with_playbook_environment() {
+        let dir = fake_ansible_dir();
+        let mut playbook = AnsiblePlaybookCmd {
+            binary: format!("{}/ansible-playbook", dir),
+            ..Default::default()
+        };
+        playbook.env.insert("ANSIBLE_FORKS".into(), "42".into());
+
+        let cmd = AnsibleConfigCmd::from_playbook(&playbook);
+        assert_eq!(cmd.binary, format!("{}/ansible-config", dir));
+
+        let settings = cmd.dump();
+        fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+
+        match settings {
+            Ok(settings) => {
+                assert_eq!(settings["DEFAULT_FORKS"].value, json!(42));
+                assert_eq!(settings["DEFAULT_FORKS"].origin, "env: ANSIBLE_FORKS");
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+#[cfg(test)]
+mod tests {
+    use rs_ansible::*;
+    use serde_json::json;
+    use std::fs;
+
+    fn copy_doc() -> AnsibleDoc {
+        let output = fs::read_to_string("tests/fixtures/doc_copy.json").expect("read fixture");
+        match parse_doc(&output) {
+            Ok(mut docs) => docs
+                .remove("ansible.builtin.copy")
+                .expect("copy module doc"),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn generate_command() {
+        struct DocCmdTest<'a> {
+            cmd: AnsibleDocCmd,
+            expected: Vec<&'a str>,
+            desc: &'a str,
+        }
+
+        let tests = vec![
+            DocCmdTest {
+                desc: "Module documentation as json",
+                cmd: AnsibleDocCmd {
+                    plugins: vec!["ansible.builtin.copy".into()],
+                    options: AnsibleDocOptions {
+                        json: true,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                expected: vec![
+                    "ansible-doc",
+                    "--type",
+                    "module",
+                    "--json",
+                    "ansible.builtin.copy",
+                ],
+            },
+            DocCmdTest {
+                desc: "List lookup plugins",
+                cmd: AnsibleDocCmd {
+                    plugin_type: AnsibleDocPluginType::Lookup,
+                    options: AnsibleDocOptions {
+                        json: true,
+                        list: true,
+                        playbook_dir: "playbooks".into(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                expected: vec![
+                    "ansible-doc",
+                    "--type",
+                    "lookup",
+                    "--json",
+                    "--list",
+                    "--playbook-dir",
+                    "playbooks",
+                ],
+            },
+        ];
+
+        for test in tests {
+            let DocCmdTest {
+                desc,
+                cmd,
+                expected,
+            } = test;
+            match cmd.command() {
+                Ok(res) => assert_eq!(res, expected, "{}", desc),
+                _ => panic!("{}", desc),
+            }
+        }
+    }
+
+    #[test]
+    fn should_fail_without_plugins() {
+        let cmd = AnsibleDocCmd {
+            ..Default::default()
+        };
+
+        assert!(cmd.command().is_err());
+    }
+
+    #[test]
+    fn parse_module_doc() {
+        let doc = copy_doc();
+
+        assert_eq!(doc.short_description, "Copy files to remote locations");
+        assert_eq!(doc.description.len(), 1);
+        assert!(doc.examples.contains("ansible.builtin.copy:"));
+
+        let dest = &doc.options["dest"];
+        assert_eq!(dest.option_type, "path");
+        assert!(dest.required);
+        assert_eq!(dest.default, json!(null));
+
+        let state = &doc.options["state"];
+        assert_eq!(state.option_type, "str");
+        assert!(!state.required);
+        assert_eq!(state.default, json!("file"));
+        assert_eq!(state.choices, vec![json!("file"), json!("absent")]);
+
+        assert_eq!(
+            doc.options["mode"].description,
+            vec!["The permissions of the destination file or directory."]
+        );
+        assert_eq!(doc.options["checksum"].option_type, "str");
+
+        let ret = &doc.returns["dest"];
+        assert_eq!(ret.returned, "success");
+        assert_eq!(ret.return_type, "str");
+        assert_eq!(ret.sample, json!("/path/to/file.txt"));
+    }
+
+    #[test]
+    fn parse_plugin_list() {
+        let output = r#"{"ansible.builtin.copy": "Copy files to remote locations", "ansible.builtin.debug": "Print statements during execution"}"#;
+
+        match parse_doc_list(output) {
+            Ok(list) => {
+                assert_eq!(list.len(), 2);
+                assert_eq!(
+                    list["ansible.builtin.debug"],
+                    "Print statements during execution"
+                );
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn validate_module_args() {
+        let doc = copy_doc();
+
+        struct ValidateTest<'a> {
+            args: serde_json::Value,
+            valid: bool,
+            desc: &'a str,
+        }
+
+        let tests = vec![
+            ValidateTest {
+                desc: "Required option and known options",
+                args: json!({"src": "foo.conf", "dest": "/etc/foo.conf", "backup": "yes", "mode": "0644"}),
+                valid: true,
+            },
+            ValidateTest {
+                desc: "Option given through its alias",
+                args: json!({"dest": "/etc/foo.conf", "validate_cmd": "visudo -cf %s"}),
+                valid: true,
+            },
+            ValidateTest {
+                desc: "Missing required option",
+                args: json!({"src": "foo.conf"}),
+                valid: false,
+            },
+            ValidateTest {
+                desc: "Unknown option",
+                args: json!({"dest": "/etc/foo.conf", "owner_name": "root"}),
+                valid: false,
+            },
+            ValidateTest {
+                desc: "Value outside of choices",
+                args: json!({"dest": "/etc/foo.conf", "state": "directory"}),
+                valid: false,
+            },
+            ValidateTest {
+                desc: "Value of the wrong type",
+                args: json!({"dest": "/etc/foo.conf", "backup": ["yes"]}),
+                valid: false,
+            },
+        ];
+
+        for test in tests {
+            assert_eq!(
+                doc.validate_args(&test.args).is_ok(),
+                test.valid,
+                "{}",
+                test.desc
+            );
+        }
+    }
+}
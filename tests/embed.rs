@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+    use rs_ansible::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    static PROJECT: PlaybookBundle = embed_playbooks!("tests/fixtures/bundle", [
+        "site.yml",
+        "roles/web/tasks/main.yml",
+        "roles/web/files/check.sh" => 0o755,
+    ]);
+
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).expect("stat").permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn embed_playbook_project() {
+        assert_eq!(PROJECT.files.len(), 3);
+        assert_eq!(PROJECT.hash, bundle_hash(PROJECT.files));
+        assert_eq!(
+            PROJECT.files[0].content,
+            fs::read("tests/fixtures/bundle/site.yml").expect("read site.yml")
+        );
+        assert_eq!(PROJECT.files[0].mode, 0o644);
+        assert_eq!(PROJECT.files[2].mode, 0o755);
+
+        // the hash covers paths, modes and contents
+        static RENAMED: [EmbeddedFile; 1] = [EmbeddedFile {
+            path: "main.yml",
+            content: b"---\n",
+            mode: 0o644,
+        }];
+        static EXECUTABLE: [EmbeddedFile; 1] = [EmbeddedFile {
+            path: "main.yml",
+            content: b"---\n",
+            mode: 0o755,
+        }];
+        assert_ne!(bundle_hash(&RENAMED), bundle_hash(&EXECUTABLE));
+        assert_ne!(bundle_hash(&RENAMED), PROJECT.hash);
+    }
+
+    #[test]
+    fn extract_and_reuse_bundle() {
+        let directory = PROJECT.extract().expect("extract bundle");
+        assert!(directory.ends_with(format!("rs-ansible-bundle-{:016x}", PROJECT.hash)));
+        assert_eq!(mode(&directory), 0o700);
+        assert_eq!(mode(&directory.join("roles/web")), 0o700);
+        assert_eq!(mode(&directory.join("site.yml")), 0o600);
+        assert_eq!(mode(&directory.join("roles/web/files/check.sh")), 0o700);
+        assert_eq!(
+            fs::read_to_string(directory.join("roles/web/tasks/main.yml")).expect("read task"),
+            fs::read_to_string("tests/fixtures/bundle/roles/web/tasks/main.yml")
+                .expect("read fixture")
+        );
+
+        // a later extraction reuses the first one
+        assert_eq!(PROJECT.extract().expect("extract bundle again"), directory);
+
+        fs::remove_dir_all(&directory).expect("remove extraction");
+    }
+
+    #[test]
+    fn skip_tampered_extraction() {
+        static BUNDLE: PlaybookBundle = embed_playbooks!("tests/fixtures/bundle", ["site.yml"]);
+
+        let directory = BUNDLE.extract().expect("extract bundle");
+        fs::write(directory.join("site.yml"), "- hosts: all\n").expect("tamper playbook");
+
+        let extracted = BUNDLE.extract().expect("extract tampered bundle");
+        assert!(extracted.ends_with(format!("rs-ansible-bundle-{:016x}-1", BUNDLE.hash)));
+        assert_eq!(
+            fs::read(extracted.join("site.yml")).expect("read playbook"),
+            BUNDLE.files[0].content
+        );
+
+        // the fallback is reused rather than extracting the bundle again
+        assert_eq!(BUNDLE.extract().expect("extract bundle again"), extracted);
+
+        // a taken path, such as a file squatting it, is skipped too
+        fs::remove_dir_all(&extracted).expect("remove extraction");
+        fs::write(&extracted, "").expect("squat fallback");
+        let fallback = BUNDLE.extract().expect("extract squatted bundle");
+        assert!(fallback.ends_with(format!("rs-ansible-bundle-{:016x}-2", BUNDLE.hash)));
+
+        fs::remove_dir_all(&directory).expect("remove tampered extraction");
+        fs::remove_file(&extracted).expect("remove squatting file");
+        fs::remove_dir_all(&fallback).expect("remove extraction");
+    }
+
+    #[test]
+    fn run_embedded_playbook() {
+        static BUNDLE: PlaybookBundle = embed_playbooks!(
+            "tests/fixtures/bundle",
+            ["site.yml", "roles/web/tasks/main.yml"]
+        );
+
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create fake ansible dir");
+        let script = format!("{}/ansible-playbook", dir);
+        fs::write(&script, format!("#!/bin/sh\necho \"$@\" > {}/args\n", dir))
+            .expect("write fake ansible-playbook");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let cmd = AnsiblePlaybookCmd {
+            binary: script,
+            playbooks: vec![BUNDLE.playbook("site.yml").expect("extract playbook")],
+            ..Default::default()
+        };
+        let mut child = cmd.run().expect("run playbook");
+        assert!(child.wait().expect("wait for playbook").success());
+
+        let args = fs::read_to_string(format!("{}/args", dir)).expect("read args");
+        fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+
+        // the extraction is kept once the run is over, to be reused
+        let playbook = Path::new(args.trim());
+        assert!(playbook.ends_with("site.yml"));
+        assert!(playbook.exists());
+        fs::remove_dir_all(playbook.parent().expect("extraction")).expect("remove extraction");
+
+        assert!(BUNDLE.playbook("missing.yml").is_err());
+    }
+
+    #[test]
+    fn generate_bundle() {
+        let out = format!("/tmp/rs-ansible-test-{}.rs", thread_rng().gen::<u64>());
+        generate_playbook_bundle("tests/fixtures/bundle", &out).expect("generate bundle");
+        let code = fs::read_to_string(&out).expect("read generated bundle");
+        fs::remove_file(&out).expect("remove generated bundle");
+
+        let root = Path::new("tests/fixtures/bundle")
+            .canonicalize()
+            .expect("canonicalize");
+        for (path, mode) in [
+            ("roles/web/files/check.sh", "0o755"),
+            ("roles/web/tasks/main.yml", "0o644"),
+            ("site.yml", "0o644"),
+        ] {
+            assert!(code.contains(&format!(
+                "path: {:?}, content: include_bytes!({:?}), mode: {} }}",
+                path,
+                root.join(path).to_string_lossy(),
+                mode
+            )));
+        }
+        assert!(code.contains("::rs_ansible::bundle_hash(FILES)"));
+
+        assert!(generate_playbook_bundle("tests/fixtures/missing", &out).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+#[cfg(test)]
+mod tests {
+    use rs_ansible::*;
+
+    #[test]
+    fn generate_galaxy_options() {
+        let options = AnsibleGalaxyOptions {
+            force: true,
+            force_with_deps: true,
+            format: "json".into(),
+            ignore_errors: true,
+            no_deps: true,
+            offline: true,
+            output_path: "dist".into(),
+            path: "collections".into(),
+            requirements_file: "requirements.yml".into(),
+        };
+
+        let expected = vec![
+            "--force",
+            "--force-with-deps",
+            "--format",
+            "json",
+            "--ignore-errors",
+            "--no-deps",
+            "--offline",
+            "--output-path",
+            "dist",
+            "-p",
+            "collections",
+            "-r",
+            "requirements.yml",
+        ];
+
+        match options.gen_opts() {
+            Ok(res) => assert_eq!(res, expected),
+            _ => panic!("Generate galaxy options"),
+        }
+    }
+
+    #[test]
+    fn generate_command() {
+        struct GalaxyCmdTest<'a> {
+            cmd: AnsibleGalaxyCmd,
+            expected: Vec<&'a str>,
+            desc: &'a str,
+        }
+
+        let tests = vec![
+            GalaxyCmdTest {
+                desc: "Install roles from a requirements file",
+                cmd: AnsibleGalaxyCmd {
+                    galaxy_type: AnsibleGalaxyType::Role,
+                    options: AnsibleGalaxyOptions {
+                        force: true,
+                        path: "roles".into(),
+                        requirements_file: "requirements.yml".into(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                expected: vec![
+                    "ansible-galaxy",
+                    "role",
+                    "install",
+                    "--force",
+                    "-p",
+                    "roles",
+                    "-r",
+                    "requirements.yml",
+                ],
+            },
+            GalaxyCmdTest {
+                desc: "Install a collection tarball offline",
+                cmd: AnsibleGalaxyCmd {
+                    args: vec!["./my_ns-my_col-1.0.0.tar.gz".into()],
+                    options: AnsibleGalaxyOptions {
+                        offline: true,
+                        path: "collections".into(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                expected: vec![
+                    "ansible-galaxy",
+                    "collection",
+                    "install",
+                    "--offline",
+                    "-p",
+                    "collections",
+                    "./my_ns-my_col-1.0.0.tar.gz",
+                ],
+            },
+            GalaxyCmdTest {
+                desc: "Build a collection from a directory",
+                cmd: AnsibleGalaxyCmd {
+                    action: AnsibleGalaxyAction::Build,
+                    args: vec!["my_ns/my_col".into()],
+                    options: AnsibleGalaxyOptions {
+                        force: true,
+                        output_path: "dist".into(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                expected: vec![
+                    "ansible-galaxy",
+                    "collection",
+                    "build",
+                    "--force",
+                    "--output-path",
+                    "dist",
+                    "my_ns/my_col",
+                ],
+            },
+            GalaxyCmdTest {
+                desc: "List installed roles",
+                cmd: AnsibleGalaxyCmd {
+                    galaxy_type: AnsibleGalaxyType::Role,
+                    action: AnsibleGalaxyAction::List,
+                    ..Default::default()
+                },
+                expected: vec!["ansible-galaxy", "role", "list"],
+            },
+        ];
+
+        for test in tests {
+            let GalaxyCmdTest {
+                desc,
+                cmd,
+                expected,
+            } = test;
+            match cmd.command() {
+                Ok(res) => assert_eq!(res, expected, "{}", desc),
+                _ => panic!("{}", desc),
+            }
+        }
+    }
+
+    #[test]
+    fn should_fail_to_build_roles() {
+        let cmd = AnsibleGalaxyCmd {
+            galaxy_type: AnsibleGalaxyType::Role,
+            action: AnsibleGalaxyAction::Build,
+            ..Default::default()
+        };
+
+        assert!(cmd.command().is_err());
+    }
+
+    #[test]
+    fn should_fail_to_install_roles_offline() {
+        let cmd = AnsibleGalaxyCmd {
+            galaxy_type: AnsibleGalaxyType::Role,
+            options: AnsibleGalaxyOptions {
+                offline: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert!(cmd.command().is_err());
+    }
+
+    #[test]
+    fn parse_collections() {
+        let output = r#"{
+            "/usr/share/ansible/collections/ansible_collections": {
+                "community.general": {"version": "7.5.0"},
+                "ansible.posix": {"version": "1.5.4"}
+            },
+            "/home/user/.ansible/collections/ansible_collections": {
+                "my_ns.my_col": {"version": "*"}
+            }
+        }"#;
+
+        let expected = vec![
+            AnsibleGalaxyCollection {
+                namespace: "my_ns".into(),
+                name: "my_col".into(),
+                version: "*".into(),
+                path: "/home/user/.ansible/collections/ansible_collections".into(),
+            },
+            AnsibleGalaxyCollection {
+                namespace: "ansible".into(),
+                name: "posix".into(),
+                version: "1.5.4".into(),
+                path: "/usr/share/ansible/collections/ansible_collections".into(),
+            },
+            AnsibleGalaxyCollection {
+                namespace: "community".into(),
+                name: "general".into(),
+                version: "7.5.0".into(),
+                path: "/usr/share/ansible/collections/ansible_collections".into(),
+            },
+        ];
+
+        match parse_collection_list(output) {
+            Ok(res) => {
+                assert_eq!(res, expected);
+                assert_eq!(res[2].fqcn(), "community.general");
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn should_fail_to_parse_invalid_collections() {
+        assert!(parse_collection_list("not json").is_err());
+        assert!(
+            parse_collection_list(r#"{"/path": {"nonamespace": {"version": "1.0.0"}}}"#).is_err()
+        );
+    }
+}
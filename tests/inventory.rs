@@ -0,0 +1,1891 @@
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+    use rs_ansible::*;
+    use serde_json::json;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    fn sample_inventory() -> Inventory {
+        let mut inventory = Inventory::new();
+
+        inventory.add_host("bastion");
+        inventory.set_host_var("bastion", "ansible_host", json!("10.0.0.1"));
+        inventory.add_host_to_group("web", "web1");
+        inventory.add_host_to_group("web", "web2");
+        inventory.set_host_var("web1", "http_port", json!(8080));
+        inventory.add_host_to_group("db", "db1");
+        inventory.set_host_var("db1", "motd", json!("hello world"));
+        inventory.add_child_group("app", "web");
+        inventory.add_child_group("app", "db");
+        inventory.set_group_var("web", "proxy", json!(true));
+        inventory.set_group_var("all", "ntp_server", json!("ntp.example.com"));
+
+        inventory
+    }
+
+    #[test]
+    fn resolve_groups() {
+        let inventory = sample_inventory();
+
+        assert_eq!(inventory.ungrouped_hosts(), vec!["bastion"]);
+        assert_eq!(inventory.group_hosts("app"), vec!["web1", "web2", "db1"]);
+        // hosts of `all` keep their definition order, as in ansible
+        assert_eq!(
+            inventory.group_hosts("all"),
+            vec!["bastion", "web1", "web2", "db1"]
+        );
+        assert_eq!(inventory.group_hosts("ungrouped"), vec!["bastion"]);
+        assert!(inventory.group_hosts("missing").is_empty());
+        assert_eq!(inventory.host_groups("web1"), vec!["all", "app", "web"]);
+        assert_eq!(inventory.host_groups("bastion"), vec!["all", "ungrouped"]);
+        assert_eq!(inventory.parent_groups("db"), vec!["app"]);
+    }
+
+    #[test]
+    fn render_ini() {
+        let expected = "bastion ansible_host=10.0.0.1
+
+[app]
+
+[app:children]
+web
+db
+
+[db]
+db1 motd=\"hello world\"
+
+[web]
+web1 http_port=8080
+web2
+
+[web:vars]
+proxy=True
+
+[all:vars]
+ntp_server=ntp.example.com
+";
+
+        assert_eq!(sample_inventory().to_ini(), expected);
+    }
+
+    #[test]
+    fn render_yaml_and_json() {
+        let expected = json!({
+            "all": {
+                "hosts": {
+                    "bastion": {"ansible_host": "10.0.0.1"}
+                },
+                "vars": {"ntp_server": "ntp.example.com"},
+                "children": {
+                    "app": {
+                        "children": {
+                            "web": {
+                                "hosts": {"web1": {"http_port": 8080}, "web2": null},
+                                "vars": {"proxy": true}
+                            },
+                            "db": {
+                                "hosts": {"db1": {"motd": "hello world"}}
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        let inventory = sample_inventory();
+        assert_eq!(inventory.to_value(), expected);
+
+        let yaml: serde_json::Value =
+            serde_yaml::from_str(&inventory.to_yaml().expect("render yaml")).expect("parse yaml");
+        assert_eq!(yaml, expected);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&inventory.to_json().expect("render json")).expect("parse json");
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn render_ini_literals() {
+        let mut inventory = Inventory::new();
+        inventory.set_host_var("host", "port", json!("22"));
+        inventory.set_host_var("host", "list", json!([1, "a"]));
+        inventory.set_host_var("host", "empty", json!(""));
+        inventory.set_host_var("host", "none", json!(null));
+
+        assert_eq!(
+            inventory.to_ini(),
+            "host empty=\"''\" list=\"[1, 'a']\" none=None port=\"'22'\"\n"
+        );
+    }
+
+    #[test]
+    fn run_with_inventory_model() {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create fake ansible dir");
+        let script = format!("{}/ansible-playbook", dir);
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$@\" > {dir}/args\ncat \"$2\" > {dir}/inventory\n",
+                dir = dir
+            ),
+        )
+        .expect("write fake ansible-playbook");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let playbook = AnsiblePlaybookCmd {
+            binary: script,
+            playbooks: vec!["site.yml".into()],
+            options: AnsiblePlaybookOptions {
+                inventory: vec![sample_inventory().into()],
+                ..Default::default()
+            },
+            inventory_format: InventoryFormat::Ini,
+            ..Default::default()
+        };
+
+        let mut child = playbook.run().expect("run playbook");
+        assert!(child.wait().expect("wait for playbook").success());
+
+        let args = fs::read_to_string(format!("{}/args", dir)).expect("read args");
+        let inventory = fs::read_to_string(format!("{}/inventory", dir)).expect("read inventory");
+        fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+
+        let args: Vec<&str> = args.split_whitespace().collect();
+        assert_eq!(args[0], "--inventory");
+        assert!(args[1].ends_with(".ini"));
+        assert_eq!(args[2], "site.yml");
+        assert_eq!(inventory, sample_inventory().to_ini());
+        assert!(!Path::new(args[1]).exists());
+    }
+
+    #[test]
+    fn parse_ini() {
+        let content = "# comment
+bastion ansible_host=10.0.0.1
+
+[web]
+web[01:03].example.com http_port=8080 motd='hello world'
+lb:2222 weights=\"[1, 2]\" # comment
+
+[web:vars]
+proxy = True
+retries=3
+label=front end
+
+[db]
+db-[a:b] backup=None
+
+[app:children]
+web
+db # trailing comment
+
+[all:vars]
+ntp_server=ntp.example.com
+";
+        let inventory = Inventory::from_ini(content).unwrap();
+
+        assert_eq!(inventory.ungrouped_hosts(), vec!["bastion"]);
+        assert_eq!(
+            inventory.groups["web"].hosts,
+            vec![
+                "web01.example.com",
+                "web02.example.com",
+                "web03.example.com",
+                "lb"
+            ]
+        );
+        assert_eq!(inventory.groups["db"].hosts, vec!["db-a", "db-b"]);
+        assert_eq!(inventory.groups["app"].children, vec!["web", "db"]);
+        assert_eq!(inventory.group_hosts("app").len(), 6);
+
+        let web2 = &inventory.hosts["web02.example.com"].vars;
+        assert_eq!(web2["http_port"], json!(8080));
+        assert_eq!(web2["motd"], json!("hello world"));
+        let lb = &inventory.hosts["lb"].vars;
+        assert_eq!(lb["ansible_port"], json!(2222));
+        assert_eq!(lb["weights"], json!([1, 2]));
+        assert_eq!(inventory.hosts["db-a"].vars["backup"], json!(null));
+
+        let web = &inventory.groups["web"].vars;
+        assert_eq!(web["proxy"], json!(true));
+        assert_eq!(web["retries"], json!(3));
+        assert_eq!(web["label"], json!("front end"));
+        assert_eq!(
+            inventory.groups["all"].vars["ntp_server"],
+            json!("ntp.example.com")
+        );
+        assert!(!inventory.groups.contains_key("ungrouped"));
+    }
+
+    #[test]
+    fn parse_yaml() {
+        let content = "all:
+  hosts:
+    bastion:
+      ansible_host: 10.0.0.1
+  vars:
+    ntp_server: ntp.example.com
+  children:
+    app:
+      children:
+        web:
+          hosts:
+            web[1:2]:
+              http_port: 8080
+            lb:2222:
+          vars:
+            proxy: true
+        db:
+          hosts:
+            db1:
+          host: db2
+";
+        let inventory = Inventory::from_yaml(content).unwrap();
+
+        assert_eq!(inventory.ungrouped_hosts(), vec!["bastion"]);
+        assert_eq!(inventory.groups["web"].hosts, vec!["web1", "web2", "lb"]);
+        assert_eq!(inventory.groups["app"].children, vec!["web", "db"]);
+        assert_eq!(inventory.hosts["web2"].vars["http_port"], json!(8080));
+        assert_eq!(inventory.hosts["lb"].vars["ansible_port"], json!(2222));
+        assert_eq!(inventory.groups["web"].vars["proxy"], json!(true));
+        assert_eq!(
+            inventory.groups["all"].vars["ntp_server"],
+            json!("ntp.example.com")
+        );
+        assert_eq!(inventory.groups["all"].hosts.len(), 0);
+        assert_eq!(
+            inventory.warnings,
+            vec![
+                "Skipping unexpected key \"host\" in group \"db\", only \"hosts\", \"vars\", \"children\" are valid"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_rendered_inventory() {
+        let inventory = sample_inventory();
+
+        assert_eq!(Inventory::from_ini(&inventory.to_ini()).unwrap(), inventory);
+        assert_eq!(
+            Inventory::from_yaml(&inventory.to_yaml().unwrap()).unwrap(),
+            inventory
+        );
+        assert_eq!(
+            Inventory::from_yaml(&inventory.to_json().unwrap()).unwrap(),
+            inventory
+        );
+    }
+
+    #[test]
+    fn render_ungrouped_vars() {
+        let mut inventory = sample_inventory();
+        inventory.set_group_var("ungrouped", "ansible_user", json!("admin"));
+
+        assert!(inventory.to_ini().ends_with(
+            "[ungrouped:vars]\nansible_user=admin\n\n[all:vars]\nntp_server=ntp.example.com\n"
+        ));
+        assert_eq!(
+            inventory.to_value()["all"]["children"]["ungrouped"],
+            json!({"vars": {"ansible_user": "admin"}})
+        );
+
+        assert_eq!(Inventory::from_ini(&inventory.to_ini()).unwrap(), inventory);
+        assert_eq!(
+            Inventory::from_yaml(&inventory.to_yaml().unwrap()).unwrap(),
+            inventory
+        );
+    }
+
+    struct InventoryParseErrorTest {
+        content: &'static str,
+        line: usize,
+    }
+
+    #[test]
+    fn parse_ini_errors() {
+        let tests = [
+            InventoryParseErrorTest {
+                content: "[web]\nweb1\n[web:hosts:extra]",
+                line: 3,
+            },
+            InventoryParseErrorTest {
+                content: "[web]\nweb1 http_port",
+                line: 2,
+            },
+            InventoryParseErrorTest {
+                content: "[web:vars]\nproxy\n",
+                line: 2,
+            },
+            InventoryParseErrorTest {
+                content: "[web]\nweb1\n\n[db:vars]\nproxy=true\n",
+                line: 4,
+            },
+            InventoryParseErrorTest {
+                content: "[web]\nweb[1:]\n",
+                line: 2,
+            },
+            InventoryParseErrorTest {
+                content: "[web]\nweb1 motd='hello\n",
+                line: 2,
+            },
+            InventoryParseErrorTest {
+                content: "[web]\n[db:unknown]\n",
+                line: 2,
+            },
+        ];
+
+        for test in tests {
+            let err = Inventory::from_ini(test.content).unwrap_err();
+            assert_eq!(err.line, test.line, "{}", err);
+        }
+    }
+
+    #[test]
+    fn parse_yaml_errors() {
+        let tests = [
+            InventoryParseErrorTest {
+                content: "all:\n  hosts:\n    web1:\n   bad: indent\n",
+                line: 4,
+            },
+            InventoryParseErrorTest {
+                content: "all:\n  children:\n    web:\n      hosts:\n        - web1\n",
+                line: 3,
+            },
+            InventoryParseErrorTest {
+                content: "all:\n  hosts:\n    web1:\n      - not vars\n",
+                line: 3,
+            },
+        ];
+
+        for test in tests {
+            let err = Inventory::from_yaml(test.content).unwrap_err();
+            assert_eq!(err.line, test.line, "{}", err);
+        }
+    }
+
+    #[test]
+    fn load_inventory_file() {
+        // tests/sites/hosts.yaml is an INI file, read as ansible does
+        let inventory = Inventory::from_file("tests/sites/hosts.yaml").unwrap();
+        assert_eq!(inventory.group_hosts("local"), vec!["127.0.0.1"]);
+
+        let path =
+            std::env::temp_dir().join(format!("rs-ansible-test-{}.yml", thread_rng().gen::<u64>()));
+        fs::write(&path, sample_inventory().to_yaml().unwrap()).unwrap();
+        let inventory = Inventory::from_file(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(inventory.unwrap(), sample_inventory());
+
+        assert!(Inventory::from_file("tests/sites/missing.ini").is_err());
+    }
+
+    struct HostRangeTest {
+        pattern: &'static str,
+        hosts: Vec<&'static str>,
+    }
+
+    #[test]
+    fn expand_host_ranges() {
+        let tests = [
+            HostRangeTest {
+                pattern: "web1.example.com",
+                hosts: vec!["web1.example.com"],
+            },
+            HostRangeTest {
+                pattern: "web[01:03].dc1.example.com",
+                hosts: vec![
+                    "web01.dc1.example.com",
+                    "web02.dc1.example.com",
+                    "web03.dc1.example.com",
+                ],
+            },
+            HostRangeTest {
+                pattern: "web[8:10]",
+                hosts: vec!["web8", "web9", "web10"],
+            },
+            HostRangeTest {
+                pattern: "web[:2]",
+                hosts: vec!["web0", "web1", "web2"],
+            },
+            HostRangeTest {
+                pattern: "web[1:10:3]",
+                hosts: vec!["web1", "web4", "web7", "web10"],
+            },
+            HostRangeTest {
+                pattern: "web[001:010:4]",
+                hosts: vec!["web001", "web005", "web009"],
+            },
+            HostRangeTest {
+                pattern: "db-[a:f]",
+                hosts: vec!["db-a", "db-b", "db-c", "db-d", "db-e", "db-f"],
+            },
+            HostRangeTest {
+                pattern: "db-[y:B:2]",
+                hosts: vec!["db-y", "db-A"],
+            },
+            HostRangeTest {
+                pattern: "rack[1:2]-node[a:b]",
+                hosts: vec!["rack1-nodea", "rack1-nodeb", "rack2-nodea", "rack2-nodeb"],
+            },
+            HostRangeTest {
+                pattern: "web[3:1]",
+                hosts: vec![],
+            },
+        ];
+
+        for test in tests {
+            assert_eq!(
+                expand_host_range(test.pattern).unwrap(),
+                test.hosts,
+                "{}",
+                test.pattern
+            );
+        }
+
+        for pattern in [
+            "web[1:]",
+            "web[01:100]",
+            "web[1:2:0]",
+            "web[1:2:3:4]",
+            "web[1:2",
+            "db-[f:a]",
+            "db-[a:3]",
+        ] {
+            assert!(expand_host_range(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn render_host_patterns() {
+        let pattern = HostPattern::new()
+            .union("webservers")
+            .union("dbservers")
+            .slice(0, Some(2))
+            .intersect("staging")
+            .exclude("web1")
+            .regex("^lb\\d+")
+            .retry_file("site.retry");
+        assert_eq!(
+            pattern.to_limit().unwrap(),
+            "webservers,dbservers[0:2],&staging,!web1,~^lb\\d+,@site.retry"
+        );
+        assert_eq!(
+            HostPattern::parse(&pattern.to_limit().unwrap()).unwrap(),
+            pattern
+        );
+
+        // ansible splits patterns holding a comma on commas only
+        let pattern = HostPattern::new().union("db").index(-1).exclude("fe80::1");
+        assert_eq!(pattern.to_limit().unwrap(), "db[-1],!fe80::1");
+        let pattern = HostPattern::new().union("web:db");
+        assert_eq!(pattern.to_limit().unwrap(), "web:db,");
+        assert_eq!(HostPattern::parse("web:db,").unwrap(), pattern);
+        assert_eq!(
+            HostPattern::new().union("fe80::1").to_limit().unwrap(),
+            "fe80::1"
+        );
+        assert!(HostPattern::new().regex("web{1,3}").to_limit().is_err());
+        assert!(HostPattern::new().regex("web").index(0).to_limit().is_err());
+        assert!(HostPattern::new().union("!web").to_limit().is_err());
+        assert!(HostPattern::new()
+            .exclude(HostPatternTarget::RetryFile("r".into()))
+            .to_limit()
+            .is_err());
+    }
+
+    #[test]
+    fn parse_host_patterns() {
+        let pattern = HostPattern::parse("web[1:]:&staging:!fe80::1").unwrap();
+        assert_eq!(
+            pattern.terms,
+            vec![
+                HostPatternTerm {
+                    operator: HostPatternOperator::Union,
+                    target: HostPatternTarget::Name("web".into()),
+                    subscript: Some(HostPatternSubscript::Slice(1, None)),
+                },
+                HostPatternTerm {
+                    operator: HostPatternOperator::Intersection,
+                    target: HostPatternTarget::Name("staging".into()),
+                    subscript: None,
+                },
+                HostPatternTerm {
+                    operator: HostPatternOperator::Exclusion,
+                    target: HostPatternTarget::Name("fe80".into()),
+                    subscript: None,
+                },
+                HostPatternTerm {
+                    operator: HostPatternOperator::Union,
+                    target: HostPatternTarget::Name("1".into()),
+                    subscript: None,
+                },
+            ]
+        );
+
+        // single addresses are not split on their colons
+        for address in [
+            "fe80::1",
+            "[fe80::1]:2222",
+            "web1:22",
+            "10.0.0.1:22",
+            "db[1:3]",
+        ] {
+            let pattern = HostPattern::parse(address).unwrap();
+            assert_eq!(pattern.terms.len(), 1, "{}", address);
+            assert_eq!(pattern.to_limit().unwrap(), address);
+        }
+        assert_eq!(
+            HostPattern::parse("db[01:03]").unwrap(),
+            HostPattern::new().union("db").slice(1, Some(3))
+        );
+
+        let pattern = HostPattern::parse("web[0-9]*, db").unwrap();
+        assert_eq!(pattern, HostPattern::new().union("web[0-9]*").union("db"));
+    }
+
+    struct HostPatternResolveTest {
+        pattern: &'static str,
+        hosts: Vec<&'static str>,
+    }
+
+    #[test]
+    fn resolve_host_patterns() {
+        let mut inventory = sample_inventory();
+        inventory.add_host_to_group("staging", "web2");
+        inventory.add_host_to_group("staging", "db1");
+
+        let tests = [
+            HostPatternResolveTest {
+                pattern: "web",
+                hosts: vec!["web1", "web2"],
+            },
+            HostPatternResolveTest {
+                pattern: "web:db",
+                hosts: vec!["web1", "web2", "db1"],
+            },
+            HostPatternResolveTest {
+                pattern: "app:&staging",
+                hosts: vec!["web2", "db1"],
+            },
+            HostPatternResolveTest {
+                pattern: "!web1:&staging:app",
+                hosts: vec!["web2", "db1"],
+            },
+            HostPatternResolveTest {
+                pattern: "app,!web",
+                hosts: vec!["db1"],
+            },
+            HostPatternResolveTest {
+                pattern: "!app",
+                hosts: vec!["bastion"],
+            },
+            HostPatternResolveTest {
+                pattern: "web*",
+                hosts: vec!["web1", "web2"],
+            },
+            HostPatternResolveTest {
+                pattern: "~(web|db)1",
+                hosts: vec!["web1", "db1"],
+            },
+            HostPatternResolveTest {
+                pattern: "app[1:]",
+                hosts: vec!["web2", "db1"],
+            },
+            HostPatternResolveTest {
+                pattern: "app[-1]",
+                hosts: vec!["db1"],
+            },
+            HostPatternResolveTest {
+                pattern: "all[0:1]",
+                hosts: vec!["bastion", "web1"],
+            },
+            HostPatternResolveTest {
+                pattern: "ungrouped:localhost",
+                hosts: vec!["bastion", "localhost"],
+            },
+            HostPatternResolveTest {
+                pattern: "missing",
+                hosts: vec![],
+            },
+        ];
+
+        for test in tests {
+            assert_eq!(
+                HostPattern::parse(test.pattern)
+                    .unwrap()
+                    .resolve(&inventory)
+                    .unwrap(),
+                test.hosts,
+                "{}",
+                test.pattern
+            );
+        }
+
+        assert_eq!(inventory.resolve_limit("").unwrap().len(), 4);
+        assert!(inventory.resolve_limit("~web(").is_err());
+
+        // a plain term naming a host selects it, even when a group is named alike
+        let mut shared = inventory.clone();
+        shared.add_host_to_group("legacy", "db");
+        for (limit, hosts) in [
+            ("db", vec!["db"]),
+            ("db,web1", vec!["db", "web1"]),
+            ("db[0]", vec!["db1"]),
+            ("db*", vec!["db1", "db"]),
+            ("all:!db", vec!["bastion", "web1", "web2", "db"]),
+        ] {
+            assert_eq!(shared.resolve_limit(limit).unwrap(), hosts, "{}", limit);
+        }
+
+        let path = std::env::temp_dir().join(format!(
+            "rs-ansible-test-{}.retry",
+            thread_rng().gen::<u64>()
+        ));
+        fs::write(&path, "db1\nweb2\n").unwrap();
+        let pattern = HostPattern::new()
+            .retry_file(path.to_str().unwrap())
+            .exclude("staging")
+            .index(0);
+        let hosts = pattern.resolve(&inventory);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(hosts.unwrap(), vec!["db1"]);
+    }
+
+    #[test]
+    fn render_script_inventory() {
+        let expected = json!({
+            "all": {
+                "children": ["ungrouped", "app"],
+                "vars": {"ntp_server": "ntp.example.com"}
+            },
+            "ungrouped": {"hosts": ["bastion"]},
+            "app": {"children": ["web", "db"]},
+            "db": {"hosts": ["db1"]},
+            "web": {"hosts": ["web1", "web2"], "vars": {"proxy": true}},
+            "_meta": {
+                "hostvars": {
+                    "bastion": {"ansible_host": "10.0.0.1"},
+                    "db1": {"motd": "hello world"},
+                    "web1": {"http_port": 8080},
+                    "web2": {}
+                }
+            }
+        });
+
+        assert_eq!(sample_inventory().to_script_value(), expected);
+    }
+
+    fn run_shim(shim: &DynamicInventoryShim, args: &[&str]) -> serde_json::Value {
+        let output = std::process::Command::new(&shim.path)
+            .args(args)
+            .output()
+            .expect("run inventory shim");
+        assert!(output.status.success(), "{:?}", output);
+        serde_json::from_slice(&output.stdout).expect("parse shim output")
+    }
+
+    #[test]
+    fn dynamic_inventory_file_transport() {
+        let inventory = DynamicInventory::new(|| Ok(sample_inventory()));
+        let shim = inventory.materialize().expect("materialize inventory");
+
+        assert_eq!(
+            run_shim(&shim, &["--list"]),
+            sample_inventory().to_script_value()
+        );
+        assert_eq!(
+            run_shim(&shim, &["--host", "web1"]),
+            json!({"http_port": 8080})
+        );
+        assert_eq!(run_shim(&shim, &["--host", "missing"]), json!({}));
+
+        let path = shim.path.clone();
+        drop(shim);
+        assert!(!path.exists());
+
+        let failing = DynamicInventory::new(|| Err("registry unavailable".into()));
+        let err = failing.materialize().unwrap_err();
+        assert!(err.to_string().contains("registry unavailable"));
+    }
+
+    #[test]
+    fn dynamic_inventory_socket_transport() {
+        if which::which("python3").is_err() {
+            return;
+        }
+
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let inventory = DynamicInventory {
+            transport: DynamicInventoryTransport::Socket,
+            ..DynamicInventory::new(move || {
+                let mut inventory = sample_inventory();
+                let call = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                inventory.set_host_var("web1", "call", json!(call));
+                Ok(inventory)
+            })
+        };
+        let shim = inventory.materialize().expect("materialize inventory");
+
+        let list = run_shim(&shim, &["--list"]);
+        assert_eq!(
+            list["web"],
+            json!({"hosts": ["web1", "web2"], "vars": {"proxy": true}})
+        );
+        assert_eq!(list["_meta"]["hostvars"]["web1"]["call"], json!(0));
+        assert_eq!(
+            run_shim(&shim, &["--host", "web1"]),
+            json!({"call": 1, "http_port": 8080})
+        );
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let path = shim.path.clone();
+        drop(shim);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn run_with_dynamic_inventory() {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create fake ansible dir");
+        let script = format!("{}/ansible-playbook", dir);
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$@\" > {dir}/args\n\"$2\" --list > {dir}/inventory\n",
+                dir = dir
+            ),
+        )
+        .expect("write fake ansible-playbook");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let playbook = AnsiblePlaybookCmd {
+            binary: script,
+            playbooks: vec!["site.yml".into()],
+            options: AnsiblePlaybookOptions {
+                inventory: vec![DynamicInventory::new(|| Ok(sample_inventory())).into()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut child = playbook.run().expect("run playbook");
+        assert!(child.wait().expect("wait for playbook").success());
+
+        let args = fs::read_to_string(format!("{}/args", dir)).expect("read args");
+        let inventory = fs::read_to_string(format!("{}/inventory", dir)).expect("read inventory");
+        fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+
+        let args: Vec<&str> = args.split_whitespace().collect();
+        assert_eq!(args[0], "--inventory");
+        assert_eq!(args[2], "site.yml");
+        let inventory: serde_json::Value =
+            serde_json::from_str(&inventory).expect("parse inventory");
+        assert_eq!(inventory, sample_inventory().to_script_value());
+        assert!(!Path::new(args[1]).exists());
+    }
+
+    #[test]
+    fn load_vars_directories() {
+        let dir =
+            std::env::temp_dir().join(format!("rs-ansible-test-{}", thread_rng().gen::<u64>()));
+        let files = [
+            ("group_vars/all.yml", "ntp_server: ntp2.example.com\n"),
+            ("group_vars/web/10-base.yml", "http_port: 80\ntls: false\n"),
+            ("group_vars/web/20-override.json", "{\"http_port\": 443}"),
+            ("group_vars/web/nested/extra.yaml", "nested: true\n"),
+            ("group_vars/web/.hidden.yml", "hidden: true\n"),
+            ("group_vars/web/backup.yml~", "backup: true\n"),
+            ("group_vars/web/notes.txt", "not: loaded\n"),
+            ("group_vars/web.yml", "shadowed: true\n"),
+            (
+                "group_vars/db.yml",
+                "$ANSIBLE_VAULT;1.1;AES256\n6338336231373066\n",
+            ),
+            ("group_vars/unknown.yml", "unknown: true\n"),
+            (
+                "host_vars/web1",
+                "ansible_host: 10.0.0.5\npassword: !vault |\n  $ANSIBLE_VAULT;1.1;AES256\n  3961\n",
+            ),
+            ("host_vars/missing.yml", "ansible_host: 10.0.0.9\n"),
+        ];
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).expect("create vars dir");
+            fs::write(path, content).expect("write vars file");
+        }
+
+        let mut inventory = sample_inventory();
+        let tree = inventory.load_vars(&dir);
+        let invalid_path = dir.join("invalid");
+        fs::create_dir_all(invalid_path.join("group_vars")).unwrap();
+        fs::write(invalid_path.join("group_vars/all.yml"), "- not a dict\n").unwrap();
+        let invalid = InventoryVarsTree::load(&invalid_path);
+        let empty = InventoryVarsTree::load(dir.join("missing"));
+        fs::remove_dir_all(&dir).expect("remove vars dir");
+        let tree = tree.expect("load vars");
+
+        assert_eq!(
+            tree.group_vars["web"],
+            [
+                ("http_port".to_string(), json!(443)),
+                ("nested".to_string(), json!(true)),
+                ("tls".to_string(), json!(false)),
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert_eq!(
+            tree.files
+                .iter()
+                .filter(|file| file.entity == "web")
+                .map(|file| file.path.strip_prefix(&dir).unwrap().to_str().unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                "group_vars/web/10-base.yml",
+                "group_vars/web/20-override.json",
+                "group_vars/web/nested/extra.yaml",
+            ]
+        );
+        assert_eq!(tree.encrypted_files(), vec![dir.join("group_vars/db.yml")]);
+        assert!(tree.group_vars["db"].is_empty());
+        assert_eq!(
+            tree.host_vars["web1"]["password"],
+            json!({"__ansible_vault": "$ANSIBLE_VAULT;1.1;AES256\n3961\n"})
+        );
+
+        assert_eq!(
+            inventory.groups["all"].vars["ntp_server"],
+            json!("ntp2.example.com")
+        );
+        assert_eq!(inventory.groups["web"].vars["proxy"], json!(true));
+        assert_eq!(inventory.groups["web"].vars["http_port"], json!(443));
+        assert_eq!(inventory.hosts["web1"].vars["http_port"], json!(8080));
+        assert_eq!(
+            inventory.hosts["web1"].vars["ansible_host"],
+            json!("10.0.0.5")
+        );
+        assert!(!inventory.groups.contains_key("unknown"));
+        assert!(!inventory.hosts.contains_key("missing"));
+
+        assert!(invalid.is_err());
+        assert_eq!(empty.unwrap(), InventoryVarsTree::default());
+    }
+
+    #[test]
+    fn validate_inventory() {
+        assert_eq!(sample_inventory().validate(), vec![]);
+
+        let content = "[web]
+web1 http_port=80
+web2
+
+[web-servers]
+web1 http_port=8080
+web2
+bad..host
+
+[app:children]
+web
+missing
+
+[db]
+web
+";
+        let mut inventory = Inventory::from_ini(content).expect("parse ini");
+        inventory.add_child_group("web", "app");
+        inventory.add_child_group("db", "db");
+
+        let issues = inventory.validate();
+        assert_eq!(
+            issues,
+            vec![
+                InventoryIssue::ConflictingHostVar(HostVarConflict {
+                    host: "web1".into(),
+                    var: "http_port".into(),
+                    previous: json!(80),
+                    value: json!(8080),
+                    line: 6,
+                }),
+                InventoryIssue::InvalidHostName("bad..host".into()),
+                InventoryIssue::UndefinedChildGroup {
+                    parent: "app".into(),
+                    child: "missing".into(),
+                },
+                InventoryIssue::GroupHostNameClash("web".into()),
+                InventoryIssue::InvalidGroupName {
+                    group: "web-servers".into(),
+                    safe_name: "web_servers".into(),
+                },
+                InventoryIssue::CyclicChildren(vec!["app".into(), "web".into(), "app".into()]),
+                InventoryIssue::CyclicChildren(vec!["db".into(), "db".into()]),
+            ]
+        );
+        assert_eq!(
+            issues[0].to_string(),
+            "host 'web1' is defined again with a conflicting value for 'http_port': 80 then 8080 (line 6)"
+        );
+        assert_eq!(issues.iter().filter(|issue| !issue.is_error()).count(), 2);
+
+        let yaml = "all:
+  children:
+    web:
+      hosts:
+        web1:
+          http_port: 80
+    lb:
+      hosts:
+        web1:
+          http_port: 8080
+";
+        let inventory = Inventory::from_yaml(yaml).expect("parse yaml");
+        assert_eq!(inventory.conflicts.len(), 1);
+        assert_eq!(inventory.conflicts[0].line, 9);
+    }
+
+    #[test]
+    fn validate_host_names() {
+        struct HostNameTest {
+            name: &'static str,
+            valid: bool,
+        }
+
+        let tests = vec![
+            HostNameTest {
+                name: "web1.example.com",
+                valid: true,
+            },
+            HostNameTest {
+                name: "web_01",
+                valid: true,
+            },
+            HostNameTest {
+                name: "10.0.0.1",
+                valid: true,
+            },
+            HostNameTest {
+                name: "fe80::1",
+                valid: true,
+            },
+            HostNameTest {
+                name: "web-",
+                valid: false,
+            },
+            HostNameTest {
+                name: "-web",
+                valid: false,
+            },
+            HostNameTest {
+                name: "web host",
+                valid: false,
+            },
+            HostNameTest {
+                name: "web1.",
+                valid: false,
+            },
+        ];
+
+        for test in tests {
+            assert_eq!(is_valid_host_name(test.name), test.valid, "{}", test.name);
+        }
+
+        assert_eq!(safe_group_name("1st-dc.web"), "_st_dc_web");
+        assert_eq!(safe_group_name("web_servers"), "web_servers");
+    }
+
+    #[test]
+    fn run_with_inventory_check() {
+        let mut inventory = sample_inventory();
+        inventory.add_child_group("app", "missing");
+
+        let playbook = AnsiblePlaybookCmd {
+            binary: "sh".into(),
+            playbooks: vec!["site.yml".into()],
+            options: AnsiblePlaybookOptions {
+                inventory: vec![inventory.into()],
+                ..Default::default()
+            },
+            inventory_check: InventoryCheck::Reject,
+            ..Default::default()
+        };
+
+        let err = playbook
+            .run()
+            .expect_err("reject invalid inventory")
+            .to_string();
+        assert!(err.contains("child group 'missing' of 'app'"));
+
+        let playbook = AnsiblePlaybookCmd {
+            inventory_check: InventoryCheck::Warn,
+            ..playbook
+        };
+        let mut child = playbook.run().expect("run with invalid inventory");
+        child.wait().expect("wait for playbook");
+        assert_eq!(
+            child.warnings,
+            vec!["child group 'missing' of 'app' is not defined"]
+        );
+
+        // declared groups may be empty
+        let inventory =
+            Inventory::from_ini("[db]\n\n[prod:children]\ndb\n").expect("parse inventory");
+        assert!(inventory.validate().is_empty());
+        let playbook = AnsiblePlaybookCmd {
+            options: AnsiblePlaybookOptions {
+                inventory: vec![inventory.into()],
+                ..Default::default()
+            },
+            inventory_check: InventoryCheck::Reject,
+            ..playbook
+        };
+        let mut child = playbook.run().expect("run with empty declared group");
+        child.wait().expect("wait for playbook");
+        assert!(child.warnings.is_empty());
+    }
+
+    #[test]
+    fn parse_script_output() {
+        let inventory = sample_inventory();
+        let parsed = Inventory::from_script_value(&inventory.to_script_value());
+        assert_eq!(parsed.unwrap(), inventory);
+
+        // output of `ansible-inventory --list`
+        let content = r#"{
+    "_meta": {
+        "hostvars": {
+            "db1": {"motd": "hello world"},
+            "web1": {"http_port": 8080}
+        }
+    },
+    "all": {"children": ["ungrouped", "app"]},
+    "app": {"children": ["db", "web"]},
+    "db": {"hosts": ["db1"]},
+    "web": {"hosts": ["web1"]},
+    "legacy": ["web1", "db1"]
+}"#;
+        let inventory = Inventory::from_script_json(content).expect("parse script output");
+        assert_eq!(inventory.group_hosts("app"), vec!["db1", "web1"]);
+        assert_eq!(inventory.group_hosts("legacy"), vec!["web1", "db1"]);
+        assert_eq!(inventory.hosts["web1"].vars["http_port"], json!(8080));
+        assert!(!inventory.groups.contains_key("all"));
+
+        assert_eq!(
+            Inventory::from_script_json("{\n\"web\": 1}")
+                .unwrap_err()
+                .line,
+            0
+        );
+        assert_eq!(Inventory::from_script_json("{\n,").unwrap_err().line, 2);
+    }
+
+    #[test]
+    fn render_graph() {
+        let inventory = sample_inventory();
+
+        let dot = inventory.to_dot(&InventoryGraphOptions::default());
+        assert_eq!(
+            dot,
+            r#"digraph inventory {
+  rankdir=LR;
+  n0 [label="all", shape=box];
+  n1 [label="ungrouped", shape=box];
+  n2 [label="app", shape=box];
+  n3 [label="db", shape=box];
+  n4 [label="web", shape=box];
+  n5 [label="bastion", shape=ellipse];
+  n6 [label="db1", shape=ellipse];
+  n7 [label="web1", shape=ellipse];
+  n8 [label="web2", shape=ellipse];
+  n0 -> n1;
+  n0 -> n2;
+  n2 -> n4;
+  n2 -> n3;
+  n1 -> n5;
+  n3 -> n6;
+  n4 -> n7;
+  n4 -> n8;
+}
+"#
+        );
+
+        let options = InventoryGraphOptions {
+            collapse_threshold: 1,
+            host_vars: true,
+        };
+        let mermaid = inventory.to_mermaid(&options);
+        assert_eq!(
+            mermaid,
+            r#"flowchart LR
+  n0["all"]
+  n1["ungrouped"]
+  n2["app"]
+  n3["db"]
+  n4["web"]
+  n5(["bastion<br/>ansible_host=10.0.0.1"])
+  n6(["db1<br/>motd=hello world"])
+  n7[/"2 hosts"/]
+  n0 --> n1
+  n0 --> n2
+  n2 --> n4
+  n2 --> n3
+  n1 --> n5
+  n3 --> n6
+  n4 --> n7
+"#
+        );
+
+        let mut quoted = Inventory::new();
+        quoted.set_host_var("web1", "banner", json!("say \"hi\" <b>"));
+        let options = InventoryGraphOptions {
+            host_vars: true,
+            ..Default::default()
+        };
+        assert!(quoted
+            .to_dot(&options)
+            .contains(r#"label="web1\nbanner=say \"hi\" <b>""#));
+        assert!(quoted
+            .to_mermaid(&options)
+            .contains("([\"web1<br/>banner=say #quot;hi#quot; #lt;b#gt;\"])"));
+    }
+
+    #[test]
+    fn diff_inventories() {
+        let before = sample_inventory();
+        assert!(before.diff(&before.clone()).is_empty());
+
+        let mut after = sample_inventory();
+        after.hosts.remove("web2");
+        after
+            .groups
+            .get_mut("web")
+            .unwrap()
+            .hosts
+            .retain(|host| host != "web2");
+        after.add_host_to_group("web", "web3");
+        after.add_host_to_group("lb", "lb1");
+        after.set_host_var("web1", "http_port", json!(8443));
+        after.set_host_var(
+            "web1",
+            "tls",
+            json!({"cert": "web1.pem", "ciphers": ["a", "b"]}),
+        );
+        after.set_host_var("db1", "motd", json!(null));
+        after.add_group("all").vars.clear();
+
+        let mut changed = before.clone();
+        changed.set_host_var(
+            "web1",
+            "tls",
+            json!({"cert": "old.pem", "ciphers": ["a", "c"]}),
+        );
+        let diff = changed.diff(&after);
+
+        assert_eq!(diff.added_hosts, vec!["lb1", "web3"]);
+        assert_eq!(diff.removed_hosts, vec!["web2"]);
+        assert_eq!(diff.added_groups, vec!["lb"]);
+        assert!(diff.removed_groups.is_empty());
+        assert_eq!(
+            diff.membership,
+            vec![
+                InventoryGroupChange {
+                    group: "lb".into(),
+                    added_hosts: vec!["lb1".into()],
+                    ..Default::default()
+                },
+                InventoryGroupChange {
+                    group: "web".into(),
+                    added_hosts: vec!["web3".into()],
+                    removed_hosts: vec!["web2".into()],
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(
+            diff.host_vars[1].changes,
+            vec![
+                InventoryVarChange {
+                    path: "http_port".into(),
+                    before: Some(json!(8080)),
+                    after: Some(json!(8443)),
+                },
+                InventoryVarChange {
+                    path: "tls.cert".into(),
+                    before: Some(json!("old.pem")),
+                    after: Some(json!("web1.pem")),
+                },
+                InventoryVarChange {
+                    path: "tls.ciphers[1]".into(),
+                    before: Some(json!("c")),
+                    after: Some(json!("b")),
+                },
+            ]
+        );
+
+        assert_eq!(
+            diff.to_string(),
+            "+ host lb1
++ host web3
+- host web2
++ group lb
+~ group lb: +host lb1
+~ group web: +host web3, -host web2
+~ host db1 vars:
+    ~ motd: \"hello world\" -> null
+~ host web1 vars:
+    ~ http_port: 8080 -> 8443
+    ~ tls.cert: \"old.pem\" -> \"web1.pem\"
+    ~ tls.ciphers[1]: \"c\" -> \"b\"
+~ group all vars:
+    - ntp_server: \"ntp.example.com\"
+"
+        );
+
+        let mut before = InventoryVars::new();
+        before.insert("users".into(), json!({"deploy.bot": 1, "root": 0}));
+        let mut after = InventoryVars::new();
+        after.insert("users".into(), json!({"deploy.bot": 2}));
+        after.insert("new".into(), json!([1]));
+        assert_eq!(
+            diff_vars(&before, &after)
+                .iter()
+                .map(|change| change.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "+ new: [1]",
+                "~ users[\"deploy.bot\"]: 1 -> 2",
+                "- users.root: 0",
+            ]
+        );
+    }
+
+    fn terraform_rules() -> Vec<TerraformRule> {
+        vec![
+            TerraformRule {
+                resource_type: "aws_instance".into(),
+                name_attribute: "tags.Name".into(),
+                groups: vec!["aws".into()],
+                group_by: vec![
+                    TerraformGroupBy {
+                        attribute: "tags.Role".into(),
+                        prefix: String::new(),
+                    },
+                    TerraformGroupBy {
+                        attribute: "availability_zone".into(),
+                        prefix: "az".into(),
+                    },
+                    TerraformGroupBy {
+                        attribute: "security_groups".into(),
+                        prefix: "sg".into(),
+                    },
+                ],
+                vars: [
+                    ("ansible_host".to_string(), "private_ip".to_string()),
+                    ("public_ip".to_string(), "public_ip".to_string()),
+                ]
+                .into_iter()
+                .collect(),
+            },
+            TerraformRule {
+                resource_type: "google_compute_instance".into(),
+                group_by: vec![TerraformGroupBy {
+                    attribute: "labels".into(),
+                    prefix: "label".into(),
+                }],
+                vars: [(
+                    "ansible_host".to_string(),
+                    "network_interface.0.network_ip".to_string(),
+                )]
+                .into_iter()
+                .collect(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn import_terraform_state() {
+        let terraform = TerraformInventory {
+            state: "tests/fixtures/terraform.tfstate".into(),
+            rules: terraform_rules(),
+        };
+        let inventory = terraform.load().expect("load terraform state");
+
+        assert_eq!(
+            inventory.hosts.keys().collect::<Vec<_>>(),
+            vec!["db_primary", "web-1", "web_1"]
+        );
+        assert_eq!(inventory.group_hosts("aws"), vec!["web-1", "web_1"]);
+        assert_eq!(inventory.group_hosts("web"), vec!["web-1", "web_1"]);
+        assert_eq!(inventory.group_hosts("az_eu_west_1a"), vec!["web-1"]);
+        assert_eq!(inventory.group_hosts("sg_ssh"), vec!["web-1"]);
+        assert_eq!(inventory.group_hosts("label_role_db"), vec!["db_primary"]);
+        assert_eq!(
+            inventory.hosts["web-1"].vars["ansible_host"],
+            json!("10.0.1.10")
+        );
+        assert_eq!(inventory.hosts["web_1"].vars["public_ip"], json!(null));
+        assert_eq!(
+            inventory.hosts["db_primary"].vars["ansible_host"],
+            json!("10.0.2.5")
+        );
+        assert_eq!(inventory.group_hosts("sg_http"), vec!["web-1", "web_1"]);
+
+        let shim = terraform.dynamic().materialize().expect("materialize");
+        let output = std::process::Command::new(&shim.path)
+            .arg("--list")
+            .output()
+            .expect("run inventory script");
+        let listed: serde_json::Value = serde_json::from_slice(&output.stdout).expect("parse list");
+        assert_eq!(listed, inventory.to_script_value());
+
+        let legacy = r#"{"version": 3, "modules": []}"#;
+        assert!(Inventory::from_terraform_state(legacy, &[]).is_err());
+        assert!(TerraformInventory {
+            state: "tests/fixtures/missing.tfstate".into(),
+            ..Default::default()
+        }
+        .load()
+        .is_err());
+    }
+
+    #[test]
+    fn import_ssh_config() {
+        let inventory =
+            Inventory::from_ssh_config_file("tests/fixtures/ssh/config").expect("read ssh config");
+
+        assert_eq!(
+            inventory.hosts.keys().collect::<Vec<_>>(),
+            vec!["bastion", "db1", "web1", "web2", "work-vm"]
+        );
+        assert_eq!(
+            json!(inventory.hosts["bastion"].vars),
+            json!({
+                "ansible_host": "bastion.example.com",
+                "ansible_port": 2222,
+                "ansible_ssh_private_key_file": "~/.ssh/id_ed25519",
+                "ansible_user": "admin"
+            })
+        );
+        assert_eq!(
+            json!(inventory.hosts["web2"].vars),
+            json!({
+                "ansible_host": "web2.internal.example.com",
+                "ansible_ssh_common_args": "-o ProxyJump=bastion",
+                "ansible_ssh_private_key_file": "~/.ssh/id_ed25519",
+                "ansible_user": "fallback"
+            })
+        );
+        assert_eq!(
+            inventory.hosts["db1"].vars["ansible_ssh_private_key_file"],
+            json!("~/.ssh/db key")
+        );
+        assert_eq!(
+            json!(inventory.hosts["work-vm"].vars),
+            json!({
+                "ansible_host": "192.168.56.10",
+                "ansible_port": 22,
+                "ansible_user": "vagrant"
+            })
+        );
+
+        let content = "Host app.example.com legacy.example.com
+    Port 2200
+
+Host *.example.com !legacy.example.com
+    User deploy
+
+Match all
+    User ignored
+
+Host *
+    User root
+";
+        let inventory = Inventory::from_ssh_config(content, ".").expect("parse ssh config");
+        assert_eq!(
+            inventory.hosts["app.example.com"].vars["ansible_user"],
+            json!("deploy")
+        );
+        assert_eq!(
+            inventory.hosts["legacy.example.com"].vars["ansible_user"],
+            json!("root")
+        );
+        assert_eq!(
+            inventory.warnings,
+            vec!["Skipping \"Match all\" block of ssh_config, Match blocks are not supported"]
+        );
+        assert!(Inventory::from_ssh_config_file("tests/fixtures/ssh/missing").is_err());
+
+        // options after an `Include` belong to the block holding it
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create ssh config dir");
+        fs::write(format!("{}/x", dir), "Host b\n    User v\n").expect("write include");
+        let inventory = Inventory::from_ssh_config("Host a\n    Include x\n    User u\n", &dir)
+            .expect("parse ssh config");
+        fs::remove_dir_all(&dir).expect("remove ssh config dir");
+        assert_eq!(inventory.hosts["a"].vars["ansible_user"], json!("u"));
+        assert_eq!(inventory.hosts["b"].vars["ansible_user"], json!("v"));
+    }
+
+    #[test]
+    fn evaluate_expressions() {
+        struct ExpressionTest {
+            expression: &'static str,
+            expected: serde_json::Value,
+        }
+
+        let vars: InventoryVars = serde_json::from_value(json!({
+            "distribution": "Ubuntu",
+            "version": "22.04",
+            "memory_mb": 4096,
+            "cpus": 4,
+            "tags": {"Role": "web", "Env": "prod"},
+            "interfaces": ["eth0", "eth1"],
+            "empty": ""
+        }))
+        .expect("vars");
+
+        let tests = vec![
+            ExpressionTest {
+                expression: "distribution | lower",
+                expected: json!("ubuntu"),
+            },
+            ExpressionTest {
+                expression: "'os_' ~ distribution ~ '_' ~ version.split('.')[0]",
+                expected: json!("os_Ubuntu_22"),
+            },
+            ExpressionTest {
+                expression: "memory_mb // 1024 + cpus * 2 % 3",
+                expected: json!(6),
+            },
+            ExpressionTest {
+                expression: "memory_mb >= 4096 and tags.Role == 'web'",
+                expected: json!(true),
+            },
+            ExpressionTest {
+                expression: "not (cpus > 8 or 'eth2' in interfaces)",
+                expected: json!(true),
+            },
+            ExpressionTest {
+                expression: "missing | default('none') | upper",
+                expected: json!("NONE"),
+            },
+            ExpressionTest {
+                expression: "empty | default('fallback', true)",
+                expected: json!("fallback"),
+            },
+            ExpressionTest {
+                expression: "missing is defined or tags['Env'] is match('pr')",
+                expected: json!(true),
+            },
+            ExpressionTest {
+                expression: "'large' if memory_mb > 8192 else 'small'",
+                expected: json!("small"),
+            },
+            ExpressionTest {
+                expression: "interfaces | join(',') | regex_replace('eth(\\\\d)', 'if\\\\1')",
+                expected: json!("if0,if1"),
+            },
+            ExpressionTest {
+                expression: "cpus is divisibleby 2 and interfaces | length == 2",
+                expected: json!(true),
+            },
+            ExpressionTest {
+                expression: "tags.keys() | sort | first",
+                expected: json!("Env"),
+            },
+            ExpressionTest {
+                expression: "version | float * 2",
+                expected: json!(44.08),
+            },
+            ExpressionTest {
+                expression: "[cpus, -1, 2] | max",
+                expected: json!(4),
+            },
+            ExpressionTest {
+                expression: "[-7 % 3, 7 % -3, 5 % 9223372036854775807]",
+                expected: json!([2, -2, 5]),
+            },
+            ExpressionTest {
+                expression: "(-9223372036854775807 - 1) % -1",
+                expected: json!(0),
+            },
+            ExpressionTest {
+                expression: "'ab' * cpus",
+                expected: json!("abababab"),
+            },
+        ];
+
+        for test in tests {
+            assert_eq!(
+                evaluate_expression(test.expression, &vars).expect(test.expression),
+                test.expected,
+                "{}",
+                test.expression
+            );
+        }
+
+        for expression in [
+            "missing",
+            "cpus +",
+            "cpus | nosuchfilter",
+            "'a' < 1",
+            "'a' * 9223372036854775807",
+        ] {
+            assert!(
+                evaluate_expression(expression, &vars).is_err(),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn construct_groups() {
+        let mut inventory = Inventory::new();
+        for (host, distribution, memory, role) in [
+            ("web1", "Ubuntu", 2048, Some("web")),
+            ("web2", "Debian", 8192, Some("web")),
+            ("db1", "Ubuntu", 16384, None),
+        ] {
+            inventory.add_host_to_group("linux", host);
+            inventory.set_host_var(host, "distribution", json!(distribution));
+            inventory.set_host_var(host, "memory_mb", json!(memory));
+            if let Some(role) = role {
+                inventory.set_host_var(host, "tags", json!({ "Role": role }));
+            }
+        }
+        inventory.set_group_var("linux", "site", json!("par1"));
+
+        let options = InventoryConstructedOptions {
+            compose: [
+                (
+                    "ansible_host".to_string(),
+                    "inventory_hostname ~ '.' ~ site".to_string(),
+                ),
+                ("memory_gb".to_string(), "memory_mb // 1024".to_string()),
+            ]
+            .into(),
+            groups: [
+                ("large".to_string(), "memory_gb >= 8".to_string()),
+                ("tagged".to_string(), "tags is defined".to_string()),
+            ]
+            .into(),
+            keyed_groups: vec![
+                InventoryKeyedGroup {
+                    key: "distribution | lower".into(),
+                    prefix: "os".into(),
+                    parent_group: "distributions".into(),
+                    ..Default::default()
+                },
+                InventoryKeyedGroup {
+                    key: "tags".into(),
+                    prefix: "tag".into(),
+                    ..Default::default()
+                },
+                InventoryKeyedGroup {
+                    key: "site".into(),
+                    leading_separator: false,
+                    ..Default::default()
+                },
+            ],
+            strict: false,
+        };
+        inventory.construct(&options).expect("construct inventory");
+
+        assert_eq!(
+            inventory.hosts["db1"].vars["ansible_host"],
+            json!("db1.par1")
+        );
+        assert_eq!(inventory.hosts["web1"].vars["memory_gb"], json!(2));
+        assert_eq!(inventory.group_hosts("large"), vec!["web2", "db1"]);
+        assert_eq!(inventory.group_hosts("tagged"), vec!["web1", "web2"]);
+        assert_eq!(inventory.group_hosts("os_ubuntu"), vec!["web1", "db1"]);
+        assert_eq!(inventory.group_hosts("tag_Role_web"), vec!["web1", "web2"]);
+        assert_eq!(inventory.group_hosts("par1").len(), 3);
+        assert_eq!(
+            inventory.group_hosts("distributions"),
+            vec!["web1", "db1", "web2"]
+        );
+
+        // the constructed inventory renders as any other one
+        let rendered = Inventory::from_ini(&inventory.to_ini()).expect("parse rendered inventory");
+        assert_eq!(rendered.group_hosts("os_debian"), vec!["web2"]);
+        assert_eq!(rendered.hosts["web2"].vars["memory_gb"], json!(8));
+
+        let strict = InventoryConstructedOptions {
+            keyed_groups: vec![InventoryKeyedGroup {
+                key: "tags.Role".into(),
+                prefix: "role".into(),
+                ..Default::default()
+            }],
+            strict: true,
+            ..Default::default()
+        };
+        assert!(inventory.clone().construct(&strict).is_err());
+    }
+
+    #[test]
+    fn construct_keyed_groups() {
+        struct KeyedGroupTest {
+            keyed: InventoryKeyedGroup,
+            expected: Vec<&'static str>,
+        }
+
+        let mut inventory = Inventory::new();
+        inventory.add_host_to_group("linux", "web1");
+        inventory.set_host_var("web1", "tags", json!({"Env": "", "Role": "web"}));
+        inventory.set_host_var("web1", "empty", json!(""));
+        inventory.set_host_var("web1", "zones", json!(["a", ""]));
+        inventory.set_host_var("web1", "fqdn", json!("web-1.example.com"));
+        inventory.set_host_var("web1", "memory_mb", json!(2048));
+
+        let keyed = |key: &str, prefix: &str| InventoryKeyedGroup {
+            key: key.into(),
+            prefix: prefix.into(),
+            ..Default::default()
+        };
+        let tests = vec![
+            KeyedGroupTest {
+                keyed: keyed("tags", "tag"),
+                expected: vec!["tag_Env_", "tag_Role_web"],
+            },
+            KeyedGroupTest {
+                keyed: InventoryKeyedGroup {
+                    default_value: Some("none".into()),
+                    ..keyed("tags", "tag")
+                },
+                expected: vec!["tag_Env_none", "tag_Role_web"],
+            },
+            KeyedGroupTest {
+                keyed: InventoryKeyedGroup {
+                    trailing_separator: false,
+                    ..keyed("tags", "tag")
+                },
+                expected: vec!["tag_Env", "tag_Role_web"],
+            },
+            KeyedGroupTest {
+                keyed: keyed("empty", "env"),
+                expected: vec!["env_"],
+            },
+            KeyedGroupTest {
+                keyed: InventoryKeyedGroup {
+                    trailing_separator: false,
+                    ..keyed("empty", "env")
+                },
+                expected: vec!["env_"],
+            },
+            KeyedGroupTest {
+                keyed: InventoryKeyedGroup {
+                    default_value: Some("none".into()),
+                    ..keyed("zones", "zone")
+                },
+                expected: vec!["zone_a", "zone_none"],
+            },
+            // names are kept as written
+            KeyedGroupTest {
+                keyed: keyed("fqdn", "host"),
+                expected: vec!["host_web-1.example.com"],
+            },
+            KeyedGroupTest {
+                keyed: keyed("missing | default(None)", "none"),
+                expected: vec![],
+            },
+        ];
+
+        for test in tests {
+            let mut constructed = inventory.clone();
+            let options = InventoryConstructedOptions {
+                keyed_groups: vec![test.keyed.clone()],
+                strict: true,
+                ..Default::default()
+            };
+            constructed
+                .construct(&options)
+                .expect("construct inventory");
+
+            let mut expected = vec!["all", "linux"];
+            expected.extend(test.expected);
+            expected.sort();
+            assert_eq!(
+                constructed.host_groups("web1"),
+                expected,
+                "{:?}",
+                test.keyed
+            );
+        }
+
+        for keyed in [
+            keyed("memory_mb", "memory"),
+            InventoryKeyedGroup {
+                default_value: Some("none".into()),
+                trailing_separator: false,
+                ..keyed("tags", "tag")
+            },
+        ] {
+            let options = InventoryConstructedOptions {
+                keyed_groups: vec![keyed.clone()],
+                strict: true,
+                ..Default::default()
+            };
+            assert!(
+                inventory.clone().construct(&options).is_err(),
+                "{:?}",
+                keyed
+            );
+        }
+    }
+
+    #[test]
+    fn run_with_inventory_sources() {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create fake ansible dir");
+        let script = format!("{}/ansible-playbook", dir);
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$@\" > {dir}/args\ncat \"$6\" > {dir}/inventory\n\"$8\" --list > {dir}/dynamic\n",
+                dir = dir
+            ),
+        )
+        .expect("write fake ansible-playbook");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let mut dynamic = Inventory::new();
+        dynamic.add_host_to_group("cache", "redis1");
+        let playbook = AnsiblePlaybookCmd {
+            binary: script,
+            playbooks: vec!["site.yml".into()],
+            options: AnsiblePlaybookOptions {
+                inventory: vec![
+                    "inventories/prod".into(),
+                    InventorySource::Hosts(vec!["web9".into()]),
+                    sample_inventory().into(),
+                    DynamicInventory::new(move || Ok(dynamic.clone())).into(),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // in-memory and dynamic sources are only written out when running
+        assert_eq!(
+            playbook.command().expect_err("generate command").to_string(),
+            "(playbook::gen_opts) in-memory and dynamic inventories are only written out when the command runs"
+        );
+
+        let mut child = playbook.run().expect("run playbook");
+        assert!(child.wait().expect("wait for playbook").success());
+
+        let args = fs::read_to_string(format!("{}/args", dir)).expect("read args");
+        let inventory = fs::read_to_string(format!("{}/inventory", dir)).expect("read inventory");
+        let dynamic = fs::read_to_string(format!("{}/dynamic", dir)).expect("read dynamic");
+        fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+
+        let args: Vec<&str> = args.split_whitespace().collect();
+        assert_eq!(
+            [args[0], args[1], args[2], args[3], args[4], args[6], args[8]],
+            [
+                "--inventory",
+                "inventories/prod",
+                "--inventory",
+                "web9,",
+                "--inventory",
+                "--inventory",
+                "site.yml"
+            ]
+        );
+        assert_eq!(
+            Inventory::from_yaml(&inventory).expect("parse inventory"),
+            Inventory::from_yaml(&sample_inventory().to_yaml().expect("render yaml"))
+                .expect("parse sample")
+        );
+        assert!(dynamic.contains("redis1"));
+        assert!(!Path::new(args[5]).exists());
+        assert!(!Path::new(args[7]).exists());
+    }
+
+    #[test]
+    fn merge_sources() {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(format!("{}/prod/group_vars", dir)).expect("create inventory dir");
+        fs::write(
+            format!("{}/base.ini", dir),
+            "[web]\nweb1 http_port=80\n\n[web:vars]\nproxy=false\n",
+        )
+        .expect("write base inventory");
+        fs::write(
+            format!("{}/prod/10-hosts.yml", dir),
+            "web:\n  hosts:\n    web1:\n      http_port: 8080\n    web2:\n",
+        )
+        .expect("write prod inventory");
+        fs::write(format!("{}/prod/20-db.ini", dir), "[db]\ndb1\n").expect("write db inventory");
+        fs::write(
+            format!("{}/prod/30-web.ini", dir),
+            "[web]\nweb1 http_port=8081\n",
+        )
+        .expect("write web inventory");
+        // vars directories are also read next to inventory files
+        fs::create_dir_all(format!("{}/host_vars", dir)).expect("create host vars dir");
+        fs::write(format!("{}/host_vars/web1.yml", dir), "rack: a1\n").expect("write host vars");
+        fs::write(format!("{}/prod/hosts.retry", dir), "ignored\n").expect("write retry file");
+        fs::write(
+            format!("{}/prod/README.md", dir),
+            "# Production\n\nHosts of the production site.\n",
+        )
+        .expect("write readme");
+        fs::write(format!("{}/prod/20-db.ini.bak", dir), "[db]\nold-db1\n").expect("write backup");
+        fs::write(
+            format!("{}/prod/group_vars/web.yml", dir),
+            "proxy: true\ntls:\n  port: 443\n",
+        )
+        .expect("write group vars");
+
+        let mut model = Inventory::new();
+        model.set_group_var("web", "tls", json!({"port": 8443}));
+
+        let merged = merge_inventory_sources(&[
+            format!("{}/base.ini", dir).into(),
+            format!("{}/prod", dir).into(),
+            "bastion,".into(),
+            model.into(),
+        ])
+        .expect("merge sources");
+        assert!(merge_inventory_sources(&[format!("{}/missing.ini", dir).into()]).is_err());
+        fs::remove_dir_all(&dir).expect("remove inventory dir");
+
+        let inventory = merged.inventory;
+        assert_eq!(inventory.group_hosts("web"), vec!["web1", "web2"]);
+        assert_eq!(inventory.group_hosts("db"), vec!["db1"]);
+        assert_eq!(inventory.ungrouped_hosts(), vec!["bastion"]);
+        assert_eq!(inventory.hosts["web1"].vars["http_port"], json!(8081));
+        assert_eq!(inventory.hosts["web1"].vars["rack"], json!("a1"));
+        assert_eq!(inventory.groups["web"].vars["tls"], json!({"port": 8443}));
+
+        let conflicts: Vec<String> = merged
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.to_string())
+            .collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                // files of a directory source disagreeing are reported too
+                format!("host 'web1' has conflicting values for 'http_port': 8080 in '{dir}/prod/10-hosts.yml' is overridden by 8081 in '{dir}/prod/30-web.ini'", dir = dir),
+                format!("host 'web1' has conflicting values for 'http_port': 80 in '{dir}/base.ini' is overridden by 8081 in '{dir}/prod'", dir = dir),
+                format!("group 'web' has conflicting values for 'proxy': \"false\" in '{dir}/base.ini' is overridden by true in '{dir}/prod'", dir = dir),
+                format!("group 'web' has conflicting values for 'tls': {{\"port\":443}} in '{dir}/prod' is overridden by {{\"port\":8443}} in 'inventory model #3'", dir = dir),
+            ]
+        );
+        assert_eq!(
+            merged.conflicts[3].changes,
+            vec![InventoryVarChange {
+                path: "tls.port".into(),
+                before: Some(json!(443)),
+                after: Some(json!(8443)),
+            }]
+        );
+    }
+}
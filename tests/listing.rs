@@ -0,0 +1,266 @@
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+    use rs_ansible::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn fixture(name: &str) -> String {
+        fs::read_to_string(format!("tests/fixtures/listing/{}", name)).expect("read fixture")
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    fn task(role: &str, name: &str, tags: &[&str]) -> ListedTask {
+        ListedTask {
+            name: name.into(),
+            role: role.into(),
+            tags: strings(tags),
+        }
+    }
+
+    #[test]
+    fn parse_list_hosts() {
+        let listing = parse_playbook_listing(&fixture("list_hosts.txt")).expect("parse listing");
+
+        assert_eq!(
+            listing.plays,
+            vec![
+                ListedPlay {
+                    playbook: "site.yml".into(),
+                    number: 1,
+                    name: "Gather facts".into(),
+                    host_list: "all".into(),
+                    tags: strings(&["base"]),
+                    pattern: strings(&["all"]),
+                    hosts: strings(&["web3", "db1", "web1", "web2"]),
+                    ..Default::default()
+                },
+                ListedPlay {
+                    playbook: "site.yml".into(),
+                    number: 2,
+                    name: "Deploy web servers".into(),
+                    host_list: "web:!web2".into(),
+                    tags: strings(&["deploy"]),
+                    pattern: strings(&["web:!web2"]),
+                    hosts: strings(&["web3", "web1"]),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(
+            listing.hosts().into_iter().collect::<Vec<_>>(),
+            vec!["db1", "web1", "web2", "web3"]
+        );
+        assert!(listing.tags().is_empty());
+    }
+
+    #[test]
+    fn parse_list_tasks() {
+        let listing = parse_playbook_listing(&fixture("list_tasks.txt")).expect("parse listing");
+
+        assert_eq!(listing.plays.len(), 2);
+        assert!(listing.plays.iter().all(|play| play.hosts.is_empty()));
+        assert_eq!(
+            listing.plays[0].tasks,
+            vec![task("", "Collect facts", &["base", "facts"])]
+        );
+        assert_eq!(
+            listing.plays[1].tasks,
+            vec![
+                task("", "Wait for connection", &["deploy"]),
+                task(
+                    "common",
+                    "Install common packages",
+                    &["deploy", "packages", "web"]
+                ),
+                task("web", "Install nginx", &["deploy", "web"]),
+                task("web", "Start nginx", &["deploy", "service", "web"]),
+                task("", "Write configuration", &["config", "deploy", "files"]),
+                task("", "Include system tasks", &["config", "deploy"]),
+                task(
+                    "common",
+                    "Install common packages",
+                    &["again", "deploy", "packages"]
+                ),
+                task("", "ansible.builtin.ping", &["deploy"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_list_tags() {
+        let listing = parse_playbook_listing(&fixture("list_tags.txt")).expect("parse listing");
+
+        assert_eq!(listing.plays.len(), 2);
+        assert!(listing.plays.iter().all(|play| play.tasks.is_empty()));
+        assert_eq!(listing.plays[0].task_tags, strings(&["base", "facts"]));
+        assert_eq!(
+            listing.tags().into_iter().collect::<Vec<_>>(),
+            vec![
+                "again", "base", "config", "deploy", "facts", "files", "packages", "service", "web"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_combined_listing() {
+        let listing = parse_playbook_listing(&fixture("list_all.txt")).expect("parse listing");
+
+        assert_eq!(listing.playbooks(), vec!["deploy.yml", "check.yml"]);
+        assert_eq!(
+            listing.plays[0],
+            ListedPlay {
+                playbook: "deploy.yml".into(),
+                number: 1,
+                name: "Update servers".into(),
+                host_list: "web,db".into(),
+                tags: strings(&["update", "weekly"]),
+                pattern: strings(&["web", "db"]),
+                hosts: strings(&["web1", "db1"]),
+                tasks: vec![
+                    task("", "Update packages", &["update", "weekly"]),
+                    task(
+                        "",
+                        "ansible.builtin.reboot",
+                        &["reboot", "update", "weekly"]
+                    ),
+                ],
+                task_tags: strings(&["reboot", "update", "weekly"]),
+            }
+        );
+        assert_eq!(
+            listing.plays[1],
+            ListedPlay {
+                playbook: "check.yml".into(),
+                number: 1,
+                name: "Check templated hosts".into(),
+                host_list: "{{ target }}".into(),
+                pattern: strings(&["{{ target }}"]),
+                tasks: vec![task("", "Check uptime", &[])],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_invalid_listings() {
+        struct ParseListingTest {
+            output: &'static str,
+            expected: &'static str,
+        }
+
+        let tests = vec![
+            ParseListingTest {
+                output: "\nplaybook: site.yml\n\n    tasks:\n",
+                expected: "line 4: expected a play: 'tasks:'",
+            },
+            ParseListingTest {
+                output: "playbook: site.yml\n  play #1 (all): all\tTAGS: []\n    hosts (2):\n      web1\n",
+                expected: "play #1 lists 1 hosts out of 2",
+            },
+            ParseListingTest {
+                output: "playbook: site.yml\n  play #1 (all): all\tTAGS: []\n    hosts (1):\n      web1\n  play #2 (db): db\tTAGS: []\n    hosts (3):\n      db1\n    tasks:\n",
+                expected: "line 8: play #2 lists 1 hosts out of 3: 'tasks:'",
+            },
+            ParseListingTest {
+                output: "playbook: site.yml\n  play #1 (all): all\tTAGS: []\n    tasks:\n      Untagged task\n",
+                expected: "line 4: expected a task: 'Untagged task'",
+            },
+            ParseListingTest {
+                output: "playbook: site.yml\n  play #1 (all): all\tTAGS: []\n    something else\n",
+                expected: "line 3: unexpected line: 'something else'",
+            },
+        ];
+
+        for test in tests {
+            match parse_playbook_listing(test.output) {
+                Ok(listing) => panic!("expected an error, got {:?}", listing),
+                Err(err) => assert_eq!(
+                    err.to_string(),
+                    format!("(listing::parse_playbook_listing) {}", test.expected)
+                ),
+            }
+        }
+
+        assert_eq!(
+            parse_playbook_listing("").expect("parse empty listing"),
+            PlaybookListing::default()
+        );
+    }
+
+    #[test]
+    fn list_playbooks() {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create fake ansible dir");
+        let script = format!("{}/ansible-playbook", dir);
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$@\" > {dir}/args\ncat {fixtures}/list_all.txt\n",
+                dir = dir,
+                fixtures = fs::canonicalize("tests/fixtures/listing")
+                    .expect("canonicalize")
+                    .display()
+            ),
+        )
+        .expect("write fake ansible-playbook");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        struct ListPlaybooksTest {
+            options: AnsiblePlaybookOptions,
+            expected: &'static str,
+        }
+
+        let tests = vec![
+            ListPlaybooksTest {
+                options: AnsiblePlaybookOptions::default(),
+                expected: "--list-hosts --list-tags --list-tasks deploy.yml",
+            },
+            ListPlaybooksTest {
+                options: AnsiblePlaybookOptions {
+                    list_tags: true,
+                    tags: "update".into(),
+                    ..Default::default()
+                },
+                expected: "--list-tags --tags update deploy.yml",
+            },
+        ];
+
+        for test in tests {
+            let cmd = AnsiblePlaybookCmd {
+                binary: script.clone(),
+                playbooks: vec!["deploy.yml".into()],
+                options: test.options,
+                ..Default::default()
+            };
+            let listing = cmd.list_playbooks().expect("list playbooks");
+            assert_eq!(listing.plays.len(), 2);
+
+            let args = fs::read_to_string(format!("{}/args", dir)).expect("read args");
+            assert_eq!(args.trim(), test.expected);
+        }
+
+        fs::write(
+            &script,
+            "#!/bin/sh\necho 'ERROR! the playbook could not be found' >&2\nexit 1\n",
+        )
+        .expect("write failing ansible-playbook");
+        let cmd = AnsiblePlaybookCmd {
+            binary: script.clone(),
+            playbooks: vec!["missing.yml".into()],
+            ..Default::default()
+        };
+        match cmd.list_playbooks() {
+            Ok(listing) => panic!("expected an error, got {:?}", listing),
+            Err(err) => assert_eq!(
+                err.to_string(),
+                "(listing::list_playbooks) ERROR! the playbook could not be found"
+            ),
+        }
+
+        fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+    }
+}
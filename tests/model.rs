@@ -0,0 +1,280 @@
+#[cfg(test)]
+mod tests {
+    use rs_ansible::*;
+    use serde_json::{json, Map, Value};
+
+    fn yaml_value(content: &str) -> Value {
+        serde_yaml::from_str(content).expect("parse yaml")
+    }
+
+    #[test]
+    fn parse_playbook() {
+        let playbook = Playbook::from_file("tests/sites/site2.yaml").expect("parse playbook");
+        assert_eq!(playbook.plays.len(), 2);
+        assert_eq!(playbook.plays[0].import_playbook, "site1.yaml");
+
+        let play = &playbook.plays[1];
+        assert_eq!(play.hosts, "web,!web9");
+        assert_eq!(play.do_become, Some(true));
+        assert_eq!(play.keywords["serial"], json!(2));
+        assert_eq!(play.vars_files, vec!["vars/common.yml"]);
+
+        assert_eq!(
+            play.roles,
+            vec![
+                RoleRef {
+                    role: "common".into(),
+                    ..Default::default()
+                },
+                RoleRef {
+                    role: "nginx".into(),
+                    tags: vec!["web".into(), "proxy".into()],
+                    vars: Map::from_iter([("worker_processes".to_string(), json!(4))]),
+                    ..Default::default()
+                },
+                RoleRef {
+                    role: "app".into(),
+                    when: vec!["deploy_app | bool".into()],
+                    keywords: Map::from_iter([("app_port".to_string(), json!(9000))]),
+                    ..Default::default()
+                },
+            ]
+        );
+
+        let uptime = match &play.tasks[0] {
+            TaskEntry::Task(task) => task,
+            entry => panic!("expected a task, got {:?}", entry),
+        };
+        assert_eq!(uptime.module, "command");
+        assert_eq!(uptime.args["_raw_params"], json!("uptime"));
+        assert_eq!(uptime.register, "uptime");
+        assert_eq!(uptime.keywords["changed_when"], json!(false));
+
+        let block = match &play.tasks[1] {
+            TaskEntry::Block(block) => block,
+            entry => panic!("expected a block, got {:?}", entry),
+        };
+        assert_eq!(block.block.len(), 2);
+        assert_eq!(block.when, vec!["deploy_app | bool", "http_port > 1024"]);
+        assert_eq!(block.tags, vec!["deploy"]);
+        assert_eq!(
+            block.block[1],
+            TaskEntry::Task(Task {
+                name: "Install packages".into(),
+                module: "ansible.builtin.package".into(),
+                args: Map::from_iter([
+                    ("name".to_string(), json!("{{ item }}")),
+                    ("state".to_string(), json!("present")),
+                ]),
+                loop_items: Some(json!("{{ packages }}")),
+                tags: vec!["packages".into()],
+                ..Default::default()
+            })
+        );
+        match &block.always[0] {
+            TaskEntry::Task(task) => {
+                assert_eq!(task.module, "ansible.builtin.ping");
+                assert!(task.args.is_empty());
+            }
+            entry => panic!("expected a task, got {:?}", entry),
+        }
+
+        assert_eq!(play.handlers[0].task.name, "restart app");
+        assert_eq!(play.handlers[0].listen, vec!["restart services"]);
+    }
+
+    #[test]
+    fn playbook_round_trip() {
+        struct RoundTripTest {
+            path: &'static str,
+            expected: Option<Value>, // normalized content, when it differs from the file
+        }
+
+        let tests = vec![
+            RoundTripTest {
+                path: "tests/sites/site1.yaml",
+                expected: None,
+            },
+            RoundTripTest {
+                path: "tests/sites/site2.yaml",
+                expected: Some({
+                    let mut expected = yaml_value(
+                        &std::fs::read_to_string("tests/sites/site2.yaml").expect("read playbook"),
+                    );
+                    let play = &mut expected[1];
+                    play["hosts"] = json!("web,!web9");
+                    play["become"] = json!(true);
+                    play["roles"][1]["tags"] = json!(["web", "proxy"]);
+                    play["tasks"][1]["block"][1]["tags"] = json!(["packages"]);
+                    play["tasks"][1]["block"][0]["notify"] = json!(["restart app"]);
+                    play["tasks"][1]["tags"] = json!(["deploy"]);
+                    play["handlers"][0]["listen"] = json!(["restart services"]);
+                    expected
+                }),
+            },
+        ];
+
+        for test in tests {
+            let content = std::fs::read_to_string(test.path).expect("read playbook");
+            let playbook = Playbook::from_yaml(&content).expect("parse playbook");
+            let rendered = playbook.to_yaml().expect("render playbook");
+
+            assert_eq!(
+                yaml_value(&rendered),
+                test.expected.unwrap_or_else(|| yaml_value(&content)),
+                "{}",
+                test.path
+            );
+            assert_eq!(
+                Playbook::from_yaml(&rendered).expect("parse rendered playbook"),
+                playbook
+            );
+        }
+    }
+
+    #[test]
+    fn build_playbook() {
+        let playbook = Playbook {
+            plays: vec![Play {
+                name: "Bootstrap".into(),
+                hosts: "all".into(),
+                gather_facts: Some(false),
+                tasks: vec![TaskEntry::Task(Task {
+                    name: "Run bootstrap".into(),
+                    module: "ansible.builtin.shell".into(),
+                    args: Map::from_iter([(
+                        "_raw_params".to_string(),
+                        json!("/opt/bootstrap.sh --quiet"),
+                    )]),
+                    when: vec!["bootstrap | default(true)".into()],
+                    do_become: Some(true),
+                    ..Default::default()
+                })],
+                ..Default::default()
+            }],
+        };
+
+        assert_eq!(
+            playbook.to_yaml().expect("render playbook"),
+            "---
+- name: Bootstrap
+  hosts: all
+  gather_facts: false
+  tasks:
+  - name: Run bootstrap
+    ansible.builtin.shell: /opt/bootstrap.sh --quiet
+    when: bootstrap | default(true)
+    become: true
+"
+        );
+    }
+
+    #[test]
+    fn templated_booleans() {
+        let content = "- hosts: all
+  become: \"{{ use_become }}\"
+  gather_facts: \"{{ gather }}\"
+  tasks:
+    - name: Restart service
+      ansible.builtin.service:
+        name: app
+        state: restarted
+      become: \"{{ restart_as_root | bool }}\"
+";
+        let playbook = Playbook::from_yaml(content).expect("parse playbook");
+        let play = &playbook.plays[0];
+        assert_eq!(play.do_become, None);
+        assert_eq!(play.gather_facts, None);
+        assert_eq!(play.keywords["become"], json!("{{ use_become }}"));
+        assert_eq!(play.keywords["gather_facts"], json!("{{ gather }}"));
+        match &play.tasks[0] {
+            TaskEntry::Task(task) => {
+                assert_eq!(task.module, "ansible.builtin.service");
+                assert_eq!(
+                    task.keywords["become"],
+                    json!("{{ restart_as_root | bool }}")
+                );
+            }
+            entry => panic!("expected a task, got {:?}", entry),
+        }
+
+        let rendered = playbook.to_yaml().expect("render playbook");
+        assert_eq!(yaml_value(&rendered), yaml_value(content));
+        assert_eq!(
+            Playbook::from_yaml(&rendered).expect("parse rendered playbook"),
+            playbook
+        );
+    }
+
+    #[test]
+    fn tagged_values() {
+        let content = "- hosts: all
+  vars:
+    db_password: !vault |
+      $ANSIBLE_VAULT;1.1;AES256
+      62313365396662343061393464336163383764373764613633653634306231386433626436623361
+  tasks:
+    - name: Print a template
+      ansible.builtin.debug:
+        msg: !unsafe '{{ not_templated }}'
+";
+        let playbook = Playbook::from_yaml(content).expect("parse playbook");
+        let play = &playbook.plays[0];
+        assert_eq!(
+            play.vars["db_password"],
+            json!({ VAULT_KEY: "$ANSIBLE_VAULT;1.1;AES256\n62313365396662343061393464336163383764373764613633653634306231386433626436623361\n" })
+        );
+        match &play.tasks[0] {
+            TaskEntry::Task(task) => {
+                assert_eq!(
+                    task.args["msg"],
+                    json!({ UNSAFE_KEY: "{{ not_templated }}" })
+                )
+            }
+            entry => panic!("expected a task, got {:?}", entry),
+        }
+
+        // the tags are written back
+        let rendered = playbook.to_yaml().expect("render playbook");
+        assert_eq!(
+            serde_yaml::from_str::<serde_yaml::Value>(&rendered).expect("parse rendered yaml"),
+            serde_yaml::from_str::<serde_yaml::Value>(content).expect("parse yaml")
+        );
+        assert_eq!(
+            Playbook::from_yaml(&rendered).expect("parse rendered playbook"),
+            playbook
+        );
+    }
+
+    #[test]
+    fn parse_invalid_playbooks() {
+        let tests = vec![
+            ("hosts: all", "expected a list of plays"),
+            (
+                "- hosts: all\n  tasks:\n    - name: nothing\n",
+                "tasks[0]: no module found in task",
+            ),
+            (
+                "- hosts: all\n  tasks:\n    - copy: {}\n      template: {}\n",
+                "conflicting modules: copy, template",
+            ),
+            (
+                "- hosts: all\n  become: maybe\n",
+                "'become': expected a boolean",
+            ),
+            (
+                "- hosts: all\n  roles:\n    - {tags: x}\n",
+                "roles[0]: no role name found",
+            ),
+        ];
+
+        for (content, expected) in tests {
+            let err = Playbook::from_yaml(content).expect_err(content);
+            assert!(err.to_string().contains(expected), "{}", err);
+        }
+        assert_eq!(
+            Playbook::from_yaml("").expect("parse empty playbook").plays,
+            vec![]
+        );
+    }
+}
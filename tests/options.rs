@@ -7,6 +7,7 @@ mod tests {
         let options = AnsibleConnectionOptions {
             ask_pass: true,
             connection: "local".into(),
+            connection_password_file: "connection-password-file".into(),
             private_key: "pk".into(),
             scp_extra_args: "scp-extra-args".into(),
             sftp_extra_args: "sftp-extra-args".into(),
@@ -20,6 +21,8 @@ mod tests {
             "--ask-pass",
             "--connection",
             "local",
+            "--connection-password-file",
+            "connection-password-file",
             "--private-key",
             "pk",
             "--scp-extra-args",
@@ -47,6 +50,7 @@ mod tests {
         let options = AnsibleConnectionOptions {
             ask_pass: true,
             connection: "local".into(),
+            connection_password_file: "connection-password-file".into(),
             private_key: "pk".into(),
             scp_extra_args: "scp-extra-args".into(),
             sftp_extra_args: "sftp-extra-args".into(),
@@ -56,7 +60,7 @@ mod tests {
             user: "user".into(),
         };
 
-        let expected = " --ask-pass --connection local --private-key pk --scp-extra-args scp-extra-args --sftp-extra-args sftp-extra-args --ssh-common-args ssh-common-args --ssh-extra-args ssh-extra-args --timeout 10 --user user";
+        let expected = " --ask-pass --connection local --connection-password-file connection-password-file --private-key pk --scp-extra-args scp-extra-args --sftp-extra-args sftp-extra-args --ssh-common-args ssh-common-args --ssh-extra-args ssh-extra-args --timeout 10 --user user";
 
         assert_eq!(options.to_string(), expected);
     }
@@ -66,6 +70,7 @@ mod tests {
         let options = AnsiblePrivilegeEscalationOptions {
             do_become: true,
             become_method: "become-method".into(),
+            become_password_file: "become-password-file".into(),
             become_user: "become-user".into(),
             ask_become_pass: true,
         };
@@ -75,6 +80,8 @@ mod tests {
             "--become",
             "--become-method",
             "become-method",
+            "--become-password-file",
+            "become-password-file",
             "--become-user",
             "become-user",
         ];
@@ -90,12 +97,13 @@ mod tests {
         let options = AnsiblePrivilegeEscalationOptions {
             do_become: true,
             become_method: "become-method".into(),
+            become_password_file: "become-password-file".into(),
             become_user: "become-user".into(),
             ask_become_pass: true,
         };
 
         let expected =
-            " --ask-become-pass --become --become-method become-method --become-user become-user";
+            " --ask-become-pass --become --become-method become-method --become-password-file become-password-file --become-user become-user";
 
         assert_eq!(options.to_string(), expected);
     }
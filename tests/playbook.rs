@@ -1,7 +1,37 @@
 #[cfg(test)]
 mod tests {
+    use rand::{thread_rng, Rng};
     use rs_ansible::*;
     use serde_json::json;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    // fake ansible-playbook recording its arguments, the playbooks given to
+    // it along with their directory listing, and exiting with `status`
+    fn fake_playbook(dir: &str, status: i32) -> String {
+        let script = format!("{}/ansible-playbook", dir);
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh
+echo \"$@\" > {dir}/args
+for playbook in \"$@\"; do
+    cat \"$playbook\" >> {dir}/playbooks
+    (cd \"$(dirname \"$playbook\")\" && find . -type f | sort) >> {dir}/files
+done
+exit {status}
+",
+                dir = dir,
+                status = status
+            ),
+        )
+        .expect("write fake ansible-playbook");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        script
+    }
 
     #[test]
     fn generate_connection_options() {
@@ -68,7 +98,7 @@ mod tests {
                     }),
                     extra_vars_file: vec!["@test.yml".into()],
                     flush_cache: true,
-                    inventory: "inventory".into(),
+                    inventory: vec!["inventory".into()],
                     limit: "limit".into(),
                     list_hosts: true,
                     list_tags: true,
@@ -133,7 +163,7 @@ mod tests {
                 verbose: true,
                 version: true,
 
-                inventory: "test/ansible/inventory/all".into(),
+                inventory: vec!["test/ansible/inventory/all".into()],
                 limit: "myhost".into(),
                 extra_vars: json!({
                     "var1": "value1",
@@ -201,4 +231,211 @@ mod tests {
             _ => panic!("generate AnsiblePlaybookCmd command"),
         }
     }
+
+    #[test]
+    fn resolve_limit_hosts() {
+        let mut inventory = Inventory::new();
+        for host in ["web01", "web02", "web03"] {
+            inventory.add_host_to_group("web", host);
+        }
+        inventory.add_host("lb");
+
+        // a trailing `[..]` is a subscript of the matched hosts, not a range
+        let options = AnsiblePlaybookOptions {
+            limit: "web[0:1],lb".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.limit_hosts(&inventory).unwrap(),
+            vec!["web01", "web02", "lb"]
+        );
+
+        let options = AnsiblePlaybookOptions {
+            limit: "web[-1]:&web[1:]".into(),
+            ..Default::default()
+        };
+        assert_eq!(options.limit_hosts(&inventory).unwrap(), vec!["web03"]);
+
+        let options = AnsiblePlaybookOptions {
+            ..Default::default()
+        };
+        assert_eq!(options.limit_hosts(&inventory).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn run_in_memory_playbooks() {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create fake ansible dir");
+
+        let playbook = Playbook {
+            plays: vec![Play {
+                hosts: "all".into(),
+                tasks: vec![TaskEntry::Task(Task {
+                    module: "ansible.builtin.ping".into(),
+                    ..Default::default()
+                })],
+                ..Default::default()
+            }],
+        };
+        let files = PlaybookFiles {
+            files: BTreeMap::from([
+                (
+                    "site.yml".to_string(),
+                    b"- hosts: all\n  roles: [web]\n".to_vec(),
+                ),
+                (
+                    "roles/web/tasks/main.yml".to_string(),
+                    b"- ansible.builtin.template: {src: index.html.j2, dest: /var/www/index.html}\n"
+                        .to_vec(),
+                ),
+                (
+                    "roles/web/templates/index.html.j2".to_string(),
+                    b"{{ inventory_hostname }}\n".to_vec(),
+                ),
+            ]),
+            playbook: "site.yml".into(),
+        };
+        let cmd = AnsiblePlaybookCmd {
+            binary: fake_playbook(&dir, 0),
+            playbooks: vec![
+                PlaybookSource::Yaml("- hosts: localhost\n".into()),
+                playbook.clone().into(),
+                files.into(),
+            ],
+            ..Default::default()
+        };
+
+        // in-memory playbooks have no path until the command runs
+        assert_eq!(
+            cmd.command().expect_err("generate command").to_string(),
+            "(playbook::command) in-memory playbooks are only written out when the command runs"
+        );
+
+        let mut child = cmd.run().expect("run playbook");
+        assert!(child.wait().expect("wait for playbook").success());
+
+        let args = fs::read_to_string(format!("{}/args", dir)).expect("read args");
+        let playbooks = fs::read_to_string(format!("{}/playbooks", dir)).expect("read playbooks");
+        let files = fs::read_to_string(format!("{}/files", dir)).expect("read files");
+        fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+
+        let args: Vec<&str> = args.split_whitespace().collect();
+        assert_eq!(args.len(), 3);
+        assert!(args[2].ends_with("/2/site.yml"));
+        assert_eq!(
+            playbooks,
+            format!(
+                "- hosts: localhost\n{}- hosts: all\n  roles: [web]\n",
+                playbook.to_yaml().expect("render playbook")
+            )
+        );
+        assert_eq!(
+            files,
+            "./playbook.yml\n./playbook.yml\n./roles/web/tasks/main.yml\n./roles/web/templates/index.html.j2\n./site.yml\n"
+        );
+
+        // the directory is private and removed once the run is over
+        let root = Path::new(args[0])
+            .parent()
+            .and_then(Path::parent)
+            .expect("playbook directory");
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn keep_playbooks_on_failure() {
+        struct KeepOnFailureTest {
+            status: i32,
+            keep_on_failure: bool,
+            kept: bool,
+        }
+
+        let tests = vec![
+            KeepOnFailureTest {
+                status: 2,
+                keep_on_failure: true,
+                kept: true,
+            },
+            KeepOnFailureTest {
+                status: 0,
+                keep_on_failure: true,
+                kept: false,
+            },
+            KeepOnFailureTest {
+                status: 2,
+                keep_on_failure: false,
+                kept: false,
+            },
+        ];
+
+        for test in tests {
+            let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+            fs::create_dir_all(&dir).expect("create fake ansible dir");
+            let cmd = AnsiblePlaybookCmd {
+                binary: fake_playbook(&dir, test.status),
+                playbooks: vec![PlaybookSource::Yaml("- hosts: all\n".into())],
+                keep_on_failure: test.keep_on_failure,
+                ..Default::default()
+            };
+
+            let mut child = cmd.run().expect("run playbook");
+            let status = child.wait().expect("wait for playbook");
+            assert_eq!(status.code(), Some(test.status));
+
+            let args = fs::read_to_string(format!("{}/args", dir)).expect("read args");
+            fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+
+            let playbook = Path::new(args.trim());
+            assert_eq!(playbook.exists(), test.kept);
+            let root = playbook.parent().and_then(Path::parent).expect("root");
+            let warnings = if test.keep_on_failure {
+                vec![format!(
+                    "The in-memory playbooks are written in {}, which is kept if the run fails",
+                    root.display()
+                )]
+            } else {
+                vec![]
+            };
+            assert_eq!(child.warnings, warnings);
+            if test.kept {
+                let mode = fs::metadata(playbook.parent().and_then(Path::parent).expect("root"))
+                    .expect("stat playbook directory")
+                    .permissions()
+                    .mode();
+                assert_eq!(mode & 0o777, 0o700);
+                fs::remove_dir_all(playbook.parent().and_then(Path::parent).expect("root"))
+                    .expect("remove kept directory");
+            }
+        }
+    }
+
+    #[test]
+    fn reject_invalid_playbook_files() {
+        let tests = vec![
+            (
+                PlaybookFiles {
+                    files: BTreeMap::from([("../escape.yml".to_string(), vec![])]),
+                    playbook: "../escape.yml".into(),
+                },
+                "is not a path within the playbook directory",
+            ),
+            (
+                PlaybookFiles {
+                    files: BTreeMap::from([("site.yml".to_string(), vec![])]),
+                    playbook: "missing.yml".into(),
+                },
+                "playbook 'missing.yml' is not one of the files",
+            ),
+        ];
+
+        for (files, expected) in tests {
+            let cmd = AnsiblePlaybookCmd {
+                binary: "sh".into(),
+                playbooks: vec![files.into()],
+                ..Default::default()
+            };
+            let err = cmd.run().expect_err("reject playbook files");
+            assert!(err.to_string().contains(expected), "{}", err);
+        }
+    }
 }
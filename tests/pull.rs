@@ -0,0 +1,217 @@
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+    use rs_ansible::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use std::process::Command;
+
+    /// Create a local git repository holding a single `local.yml` playbook
+    /// and return its path
+    fn local_repository() -> String {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create repository dir");
+        fs::write(
+            format!("{}/local.yml", dir),
+            "---\n- hosts: localhost\n  gather_facts: false\n  tasks:\n    - ansible.builtin.debug:\n        msg: pulled\n",
+        )
+        .expect("write playbook");
+
+        for args in [
+            vec!["init", "--quiet"],
+            vec!["add", "local.yml"],
+            vec![
+                "-c",
+                "user.name=rs-ansible",
+                "-c",
+                "user.email=rs-ansible@localhost",
+                "commit",
+                "--quiet",
+                "-m",
+                "init",
+            ],
+        ] {
+            let status = Command::new("git")
+                .args(&args)
+                .current_dir(&dir)
+                .status()
+                .expect("run git");
+            assert!(status.success(), "git {:?}", args);
+        }
+
+        dir
+    }
+
+    #[test]
+    fn generate_command() {
+        let cmd = AnsiblePullCmd {
+            playbooks: vec!["local.yml".into()],
+            pull_options: AnsiblePullOptions {
+                accept_host_key: true,
+                checkout: "main".into(),
+                clean: true,
+                directory: "/var/lib/ansible/local".into(),
+                only_if_changed: true,
+                purge: true,
+                sleep: "30".into(),
+                url: "https://example.com/playbooks.git".into(),
+            },
+            options: AnsiblePlaybookOptions {
+                inventory: vec!["localhost,".into()],
+                limit: "localhost".into(),
+                tags: "deploy".into(),
+                verbose_v: true,
+                ..Default::default()
+            },
+            connection_options: AnsibleConnectionOptions {
+                connection: "local".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let expected = vec![
+            "ansible-pull",
+            "--accept-host-key",
+            "--checkout",
+            "main",
+            "--clean",
+            "--directory",
+            "/var/lib/ansible/local",
+            "--only-if-changed",
+            "--purge",
+            "--sleep",
+            "30",
+            "--url",
+            "https://example.com/playbooks.git",
+            "--inventory",
+            "localhost,",
+            "--limit",
+            "localhost",
+            "--tags",
+            "deploy",
+            "-v",
+            "--connection",
+            "local",
+            "local.yml",
+        ];
+
+        match cmd.command() {
+            Ok(res) => assert_eq!(res, expected),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn should_fail_without_url() {
+        let cmd = AnsiblePullCmd {
+            playbooks: vec!["local.yml".into()],
+            ..Default::default()
+        };
+
+        assert!(cmd.command().is_err());
+    }
+
+    #[test]
+    fn should_fail_with_unsupported_playbook_options() {
+        let cmd = AnsiblePullCmd {
+            pull_options: AnsiblePullOptions {
+                url: "https://example.com/playbooks.git".into(),
+                ..Default::default()
+            },
+            options: AnsiblePlaybookOptions {
+                forks: "10".into(),
+                step: true,
+                inventory: vec![Inventory::new().into()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        match cmd.command() {
+            Err(err) => assert!(err.to_string().contains("forks, inventory, step")),
+            _ => panic!("Should return Err"),
+        }
+    }
+
+    /// Fake ansible-pull recording its arguments and checking the repository
+    /// out in its `--directory`, as ansible-pull does before running the
+    /// playbooks
+    fn fake_pull(dir: &str) -> String {
+        let script = format!("{}/ansible-pull", dir);
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh
+echo \"$@\" > {dir}/args
+while [ $# -gt 0 ]; do
+    case \"$1\" in
+        --url) url=\"$2\"; shift ;;
+        --directory) directory=\"$2\"; shift ;;
+    esac
+    shift
+done
+git clone --quiet \"$url\" \"$directory\" || exit 1
+",
+                dir = dir
+            ),
+        )
+        .expect("write fake ansible-pull");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        script
+    }
+
+    #[test]
+    fn pull_from_local_repository() {
+        let repository = local_repository();
+        let checkout = format!("{}-checkout", repository);
+        let fake = format!("{}-bin", repository);
+        fs::create_dir_all(&fake).expect("create fake ansible-pull dir");
+
+        let cmd = AnsiblePullCmd {
+            binary: fake_pull(&fake),
+            playbooks: vec!["local.yml".into()],
+            pull_options: AnsiblePullOptions {
+                directory: checkout.clone(),
+                url: format!("file://{}", repository),
+                ..Default::default()
+            },
+            options: AnsiblePlaybookOptions {
+                inventory: vec!["localhost,".into()],
+                ..Default::default()
+            },
+            connection_options: AnsibleConnectionOptions {
+                connection: "local".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let output = cmd
+            .run()
+            .expect("run ansible-pull")
+            .wait_with_output()
+            .expect("wait for ansible-pull");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let args = fs::read_to_string(format!("{}/args", fake)).expect("read args");
+        assert_eq!(
+            args.trim(),
+            format!(
+                "--directory {} --url file://{} --inventory localhost, --connection local local.yml",
+                checkout, repository
+            )
+        );
+        assert!(Path::new(&format!("{}/local.yml", checkout)).exists());
+
+        fs::remove_dir_all(&repository).expect("remove repository");
+        fs::remove_dir_all(&checkout).expect("remove checkout");
+        fs::remove_dir_all(&fake).expect("remove fake ansible-pull dir");
+    }
+}
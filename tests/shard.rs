@@ -0,0 +1,235 @@
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+    use rs_ansible::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    // 12 hosts spread over three racks, all of them being linux hosts
+    fn fleet() -> Inventory {
+        let mut inventory = Inventory::new();
+        for index in 1..=12 {
+            let host = format!("host{}", index);
+            let rack = ["rack_a", "rack_b", "rack_c"][(index - 1) / 4];
+            inventory.add_host_to_group(rack, &host);
+            inventory.add_host_to_group("linux", &host);
+        }
+
+        inventory
+    }
+
+    #[test]
+    fn shard_consistent_hash() {
+        let mut inventory = Inventory::new();
+        for index in 0..100 {
+            inventory.add_host(&format!("node{:03}", index));
+        }
+        let options = InventoryShardOptions {
+            shards: 4,
+            ..Default::default()
+        };
+
+        let shards = inventory.shard(&options).expect("shard inventory");
+        assert_eq!(shards.len(), 4);
+        assert_eq!(shards.iter().map(|shard| shard.len()).sum::<usize>(), 100);
+        assert!(shards.iter().all(|shard| shard.len() > 10));
+
+        // hosts keep their shard when another one is added
+        inventory.add_host("node100");
+        let grown = inventory.shard(&options).expect("shard inventory");
+        for (shard, hosts) in shards.iter().enumerate() {
+            assert!(hosts.iter().all(|host| grown[shard].contains(host)));
+        }
+
+        assert!(inventory
+            .shard(&InventoryShardOptions {
+                shards: 0,
+                ..Default::default()
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn shard_round_robin() {
+        struct ShardTest {
+            options: InventoryShardOptions,
+            expected: Vec<Vec<&'static str>>,
+        }
+
+        let tests = vec![
+            ShardTest {
+                options: InventoryShardOptions {
+                    shards: 4,
+                    strategy: InventoryShardStrategy::RoundRobin,
+                    pattern: "rack_a:rack_b".into(),
+                    ..Default::default()
+                },
+                expected: vec![
+                    vec!["host1", "host5"],
+                    vec!["host2", "host6"],
+                    vec!["host3", "host7"],
+                    vec!["host4", "host8"],
+                ],
+            },
+            ShardTest {
+                options: InventoryShardOptions {
+                    shards: 3,
+                    strategy: InventoryShardStrategy::RoundRobin,
+                    pattern: "linux".into(),
+                    keep_groups_together: true,
+                },
+                expected: vec![
+                    vec!["host1", "host2", "host3", "host4"],
+                    vec!["host5", "host6", "host7", "host8"],
+                    vec!["host9", "host10", "host11", "host12"],
+                ],
+            },
+            ShardTest {
+                options: InventoryShardOptions {
+                    shards: 3,
+                    strategy: InventoryShardStrategy::RoundRobin,
+                    pattern: "rack_a".into(),
+                    ..Default::default()
+                },
+                expected: vec![vec!["host1", "host4"], vec!["host2"], vec!["host3"]],
+            },
+            // hosts of `all` are taken in definition order
+            ShardTest {
+                options: InventoryShardOptions {
+                    shards: 4,
+                    strategy: InventoryShardStrategy::RoundRobin,
+                    pattern: "all".into(),
+                    ..Default::default()
+                },
+                expected: vec![
+                    vec!["host1", "host5", "host9"],
+                    vec!["host2", "host6", "host10"],
+                    vec!["host3", "host7", "host11"],
+                    vec!["host4", "host8", "host12"],
+                ],
+            },
+        ];
+
+        for test in tests {
+            assert_eq!(
+                fleet().shard(&test.options).expect("shard inventory"),
+                test.expected
+            );
+        }
+    }
+
+    #[test]
+    fn shard_keep_groups_together() {
+        let shards = fleet()
+            .shard(&InventoryShardOptions {
+                shards: 8,
+                keep_groups_together: true,
+                ..Default::default()
+            })
+            .expect("shard inventory");
+
+        for rack in ["host1", "host5", "host9"] {
+            let shard = shards
+                .iter()
+                .find(|shard| shard.iter().any(|host| host == rack))
+                .expect("rack shard");
+            assert_eq!(shard.len() % 4, 0);
+        }
+    }
+
+    #[test]
+    fn parse_recap() {
+        let output = "PLAY [all] *****
+
+TASK [ping] *****
+ok: [web1]
+
+PLAY RECAP *****
+web1                       : ok=2    changed=1    unreachable=0    failed=0    skipped=3    rescued=0    ignored=1
+\x1b[0;31mdb1\x1b[0m                        : \x1b[0;32mok=1   \x1b[0m changed=0    \x1b[1;31munreachable=1   \x1b[0m failed=0    skipped=0    rescued=0    ignored=0
+";
+        let recap = parse_play_recap(output);
+
+        assert_eq!(
+            recap["web1"],
+            PlaybookHostRecap {
+                ok: 2,
+                changed: 1,
+                skipped: 3,
+                ignored: 1,
+                ..Default::default()
+            }
+        );
+        assert!(recap["db1"].is_failed());
+        assert_eq!(recap.len(), 2);
+    }
+
+    #[test]
+    fn run_sharded_playbook() {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create fake ansible dir");
+        let script = format!("{}/ansible-playbook", dir);
+        fs::write(
+            &script,
+            "#!/bin/sh
+while [ $# -gt 0 ]; do
+    if [ \"$1\" = \"--limit\" ]; then limit=\"$2\"; fi
+    shift
+done
+echo 'PLAY RECAP *****'
+status=0
+for host in $(echo \"$limit\" | tr ',' ' '); do
+    if [ \"$host\" = host3 ]; then
+        echo \"$host : ok=1 changed=0 unreachable=0 failed=1 skipped=0 rescued=0 ignored=0\"
+        status=2
+    else
+        echo \"$host : ok=2 changed=1 unreachable=0 failed=0 skipped=0 rescued=0 ignored=0\"
+    fi
+done
+exit $status
+",
+        )
+        .expect("write fake ansible-playbook");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let playbook = AnsiblePlaybookCmd {
+            binary: script,
+            playbooks: vec!["site.yml".into()],
+            options: AnsiblePlaybookOptions {
+                limit: "!rack_c".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let commands = playbook
+            .shard(
+                &fleet(),
+                &InventoryShardOptions {
+                    shards: 3,
+                    strategy: InventoryShardStrategy::RoundRobin,
+                    ..Default::default()
+                },
+            )
+            .expect("shard playbook");
+        assert_eq!(
+            commands
+                .iter()
+                .map(|cmd| cmd.options.limit.as_str())
+                .collect::<Vec<_>>(),
+            vec!["host1,host4,host7", "host2,host5,host8", "host3,host6"]
+        );
+
+        let summary = run_shards(&commands, 2);
+        fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+
+        assert_eq!(summary.shards.len(), 3);
+        assert_eq!(summary.hosts.len(), 8);
+        assert_eq!(summary.failed_hosts(), vec!["host3"]);
+        assert!(!summary.success());
+        assert!(summary.shards[0].success());
+        assert!(!summary.shards[2].success());
+        assert!(summary
+            .to_string()
+            .starts_with("shard 0: 3 hosts, exit code 0\nshard 1: 3 hosts, exit code 0\nshard 2: 2 hosts, exit code 2\nPLAY RECAP\n"));
+    }
+}
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+    use rs_ansible::*;
+    use serde_json::json;
+    use std::fs;
+
+    fn vars(pairs: &[(&str, serde_json::Value)]) -> InventoryVars {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    }
+
+    fn definition(
+        layer: VariableLayer,
+        source: &str,
+        value: serde_json::Value,
+    ) -> VariableDefinition {
+        VariableDefinition {
+            layer,
+            source: source.into(),
+            value,
+        }
+    }
+
+    #[test]
+    fn resolve_precedence() {
+        let dir =
+            std::env::temp_dir().join(format!("rs-ansible-test-{}", thread_rng().gen::<u64>()));
+        fs::create_dir_all(&dir).expect("create vars dir");
+        let play_vars_file = dir.join("play.yml");
+        fs::write(&play_vars_file, "answer: 1\n").expect("write play vars");
+        let extra_vars_file = dir.join("extra.json");
+        fs::write(&extra_vars_file, "{\"answer\": 43}").expect("write extra vars");
+
+        let mut inventory = Inventory::new();
+        inventory.add_host_to_group("web", "web1");
+        inventory.add_child_group("app", "web");
+        inventory.set_group_var("web", "http_port", json!(80));
+        inventory.set_group_var("app", "region", json!("eu"));
+        inventory.set_group_var("all", "region", json!("us"));
+        inventory.set_group_var("all", "ntp_server", json!("a"));
+        inventory.set_host_var("web1", "http_port", json!(8080));
+
+        let mut inventory_vars = InventoryVarsTree::default();
+        inventory_vars
+            .group_vars
+            .insert("all".into(), vars(&[("ntp_server", json!("b"))]));
+        inventory_vars
+            .group_vars
+            .insert("web".into(), vars(&[("tls", json!(true))]));
+        inventory_vars
+            .host_vars
+            .insert("web1".into(), vars(&[("tls", json!(false))]));
+        let mut playbook_vars = InventoryVarsTree::default();
+        playbook_vars
+            .group_vars
+            .insert("all".into(), vars(&[("ntp_server", json!("c"))]));
+
+        let options = AnsiblePlaybookOptions {
+            extra_vars: json!({"answer": 42}),
+            extra_vars_file: vec![
+                format!("@{}", extra_vars_file.display()),
+                "color=blue".into(),
+            ],
+            ..Default::default()
+        };
+        let resolver = VariableResolver {
+            inventory,
+            inventory_vars,
+            playbook_vars,
+            play_vars: vars(&[("tls", json!("play"))]),
+            play_vars_files: vec![play_vars_file.to_string_lossy().to_string()],
+            ..VariableResolver::from_options(&options)
+        };
+
+        let resolved = resolver.resolve_host("web1");
+        let missing_file = VariableResolver {
+            play_vars_files: vec![dir.join("missing.yml").to_string_lossy().to_string()],
+            ..resolver.clone()
+        }
+        .resolve_host("web1");
+        fs::remove_dir_all(&dir).expect("remove vars dir");
+        let resolved = resolved.expect("resolve variables");
+
+        assert_eq!(resolved["region"].value, json!("eu"));
+        assert_eq!(
+            resolved["region"].shadowed,
+            vec![definition(
+                VariableLayer::InventoryGroupVars,
+                "all",
+                json!("us")
+            )]
+        );
+
+        assert_eq!(
+            resolved["ntp_server"].winner,
+            definition(VariableLayer::PlaybookGroupVarsAll, "all", json!("c"))
+        );
+        assert_eq!(
+            resolved["ntp_server"].shadowed,
+            vec![
+                definition(VariableLayer::InventoryGroupVarsAll, "all", json!("b")),
+                definition(VariableLayer::InventoryGroupVars, "all", json!("a")),
+            ]
+        );
+
+        assert_eq!(
+            resolved["http_port"].winner,
+            definition(VariableLayer::InventoryHostVars, "web1", json!(8080))
+        );
+
+        assert_eq!(
+            resolved["tls"].winner,
+            definition(VariableLayer::PlayVars, "vars", json!("play"))
+        );
+        assert_eq!(
+            resolved["tls"]
+                .shadowed
+                .iter()
+                .map(|definition| definition.layer)
+                .collect::<Vec<_>>(),
+            vec![
+                VariableLayer::InventoryHostVarsHosts,
+                VariableLayer::InventoryGroupVarsGroups
+            ]
+        );
+
+        assert_eq!(resolved["answer"].value, json!(43));
+        assert_eq!(
+            resolved["answer"]
+                .shadowed
+                .iter()
+                .map(|definition| (definition.layer, definition.value.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (VariableLayer::ExtraVars, json!(42)),
+                (VariableLayer::PlayVarsFiles, json!(1)),
+            ]
+        );
+        assert_eq!(resolved["color"].value, json!("blue"));
+
+        assert_eq!(
+            resolved["http_port"].to_string(),
+            "http_port = 8080 (inventory file or script host vars: web1)\n  shadows 80 (inventory file or script group vars: web)"
+        );
+
+        assert!(missing_file.is_err());
+        assert!(resolver.resolve_host("missing").is_err());
+        assert!(!resolved.contains_key("undefined"));
+    }
+
+    #[test]
+    fn resolve_group_priority() {
+        let mut inventory = Inventory::new();
+        inventory.add_host_to_group("alpha", "host1");
+        inventory.add_host_to_group("beta", "host1");
+        inventory.set_group_var("alpha", "color", json!("red"));
+        inventory.set_group_var("beta", "color", json!("blue"));
+
+        let resolver = VariableResolver {
+            inventory: inventory.clone(),
+            ..Default::default()
+        };
+        let color = resolver.resolve("host1", "color").unwrap().unwrap();
+        assert_eq!(color.value, json!("blue"));
+        assert_eq!(color.shadowed[0].source, "alpha");
+
+        inventory.set_group_var("alpha", "ansible_group_priority", json!(10));
+        let resolver = VariableResolver {
+            inventory,
+            ..Default::default()
+        };
+        let color = resolver.resolve("host1", "color").unwrap().unwrap();
+        assert_eq!(color.value, json!("red"));
+    }
+}
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+    use rs_ansible::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    const VERSION_2_15: &str = "ansible-playbook [core 2.15.5]
+  config file = /etc/ansible/ansible.cfg
+  configured module search path = ['/root/.ansible/plugins/modules', '/usr/share/ansible/plugins/modules']
+  ansible python module location = /usr/lib/python3/dist-packages/ansible
+  ansible collection location = /root/.ansible/collections:/usr/share/ansible/collections
+  executable location = /usr/bin/ansible-playbook
+  python version = 3.11.2 (main, Mar 13 2023, 12:18:29) [GCC 12.2.0] (/usr/bin/python3)
+  jinja version = 3.1.2
+  libyaml = True
+";
+
+    const VERSION_2_11: &str = "ansible-playbook [core 2.11.12]
+  config file = None
+  configured module search path = ['/home/user/.ansible/plugins/modules']
+  ansible python module location = /usr/lib/python3/dist-packages/ansible
+  ansible collection location = /home/user/.ansible/collections
+  executable location = /usr/bin/ansible-playbook
+  python version = 3.8.10 (default, Nov 22 2023, 10:22:35) [GCC 9.4.0]
+  jinja version = 2.10.1
+  libyaml = True
+";
+
+    /// Create a fake `ansible-playbook` printing `version`, which appends a
+    /// line to `<dir>/calls` on every execution, and return its directory
+    fn fake_ansible_dir(version: &str) -> String {
+        let dir = format!("/tmp/rs-ansible-test-{}", thread_rng().gen::<u64>());
+        fs::create_dir_all(&dir).expect("create fake ansible dir");
+
+        let script = format!("{}/ansible-playbook", dir);
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> {}/calls\ncat <<'EOF'\n{}EOF\n",
+                dir, version
+            ),
+        )
+        .expect("write fake ansible-playbook");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        dir
+    }
+
+    #[test]
+    fn parse_core_version() {
+        let expected = AnsibleVersion {
+            core_version: "2.15.5".into(),
+            config_file: Some("/etc/ansible/ansible.cfg".into()),
+            module_search_path: vec![
+                "/root/.ansible/plugins/modules".into(),
+                "/usr/share/ansible/plugins/modules".into(),
+            ],
+            module_location: "/usr/lib/python3/dist-packages/ansible".into(),
+            collection_location: vec![
+                "/root/.ansible/collections".into(),
+                "/usr/share/ansible/collections".into(),
+            ],
+            executable_location: "/usr/bin/ansible-playbook".into(),
+            python_version: "3.11.2".into(),
+            python_path: "/usr/bin/python3".into(),
+            jinja_version: "3.1.2".into(),
+        };
+
+        match parse_version(VERSION_2_15) {
+            Ok(version) => {
+                assert_eq!(version, expected);
+                assert_eq!(version.core(), (2, 15, 5));
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn parse_older_versions() {
+        match parse_version(VERSION_2_11) {
+            Ok(version) => {
+                assert_eq!(version.core_version, "2.11.12");
+                assert_eq!(version.config_file, None);
+                assert_eq!(version.python_version, "3.8.10");
+                assert_eq!(version.python_path, "");
+            }
+            Err(err) => panic!("{}", err),
+        }
+
+        match parse_version("ansible-playbook 2.9.27\n  config file = None\n") {
+            Ok(version) => assert_eq!(version.core(), (2, 9, 27)),
+            Err(err) => panic!("{}", err),
+        }
+
+        assert!(parse_version("").is_err());
+    }
+
+    #[test]
+    fn compare_versions() {
+        let version = AnsibleVersion {
+            core_version: "2.16.0rc1".into(),
+            ..Default::default()
+        };
+
+        assert_eq!(version.core(), (2, 16, 0));
+        assert!(version.at_least(2, 12));
+        assert!(version.at_least(2, 16));
+        assert!(!version.at_least(2, 17));
+    }
+
+    #[test]
+    fn detect_unsupported_flags() {
+        let old = parse_version(VERSION_2_11).expect("parse version");
+        let new = parse_version(VERSION_2_15).expect("parse version");
+        let command: Vec<String> = vec![
+            "ansible-playbook".into(),
+            "--become-password-file".into(),
+            "/dev/null".into(),
+            "site.yml".into(),
+        ];
+
+        assert_eq!(
+            old.unsupported_flags(&command),
+            vec!["--become-password-file (requires ansible-core 2.12)"]
+        );
+        assert!(new.unsupported_flags(&command).is_empty());
+    }
+
+    #[test]
+    fn detect_version_once_per_binary() {
+        let dir = fake_ansible_dir(VERSION_2_15);
+        let playbook = AnsiblePlaybookCmd {
+            binary: format!("{}/ansible-playbook", dir),
+            ..Default::default()
+        };
+
+        let first = playbook.detect_version().expect("detect version");
+        let second = playbook.detect_version().expect("detect version");
+        let calls = fs::read_to_string(format!("{}/calls", dir)).expect("read calls");
+        fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+
+        assert_eq!(first, second);
+        assert_eq!(first.core_version, "2.15.5");
+        assert_eq!(calls, "--version\n");
+    }
+
+    #[test]
+    fn reject_unsupported_options() {
+        let dir = fake_ansible_dir(VERSION_2_11);
+        let mut playbook = AnsiblePlaybookCmd {
+            binary: format!("{}/ansible-playbook", dir),
+            playbooks: vec!["site.yml".into()],
+            privilege_escalation_options: AnsiblePrivilegeEscalationOptions {
+                become_password_file: "/dev/null".into(),
+                ..Default::default()
+            },
+            version_check: AnsibleVersionCheck::Reject,
+            ..Default::default()
+        };
+
+        let rejected = playbook.run().is_err();
+
+        playbook.version_check = AnsibleVersionCheck::Warn;
+        let mut child = playbook.run().expect("run playbook");
+        let warned = child.wait().expect("wait for playbook").success();
+        fs::remove_dir_all(&dir).expect("remove fake ansible dir");
+
+        assert!(rejected);
+        assert!(warned);
+        assert_eq!(
+            child.warnings,
+            vec![
+                "options not supported by the installed ansible: --become-password-file (requires ansible-core 2.12)"
+            ]
+        );
+    }
+}